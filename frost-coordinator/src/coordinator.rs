@@ -7,7 +7,7 @@ use frost_signer::{
     net::{Error as HttpNetError, Message, NetListen},
     signing_round::{
         DkgBegin, DkgPublicShare, MessageTypes, NonceRequest, NonceResponse, Signable,
-        SignatureShareRequest,
+        SignatureShareRequest, SigningContext,
     },
 };
 use hashbrown::HashSet;
@@ -39,6 +39,8 @@ pub enum Error {
     ConfigError(#[from] ConfigError),
     #[error("Received invalid signer message.")]
     InvalidSignerMessage,
+    #[error("Signers refused to sign: {0:?}")]
+    SigningRefused(Vec<u32>),
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -161,6 +163,7 @@ where
         let dkg_begin_message = Message {
             sig: dkg_begin.sign(&self.network_private_key).expect(""),
             msg: MessageTypes::DkgBegin(dkg_begin),
+            recipients: None,
         };
         println!("AB1.1");
         self.network.send_message(dkg_begin_message).await?;
@@ -179,6 +182,7 @@ where
         let dkg_private_begin_msg = Message {
             sig: dkg_begin.sign(&self.network_private_key).expect(""),
             msg: MessageTypes::DkgPrivateBegin(dkg_begin),
+            recipients: None,
         };
         self.network.send_message(dkg_private_begin_msg).await?;
         Ok(())
@@ -198,6 +202,7 @@ where
                 .sign(&self.network_private_key)
                 .expect("Failed to sign NonceRequest"),
             msg: MessageTypes::NonceRequest(nonce_request),
+            recipients: None,
         };
 
         debug!(
@@ -255,6 +260,7 @@ where
         &self,
         nonce_responses: &[NonceResponse],
         msg: &[u8],
+        context: Option<&SigningContext>,
     ) -> Result<(), Error> {
         let signature_share_request = SignatureShareRequest {
             dkg_id: self.current_dkg_id,
@@ -262,6 +268,7 @@ where
             correlation_id: 0,
             nonce_responses: nonce_responses.to_vec(),
             message: msg.to_vec(),
+            context: context.cloned(),
         };
 
         info!(
@@ -274,6 +281,7 @@ where
                 .sign(&self.network_private_key)
                 .expect("Failed to sign SignShareRequest"),
             msg: MessageTypes::SignShareRequest(signature_share_request),
+            recipients: None,
         };
 
         self.network
@@ -286,16 +294,22 @@ where
     async fn collect_signature_shares(&mut self) -> Result<(), Error> {
         // get the parties who responded with a nonce
         let mut signers: HashSet<u32> = HashSet::from_iter(self.public_nonces.keys().cloned());
+        let mut refused = vec![];
         while !signers.is_empty() {
             match self.wait_for_next_message().await?.msg {
                 MessageTypes::SignShareResponse(response) => {
                     if let Some(_party_id) = signers.take(&response.signer_id) {
-                        info!(
-                            "Insert signature shares for signer_id {}",
-                            &response.signer_id
-                        );
-                        self.signature_shares
-                            .insert(response.signer_id, response.signature_shares.clone());
+                        if response.approved {
+                            info!(
+                                "Insert signature shares for signer_id {}",
+                                &response.signer_id
+                            );
+                            self.signature_shares
+                                .insert(response.signer_id, response.signature_shares.clone());
+                        } else {
+                            warn!("Signer_id {} refused to sign", &response.signer_id);
+                            refused.push(response.signer_id);
+                        }
                     }
                     debug!(
                         "signature shares for {} received.  left to receive: {:?}",
@@ -308,11 +322,34 @@ where
                 }
             }
         }
+        // Every signer whose nonce went into the aggregate nonce must contribute a share
+        if !refused.is_empty() {
+            refused.sort_unstable();
+            return Err(Error::SigningRefused(refused));
+        }
         Ok(())
     }
 
-    #[allow(non_snake_case)]
     pub async fn sign_message(&mut self, msg: &[u8]) -> Result<(Signature, SchnorrProof), Error> {
+        self.sign(msg, None).await
+    }
+
+    /// Sign the sighash of a Bitcoin transaction input on behalf of an sBTC transaction, so signers
+    /// can derive the message from the transaction and check their vote on it
+    pub async fn sign_transaction_message(
+        &mut self,
+        msg: &[u8],
+        context: &SigningContext,
+    ) -> Result<(Signature, SchnorrProof), Error> {
+        self.sign(msg, Some(context)).await
+    }
+
+    #[allow(non_snake_case)]
+    async fn sign(
+        &mut self,
+        msg: &[u8],
+        context: Option<&SigningContext>,
+    ) -> Result<(Signature, SchnorrProof), Error> {
         debug!("Attempting to Sign Message");
         if self.aggregate_public_key == Point::default() {
             return Err(Error::NoAggregatePublicKey);
@@ -353,7 +390,8 @@ where
         let nonce_responses: Vec<NonceResponse> = self.public_nonces.values().cloned().collect();
 
         // request signature shares
-        self.request_signature_shares(&nonce_responses, msg).await?;
+        self.request_signature_shares(&nonce_responses, msg, context)
+            .await?;
         self.collect_signature_shares().await?;
 
         let nonces = nonce_responses
@@ -639,6 +677,8 @@ mod test {
             signer_key_ids.clone(),
             coordinator_private_key,
             relay_url.clone(),
+            None,
+            None,
         );
         let signer_configs = signer_private_keys
             .iter()
@@ -650,6 +690,8 @@ mod test {
                     signer_key_ids.clone(),
                     k.clone(),
                     relay_url.clone(),
+                    None,
+                    None,
                 )
             })
             .collect::<Vec<Config>>();
@@ -3,10 +3,10 @@ pub mod coordinator;
 use coordinator::{Coordinator, Error};
 use frost_signer::{
     config::Config,
-    net::{HttpNet, HttpNetListen},
+    net::{HttpNet, HttpNetListen, COORDINATOR_ID},
 };
 
-pub const DEVNET_COORDINATOR_ID: u32 = 0;
+pub const DEVNET_COORDINATOR_ID: u32 = COORDINATOR_ID;
 
 pub fn create_coordinator_from_path(
     path: impl AsRef<std::path::Path>,
@@ -16,7 +16,7 @@ pub fn create_coordinator_from_path(
 }
 
 pub fn create_coordinator(config: &Config) -> Result<Coordinator<HttpNetListen>, Error> {
-    let net: HttpNet = HttpNet::new(config.http_relay_url.clone());
+    let net: HttpNet = HttpNet::from_config(config)?;
     let net_listen: HttpNetListen = HttpNetListen::new(net, vec![]);
     let coordinator = Coordinator::new(DEVNET_COORDINATOR_ID, config, net_listen)?;
     Ok(coordinator)
@@ -53,6 +53,10 @@ struct RawSigners {
 #[derive(Clone, Deserialize, Default, Debug)]
 struct RawConfig {
     pub http_relay_url: String,
+    /// The relay channel shared by this signer set. Uses the relay's default channel if omitted.
+    pub http_relay_channel: Option<String>,
+    /// Path to the PEM encoded CA certificate the relay's TLS certificate must be issued by.
+    pub http_relay_ca_certificate: Option<String>,
     pub keys_threshold: u32,
     pub network_private_key: String,
     signers: Vec<RawSigners>,
@@ -114,6 +118,8 @@ pub struct PublicKeys {
 #[derive(Clone, Debug)]
 pub struct Config {
     pub http_relay_url: String,
+    pub http_relay_channel: Option<String>,
+    pub http_relay_ca_certificate: Option<String>,
     pub keys_threshold: u32,
     pub network_private_key: Scalar,
     pub public_keys: PublicKeys,
@@ -124,6 +130,7 @@ pub struct Config {
 }
 
 impl Config {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         keys_threshold: u32,
         coordinator_public_key: ecdsa::PublicKey,
@@ -131,12 +138,16 @@ impl Config {
         signer_key_ids: SignerKeyIds,
         network_private_key: Scalar,
         http_relay_url: String,
+        http_relay_channel: Option<String>,
+        http_relay_ca_certificate: Option<String>,
     ) -> Config {
         Self {
             keys_threshold,
             coordinator_public_key,
             network_private_key,
             http_relay_url,
+            http_relay_channel,
+            http_relay_ca_certificate,
             total_signers: public_keys.signers.len().try_into().unwrap(),
             total_keys: public_keys.key_ids.len().try_into().unwrap(),
             public_keys,
@@ -160,6 +171,8 @@ impl TryFrom<&RawConfig> for Config {
             raw_config.signer_key_ids(),
             raw_config.network_private_key()?,
             raw_config.http_relay_url.clone(),
+            raw_config.http_relay_channel.clone(),
+            raw_config.http_relay_ca_certificate.clone(),
         ))
     }
 }
@@ -15,7 +15,13 @@ async fn main() {
     match Config::from_path(&cli.config) {
         Ok(config) => {
             let mut signer = Signer::new(config, cli.id);
-            let net: HttpNet = HttpNet::new(signer.config.http_relay_url.clone());
+            let net: HttpNet = match HttpNet::from_config(&signer.config) {
+                Ok(net) => net,
+                Err(e) => {
+                    error!("An error occurred setting up the relay network: {}", e);
+                    return;
+                }
+            };
             let net_queue = HttpNetListen::new(net.clone(), vec![]);
             info!(
                 "{} signer id #{}",
@@ -5,12 +5,18 @@ use std::{fmt::Debug, time::Duration};
 use tokio::sync::Mutex;
 use tracing::{debug, warn};
 
-use crate::signing_round;
+use crate::{config::Config, signing_round};
+
+/// The relay node id the coordinator polls with.
+pub const COORDINATOR_ID: u32 = 0;
+
 // Message is the format over the wire
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Message {
     pub msg: signing_round::MessageTypes,
     pub sig: Vec<u8>,
+    /// The relay node ids this message is addressed to, or `None` to broadcast it to every node.
+    pub recipients: Option<Vec<u32>>,
 }
 
 // Http listen/poll with queue (requires mutable access, is configured by passing in HttpNet)
@@ -43,14 +49,56 @@ impl HttpNetListen {
 #[derive(Clone)]
 pub struct HttpNet {
     pub http_relay_url: String,
+    /// The relay channel to use, so several signer sets can share one relay.
+    pub http_relay_channel: Option<String>,
     connected: bool,
+    client: reqwest::Client,
 }
 
 impl HttpNet {
     pub fn new(http_relay_url: String) -> Self {
+        Self::with_channel(http_relay_url, None)
+    }
+
+    pub fn with_channel(http_relay_url: String, http_relay_channel: Option<String>) -> Self {
         HttpNet {
             http_relay_url,
+            http_relay_channel,
             connected: true,
+            client: reqwest::Client::default(),
+        }
+    }
+
+    /// Create the network described by the relay options of `config`.
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        let net = Self::with_channel(
+            config.http_relay_url.clone(),
+            config.http_relay_channel.clone(),
+        );
+        match &config.http_relay_ca_certificate {
+            Some(path) => net.with_ca_certificate(&std::fs::read(path)?),
+            None => Ok(net),
+        }
+    }
+
+    /// Only trust a relay whose certificate is issued by the PEM encoded `ca_certificate`,
+    /// instead of the built-in web roots.
+    pub fn with_ca_certificate(self, ca_certificate: &[u8]) -> Result<Self, Error> {
+        let certificate = reqwest::Certificate::from_pem(ca_certificate).map_err(Box::new)?;
+        let client = reqwest::Client::builder()
+            .use_rustls_tls()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(certificate)
+            .build()
+            .map_err(Box::new)?;
+        Ok(Self { client, ..self })
+    }
+
+    /// The URL of the relay channel this network sends to and polls from.
+    pub fn channel_url(&self) -> String {
+        match &self.http_relay_channel {
+            Some(channel) => url_with_channel(&self.http_relay_url, channel),
+            None => self.http_relay_url.clone(),
         }
     }
 }
@@ -70,9 +118,12 @@ impl NetListen for HttpNetListen {
     type Error = Error;
 
     async fn poll(&self, id: u32) {
-        let url = url_with_id(&self.net.lock().await.http_relay_url, id);
+        let (url, client) = {
+            let net = self.net.lock().await;
+            (url_with_id(&net.channel_url(), id), net.client.clone())
+        };
         debug!("poll {}", url);
-        match reqwest::get(&url).await {
+        match client.get(&url).send().await {
             Ok(response) => {
                 self.net.lock().await.connected = true;
                 if response.status() == 200 {
@@ -122,17 +173,19 @@ impl Net for HttpNet {
         // sign message
         let bytes = &bincode::serialize(&msg)?;
 
+        let url = &match &msg.recipients {
+            Some(recipients) => url_with_recipients(&self.channel_url(), recipients),
+            None => self.channel_url(),
+        };
+
         let notify = |_err, dur| {
-            debug!(
-                "Failed to connect to {}. Next attempt in {:?}",
-                &self.http_relay_url, dur
-            );
+            debug!("Failed to connect to {}. Next attempt in {:?}", url, dur);
         };
 
         let send_request = || async move {
             println!("Attempting to send request");
-            reqwest::Client::default()
-                .post(&self.http_relay_url)
+            self.client
+                .post(url)
                 .body(bytes.clone())
                 .header("Content-Length", bytes.len())
                 .send()
@@ -153,7 +206,7 @@ impl Net for HttpNet {
             &msg.msg,
             bytes.len(),
             &response,
-            self.http_relay_url
+            url
         );
         Ok(())
     }
@@ -167,6 +220,8 @@ pub enum Error {
     NetworkError(#[from] Box<reqwest::Error>),
     #[error("Failed to connect to network.")]
     Timeout,
+    #[error("IO Error: {0}")]
+    IOError(#[from] std::io::Error),
 }
 
 fn url_with_id(base: &str, id: u32) -> String {
@@ -174,3 +229,54 @@ fn url_with_id(base: &str, id: u32) -> String {
     url.push_str(&format!("?id={id}"));
     url
 }
+
+fn url_with_channel(base: &str, channel: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), channel)
+}
+
+fn url_with_recipients(base: &str, recipients: &[u32]) -> String {
+    let recipients: Vec<String> = recipients.iter().map(u32::to_string).collect();
+    let mut url = base.to_owned();
+    url.push_str(&format!("?to={}", recipients.join(",")));
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{url_with_channel, url_with_id, url_with_recipients, HttpNet};
+
+    #[test]
+    fn url_test() {
+        assert_eq!(
+            url_with_id("http://localhost:9776", 3),
+            "http://localhost:9776?id=3"
+        );
+        assert_eq!(
+            url_with_recipients("http://localhost:9776", &[0]),
+            "http://localhost:9776?to=0"
+        );
+        assert_eq!(
+            url_with_recipients("http://localhost:9776", &[1, 2, 3]),
+            "http://localhost:9776?to=1,2,3"
+        );
+        assert_eq!(
+            url_with_channel("http://localhost:9776/", "testnet"),
+            "http://localhost:9776/testnet"
+        );
+    }
+
+    #[test]
+    fn channel_url_test() {
+        let net = HttpNet::new("http://localhost:9776".to_string());
+        assert_eq!(net.channel_url(), "http://localhost:9776");
+        let net = HttpNet::with_channel(
+            "http://localhost:9776".to_string(),
+            Some("testnet".to_string()),
+        );
+        assert_eq!(net.channel_url(), "http://localhost:9776/testnet");
+        assert_eq!(
+            url_with_id(&net.channel_url(), 1),
+            "http://localhost:9776/testnet?id=1"
+        );
+    }
+}
@@ -1,22 +1,44 @@
 use crate::config::{Config, PublicKeys};
-use crate::net::{Error as HttpNetError, Message, NetListen};
-use crate::signing_round::{Error as SigningRoundError, MessageTypes, Signable, SigningRound};
+use crate::net::{Error as HttpNetError, Message, NetListen, COORDINATOR_ID};
+use crate::signing_round::{
+    Error as SigningRoundError, MessageTypes, Signable, SignatureShareRequest, SigningRound,
+};
+use async_trait::async_trait;
 use p256k1::ecdsa;
+use std::sync::Arc;
 use std::time;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{UnboundedReceiver as Receiver, UnboundedSender as Sender};
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
+
+/// Decides whether a signer contributes its signature shares to a signing round
+#[async_trait]
+pub trait SignApprover: Send + Sync {
+    async fn approve(&self, request: &SignatureShareRequest) -> bool;
+}
 
 // on-disk format for frost save data
 #[derive(Clone)]
 pub struct Signer {
     pub config: Config,
     pub signer_id: u32,
+    approver: Option<Arc<dyn SignApprover>>,
 }
 
 impl Signer {
     pub fn new(config: Config, signer_id: u32) -> Self {
-        Self { config, signer_id }
+        Self {
+            config,
+            signer_id,
+            approver: None,
+        }
+    }
+
+    /// Consult the given approver before answering any signature share request.
+    /// Without one, every request is signed.
+    pub fn with_approver(mut self, approver: impl SignApprover + 'static) -> Self {
+        self.approver = Some(Arc::new(approver));
+        self
     }
 
     pub async fn start_p2p_async<Net: NetListen>(&mut self, network: Net) -> Result<(), Error>
@@ -52,13 +74,31 @@ impl Signer {
         Error: From<<Net as NetListen>::Error>,
     {
         let network_private_key = self.config.network_private_key;
+        let signer_ids: Vec<u32> = self.config.public_keys.signers.keys().copied().collect();
         let mut round = SigningRound::from(self);
         loop {
             // Retrieve a message from the coordinator
             let inbound = rx.recv().await.ok_or(Error::RecvStreamEnded)?;
-            let outbounds = round.process(inbound.msg)?;
+            let approved = match (&inbound.msg, &self.approver) {
+                (MessageTypes::SignShareRequest(request), Some(approver)) => {
+                    approver.approve(request).await
+                }
+                _ => true,
+            };
+            let outbounds = match inbound.msg {
+                MessageTypes::SignShareRequest(request) if !approved => {
+                    info!(
+                        "SignShareRequest sign_id #{} for txid {:?} not approved",
+                        request.sign_id,
+                        request.context.as_ref().map(|context| &context.txid)
+                    );
+                    round.refuse_sign_share_request(&request)
+                }
+                msg => round.process(msg)?,
+            };
             for out in outbounds {
                 let msg = Message {
+                    recipients: recipients(&out, &signer_ids),
                     msg: out.clone(),
                     sig: match out {
                         MessageTypes::DkgBegin(msg) | MessageTypes::DkgPrivateBegin(msg) => msg
@@ -122,6 +162,21 @@ impl From<mpsc::error::SendError<Message>> for Error {
     }
 }
 
+/// Select the relay nodes that need to see a message produced by a signing round.
+///
+/// Private shares are only of use to the other signers, and the responses to the coordinator's
+/// requests are ignored by every signer, so neither needs to be broadcast.
+fn recipients(msg: &MessageTypes, signer_ids: &[u32]) -> Option<Vec<u32>> {
+    match msg {
+        MessageTypes::DkgPrivateShares(_) => Some(signer_ids.to_vec()),
+        MessageTypes::DkgEnd(_)
+        | MessageTypes::DkgPublicEnd(_)
+        | MessageTypes::NonceResponse(_)
+        | MessageTypes::SignShareResponse(_) => Some(vec![COORDINATOR_ID]),
+        _ => None,
+    }
+}
+
 async fn poll_loop<Net: NetListen>(
     net: Net,
     tx: Sender<Message>,
@@ -334,10 +389,15 @@ mod test {
         let dkg_begin = Message {
             msg,
             sig: sig.clone(),
+            recipients: None,
         };
         // DkgPrivateBegin
         let msg = MessageTypes::DkgPrivateBegin(inner);
-        let dkg_private_begin = Message { msg, sig };
+        let dkg_private_begin = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
 
         // Check with correct public key
         assert!(verify_msg(
@@ -378,11 +438,13 @@ mod test {
         let dkg_end = Message {
             msg,
             sig: sig.clone(),
+            recipients: None,
         };
         let msg = MessageTypes::DkgPublicEnd(inner.clone());
         let dkg_public_end = Message {
             msg: msg.clone(),
             sig,
+            recipients: None,
         };
 
         assert!(verify_msg(
@@ -402,9 +464,14 @@ mod test {
         let dkg_end = Message {
             msg,
             sig: sig.clone(),
+            recipients: None,
         };
         let msg = MessageTypes::DkgPublicEnd(inner);
-        let dkg_public_end = Message { msg, sig };
+        let dkg_public_end = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
 
         assert!(!verify_msg(
             &dkg_end,
@@ -434,9 +501,14 @@ mod test {
         let dkg_end = Message {
             msg,
             sig: sig.clone(),
+            recipients: None,
         };
         let msg = MessageTypes::DkgPublicEnd(invalid_inner.clone());
-        let dkg_public_end = Message { msg, sig: sig };
+        let dkg_public_end = Message {
+            msg,
+            sig: sig,
+            recipients: None,
+        };
 
         assert!(!verify_msg(
             &dkg_end,
@@ -470,6 +542,7 @@ mod test {
         let message = Message {
             msg: msg.clone(),
             sig,
+            recipients: None,
         };
         assert!(verify_msg(
             &message,
@@ -481,7 +554,11 @@ mod test {
         let sig = inner.sign(&config.sec_keys[1]).unwrap();
         let msg = MessageTypes::DkgPublicShare(inner);
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &message,
             &config.public_keys,
@@ -506,7 +583,11 @@ mod test {
         let msg = MessageTypes::DkgPublicShare(inner.clone());
         let sig = inner.sign(&config.sec_keys[0]).unwrap();
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &message,
             &config.public_keys,
@@ -528,6 +609,7 @@ mod test {
         let message = Message {
             msg: msg.clone(),
             sig,
+            recipients: None,
         };
         assert!(verify_msg(
             &message,
@@ -537,7 +619,11 @@ mod test {
 
         // Let us sign with the wrong sec key...
         let sig = inner.sign(&config.sec_keys[1]).unwrap();
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
 
         assert!(!verify_msg(
             &message,
@@ -557,7 +643,11 @@ mod test {
         let sig = inner.sign(&config.sec_keys[0]).unwrap();
         let msg = MessageTypes::DkgPrivateShares(inner);
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &message,
             &config.public_keys,
@@ -578,7 +668,11 @@ mod test {
         let sig = inner.sign(&config.coordinator_sec_key).unwrap();
         let msg = MessageTypes::NonceRequest(inner);
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(verify_msg(
             &message,
             &config.public_keys,
@@ -609,6 +703,7 @@ mod test {
         let message = Message {
             msg: msg.clone(),
             sig,
+            recipients: None,
         };
         assert!(verify_msg(
             &message,
@@ -619,7 +714,11 @@ mod test {
         // Let's sign with the wrong sec key...
         let sig = inner.sign(&config.sec_keys[1]).unwrap();
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &message,
             &config.public_keys,
@@ -641,7 +740,11 @@ mod test {
         let sig = inner.sign(&config.sec_keys[0]).unwrap();
         let msg = MessageTypes::NonceResponse(inner);
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &message,
             &config.public_keys,
@@ -668,11 +771,16 @@ mod test {
                 }],
             }],
             message: vec![],
+            context: None,
         };
         let sig = inner.sign(&config.coordinator_sec_key).unwrap();
         let msg = MessageTypes::SignShareRequest(inner);
 
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(verify_msg(
             &message,
             &config.public_keys,
@@ -696,6 +804,7 @@ mod test {
             sign_id: 0,
             correlation_id: 0,
             signer_id: 1,
+            approved: true,
             signature_shares: vec![],
         };
         let sig = inner.sign(&config.sec_keys[0]).unwrap();
@@ -704,6 +813,7 @@ mod test {
         let message = Message {
             msg: msg.clone(),
             sig,
+            recipients: None,
         };
         assert!(verify_msg(
             &message,
@@ -713,7 +823,11 @@ mod test {
 
         // Let's sign with the wrong sec key...
         let sig = inner.sign(&config.sec_keys[1]).unwrap();
-        let message = Message { msg, sig };
+        let message = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &message,
             &config.public_keys,
@@ -729,12 +843,17 @@ mod test {
             sign_id: 0,
             correlation_id: 0,
             signer_id: 10,
+            approved: true,
             signature_shares: vec![],
         };
         let sig = inner.sign(&config.sec_keys[0]).unwrap();
         let msg = MessageTypes::SignShareResponse(inner);
 
-        let sign_share_response = Message { msg, sig };
+        let sign_share_response = Message {
+            msg,
+            sig,
+            recipients: None,
+        };
         assert!(!verify_msg(
             &sign_share_response,
             &config.public_keys,
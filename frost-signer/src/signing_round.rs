@@ -259,6 +259,32 @@ impl Signable for NonceResponse {
     }
 }
 
+/// The Bitcoin transaction input whose sighash a signature share request signs, so signers can
+/// derive the message themselves instead of trusting the coordinator
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SigningContext {
+    /// The id of the sBTC operation the transaction fulfills
+    pub txid: String,
+    /// The consensus encoded unsigned Bitcoin transaction
+    pub transaction: Vec<u8>,
+    /// The consensus encoded outputs spent by each input of the transaction
+    pub prevouts: Vec<Vec<u8>>,
+    /// The index of the input whose taproot key spend sighash is the message
+    pub input_index: u64,
+}
+
+impl Signable for SigningContext {
+    fn hash(&self, hasher: &mut Sha256) {
+        hasher.update("SIGNING_CONTEXT".as_bytes());
+        hasher.update(self.txid.as_bytes());
+        hasher.update(self.transaction.as_slice());
+        for prevout in &self.prevouts {
+            hasher.update(prevout.as_slice());
+        }
+        hasher.update(self.input_index.to_be_bytes());
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SignatureShareRequest {
     pub dkg_id: u64,
@@ -266,6 +292,8 @@ pub struct SignatureShareRequest {
     pub correlation_id: u64,
     pub nonce_responses: Vec<NonceResponse>,
     pub message: Vec<u8>,
+    /// The sBTC transaction the message signs for, if any
+    pub context: Option<SigningContext>,
 }
 
 impl Signable for SignatureShareRequest {
@@ -280,6 +308,10 @@ impl Signable for SignatureShareRequest {
         }
 
         hasher.update(self.message.as_slice());
+
+        if let Some(context) = &self.context {
+            context.hash(hasher);
+        }
     }
 }
 
@@ -289,6 +321,8 @@ pub struct SignatureShareResponse {
     pub sign_id: u64,
     pub correlation_id: u64,
     pub signer_id: u32,
+    /// Whether the signer approved signing the message. Refusals carry no signature shares.
+    pub approved: bool,
     pub signature_shares: Vec<SignatureShare>,
 }
 
@@ -299,6 +333,7 @@ impl Signable for SignatureShareResponse {
         hasher.update(self.sign_id.to_be_bytes());
         hasher.update(self.correlation_id.to_be_bytes());
         hasher.update(self.signer_id.to_be_bytes());
+        hasher.update([u8::from(self.approved)]);
 
         for signature_share in &self.signature_shares {
             hasher.update(signature_share.id.to_be_bytes());
@@ -576,6 +611,7 @@ impl SigningRound {
                     sign_id: sign_request.sign_id,
                     correlation_id: sign_request.correlation_id,
                     signer_id: *signer_id,
+                    approved: true,
                     signature_shares,
                 };
 
@@ -594,6 +630,36 @@ impl SigningRound {
         Ok(msgs)
     }
 
+    /// Answer a signature share request without contributing any signature shares.
+    pub fn refuse_sign_share_request(
+        &self,
+        sign_request: &SignatureShareRequest,
+    ) -> Vec<MessageTypes> {
+        let signer_id = self.signer.signer_id;
+        if !sign_request
+            .nonce_responses
+            .iter()
+            .any(|nr| nr.signer_id == signer_id)
+        {
+            debug!("SignShareRequest for {} dropped.", signer_id);
+            return vec![];
+        }
+
+        info!(
+            "Refusing SignatureShareRequest sign_id #{} for signer_id {}",
+            sign_request.sign_id, signer_id
+        );
+        let response = SignatureShareResponse {
+            dkg_id: sign_request.dkg_id,
+            sign_id: sign_request.sign_id,
+            correlation_id: sign_request.correlation_id,
+            signer_id,
+            approved: false,
+            signature_shares: vec![],
+        };
+        vec![MessageTypes::SignShareResponse(response)]
+    }
+
     fn dkg_begin(&mut self, dkg_begin: DkgBegin) -> Result<Vec<MessageTypes>, Error> {
         let mut rng = OsRng;
 
@@ -49,6 +49,7 @@ fn signature_share() {
             }],
         }],
         message: vec![],
+        context: None,
     };
 
     let msg_share = MessageTypes::SignShareRequest(share);
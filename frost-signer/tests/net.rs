@@ -6,6 +6,7 @@ async fn receive_msg() {
     let m1 = Message {
         msg: MessageTypes::DkgBegin(DkgBegin { dkg_id: 0 }),
         sig: vec![0u8; 64],
+        recipients: None,
     };
 
     let stacks_node_url = "http://localhost:9775".to_owned();
@@ -20,3 +21,66 @@ async fn receive_msg() {
         None => {}
     }
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tls_relay() {
+    use relay_server::{tls_config, Server, Timeouts};
+
+    let ca = rcgen::Certificate::from_params({
+        let mut params = rcgen::CertificateParams::new(vec![]);
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        params
+    })
+    .unwrap();
+    let relay_cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let dir = tempdir::TempDir::new("frost-signer-tls").unwrap();
+    let cert_path = dir.path().join("cert.pem");
+    let key_path = dir.path().join("key.pem");
+    std::fs::write(
+        &cert_path,
+        relay_cert.serialize_pem_with_signer(&ca).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(&key_path, relay_cert.serialize_private_key_pem()).unwrap();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let config = tls_config(&cert_path, &key_path).unwrap();
+    tokio::spawn(Server::run_tls(
+        format!("127.0.0.1:{port}"),
+        Timeouts::default(),
+        config,
+    ));
+    let relay_url = format!("https://localhost:{port}");
+
+    let net = HttpNet::new(relay_url.clone())
+        .with_ca_certificate(ca.serialize_pem().unwrap().as_bytes())
+        .unwrap();
+    let net_listen = HttpNetListen::new(net, vec![]);
+    let message = Message {
+        msg: MessageTypes::DkgBegin(DkgBegin { dkg_id: 7 }),
+        sig: vec![0u8; 64],
+        recipients: None,
+    };
+    net_listen.send_message(message).await.unwrap();
+    net_listen.poll(1).await;
+    match net_listen.next_message().await {
+        Some(Message {
+            msg: MessageTypes::DkgBegin(DkgBegin { dkg_id }),
+            ..
+        }) => assert_eq!(dkg_id, 7),
+        other => panic!("unexpected message {other:?}"),
+    }
+
+    // a relay certificate which isn't issued by the pinned CA is rejected.
+    let other_ca = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let untrusting = HttpNet::new(relay_url)
+        .with_ca_certificate(other_ca.serialize_pem().unwrap().as_bytes())
+        .unwrap();
+    let untrusting_listen = HttpNetListen::new(untrusting, vec![]);
+    untrusting_listen.poll(2).await;
+    assert!(untrusting_listen.next_message().await.is_none());
+}
@@ -5,19 +5,23 @@ use tokio::io::BufReader;
 
 use relay_server::{ProxyState, State};
 use tokio::net::TcpStream;
-use yarpc::http::{Call, Request, Response};
+use yarpc::http::{Call, Method, Request, Response};
 const ADDR: &str = "127.0.0.1:9776";
+const POLLERS: u32 = 200;
 
-struct RemoteServer();
+/// A client which sends every request over one persistent connection.
+struct RemoteServer(BufReader<TcpStream>);
+
+impl RemoteServer {
+    async fn connect() -> Self {
+        Self(BufReader::new(TcpStream::connect(ADDR).await.unwrap()))
+    }
+}
 
 #[async_trait]
 impl Call for RemoteServer {
     async fn call(&mut self, request: Request) -> Result<Response, Error> {
-        // note: `relay-server` doesn't support multiple requests in one connection
-        // so we create a new connection every time when we send a request.
-        BufReader::new(TcpStream::connect(ADDR).await.unwrap())
-            .call(request)
-            .await
+        self.0.call(request).await
     }
 }
 
@@ -28,7 +32,7 @@ async fn main() {
         tokio::time::sleep(Duration::from_millis(10)).await;
     }
     //
-    let mut state = ProxyState(RemoteServer());
+    let mut state = ProxyState(RemoteServer::connect().await);
     //
     assert!(state.get(1.to_string()).await.unwrap().is_empty());
     assert!(state.get(3.to_string()).await.unwrap().is_empty());
@@ -72,5 +76,47 @@ async fn main() {
         "Msg # 2".as_bytes().to_vec(),
         state.get(4.to_string()).await.unwrap()
     );
+    load_test().await;
     println!("passed");
 }
+
+/// Many nodes poll concurrently while another client holds a connection open without sending
+/// anything, which must not stop the server from answering the others.
+async fn load_test() {
+    let _stalled = TcpStream::connect(ADDR).await.unwrap();
+    RemoteServer::connect()
+        .await
+        .call(Request::new(
+            Method::POST,
+            "/load-test".to_string(),
+            Default::default(),
+            "Load".as_bytes().to_vec(),
+        ))
+        .await
+        .unwrap();
+    let pollers: Vec<_> = (0..POLLERS)
+        .map(|id| {
+            tokio::spawn(async move {
+                RemoteServer::connect()
+                    .await
+                    .call(Request::new(
+                        Method::GET,
+                        format!("/load-test?id={id}"),
+                        Default::default(),
+                        Default::default(),
+                    ))
+                    .await
+                    .unwrap()
+                    .content
+            })
+        })
+        .collect();
+    let all_polled = async {
+        for poller in pollers {
+            assert_eq!("Load".as_bytes().to_vec(), poller.await.unwrap());
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), all_polled)
+        .await
+        .expect("pollers were blocked");
+}
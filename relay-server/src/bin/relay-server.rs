@@ -1,10 +1,22 @@
+use std::time::Duration;
+
 use clap::Parser;
-use relay_server::Server;
+use relay_server::{tls_config, Server, Timeouts};
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     let args = Cli::parse();
-    Server::run(&args.listen).await;
+    let timeouts = Timeouts {
+        idle: Duration::from_secs(args.idle_timeout),
+        request: Duration::from_secs(args.request_timeout),
+    };
+    match (args.tls_cert, args.tls_key) {
+        (Some(cert), Some(key)) => {
+            let config = tls_config(&cert, &key).expect("failed to load the TLS certificate");
+            Server::run_tls(&args.listen, timeouts, config).await
+        }
+        _ => Server::run_with_timeouts(&args.listen, timeouts).await,
+    }
 }
 
 #[derive(Parser)]
@@ -12,4 +24,16 @@ struct Cli {
     /// Where to listen for incoming connections
     #[clap(short, long, default_value = "127.0.0.1:9776")]
     listen: String,
+    /// Seconds a connection may stay open without sending a request
+    #[clap(long, default_value_t = 30)]
+    idle_timeout: u64,
+    /// Seconds a client has to send a request and receive its response
+    #[clap(long, default_value_t = 10)]
+    request_timeout: u64,
+    /// PEM encoded certificate chain. Serves HTTPS instead of HTTP when given with --tls-key
+    #[clap(long, requires = "tls_key")]
+    tls_cert: Option<String>,
+    /// PEM encoded private key of the certificate given with --tls-cert
+    #[clap(long, requires = "tls_cert")]
+    tls_key: Option<String>,
 }
@@ -2,8 +2,10 @@ mod mem_state;
 mod proxy_state;
 mod server;
 mod state;
+mod tls;
 
 pub use mem_state::MemState;
 pub use proxy_state::ProxyState;
-pub use server::Server;
+pub use server::{Server, Timeouts, MAX_CHANNELS};
 pub use state::State;
+pub use tls::tls_config;
@@ -1,5 +1,8 @@
 use async_trait::async_trait;
-use std::{collections::HashMap, io::Error};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Error,
+};
 
 use crate::state::State;
 
@@ -21,7 +24,23 @@ use crate::state::State;
 pub struct MemState {
     /// The value for this map is an index for the last read message for this node.
     highwaters: HashMap<String, usize>,
-    queue: Vec<Vec<u8>>,
+    queue: Vec<Envelope>,
+}
+
+/// A stored message together with the nodes it is addressed to.
+struct Envelope {
+    /// `None` means the message is broadcast to every node.
+    recipients: Option<HashSet<String>>,
+    msg: Vec<u8>,
+}
+
+impl Envelope {
+    fn is_for(&self, node_id: &str) -> bool {
+        match &self.recipients {
+            Some(recipients) => recipients.contains(node_id),
+            None => true,
+        }
+    }
 }
 
 #[async_trait]
@@ -31,16 +50,35 @@ impl State for MemState {
             .highwaters
             .get(&node_id)
             .map_or(0, |last_read| *last_read + 1);
-        let result = self.queue.get(first_unread);
-        Ok(if let Some(r) = result {
-            self.highwaters.insert(node_id, first_unread);
-            r.clone()
+        let result = self
+            .queue
+            .iter()
+            .enumerate()
+            .skip(first_unread)
+            .find(|(_, envelope)| envelope.is_for(&node_id));
+        Ok(if let Some((index, envelope)) = result {
+            self.highwaters.insert(node_id, index);
+            envelope.msg.clone()
         } else {
+            // Skip messages addressed to other nodes so they are not scanned again.
+            if first_unread < self.queue.len() {
+                self.highwaters.insert(node_id, self.queue.len() - 1);
+            }
             Vec::default()
         })
     }
     async fn post(&mut self, msg: Vec<u8>) -> Result<(), Error> {
-        self.queue.push(msg);
+        self.queue.push(Envelope {
+            recipients: None,
+            msg,
+        });
+        Ok(())
+    }
+    async fn post_to(&mut self, msg: Vec<u8>, recipients: Vec<String>) -> Result<(), Error> {
+        self.queue.push(Envelope {
+            recipients: Some(recipients.into_iter().collect()),
+            msg,
+        });
         Ok(())
     }
 }
@@ -95,4 +133,57 @@ mod tests {
             state.get(4.to_string()).await.unwrap()
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn post_to_test() {
+        let mut state = MemState::default();
+        state
+            .post_to("Msg # 0".as_bytes().to_vec(), vec![1.to_string()])
+            .await
+            .unwrap();
+        state.post("Msg # 1".as_bytes().to_vec()).await.unwrap();
+        state
+            .post_to(
+                "Msg # 2".as_bytes().to_vec(),
+                vec![1.to_string(), 2.to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            "Msg # 0".as_bytes().to_vec(),
+            state.get(1.to_string()).await.unwrap()
+        );
+        assert_eq!(
+            "Msg # 1".as_bytes().to_vec(),
+            state.get(1.to_string()).await.unwrap()
+        );
+        assert_eq!(
+            "Msg # 2".as_bytes().to_vec(),
+            state.get(1.to_string()).await.unwrap()
+        );
+        assert!(state.get(1.to_string()).await.unwrap().is_empty());
+        assert_eq!(
+            "Msg # 1".as_bytes().to_vec(),
+            state.get(2.to_string()).await.unwrap()
+        );
+        assert_eq!(
+            "Msg # 2".as_bytes().to_vec(),
+            state.get(2.to_string()).await.unwrap()
+        );
+        assert_eq!(
+            "Msg # 1".as_bytes().to_vec(),
+            state.get(3.to_string()).await.unwrap()
+        );
+        assert!(state.get(3.to_string()).await.unwrap().is_empty());
+        // a node doesn't receive messages addressed to others that were posted after its last read
+        state
+            .post_to("Msg # 3".as_bytes().to_vec(), vec![2.to_string()])
+            .await
+            .unwrap();
+        assert!(state.get(3.to_string()).await.unwrap().is_empty());
+        assert_eq!(
+            "Msg # 3".as_bytes().to_vec(),
+            state.get(2.to_string()).await.unwrap()
+        );
+    }
 }
@@ -33,6 +33,18 @@ impl<T: Call> State for ProxyState<T> {
             .await?;
         Ok(())
     }
+
+    async fn post_to(&mut self, msg: Vec<u8>, recipients: Vec<String>) -> Result<(), Error> {
+        self.0
+            .call(Request::new(
+                Method::POST,
+                format!("/?to={}", recipients.join(",")),
+                Default::default(),
+                msg,
+            ))
+            .await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -86,4 +98,26 @@ mod tests {
             state.get(4.to_string()).await.unwrap()
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn post_to_test() {
+        let mut state = ProxyState(Server::default());
+        state
+            .post_to("Msg # 0".as_bytes().to_vec(), vec![2.to_string()])
+            .await
+            .unwrap();
+        state.post("Msg # 1".as_bytes().to_vec()).await.unwrap();
+        assert_eq!(
+            "Msg # 1".as_bytes().to_vec(),
+            state.get(1.to_string()).await.unwrap()
+        );
+        assert_eq!(
+            "Msg # 0".as_bytes().to_vec(),
+            state.get(2.to_string()).await.unwrap()
+        );
+        assert_eq!(
+            "Msg # 1".as_bytes().to_vec(),
+            state.get(2.to_string()).await.unwrap()
+        );
+    }
 }
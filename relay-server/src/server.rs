@@ -1,8 +1,13 @@
 use async_trait::async_trait;
-use std::io::{Cursor, Error};
-use std::net::SocketAddr;
-use tokio::io::BufReader;
+use std::collections::HashMap;
+use std::io::{Cursor, Error, ErrorKind};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+use tokio::time::{error::Elapsed, timeout};
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
 
 use tokio::net::TcpListener;
 
@@ -43,48 +48,180 @@ use crate::{mem_state::MemState, state::State};
 ///     }
 /// }
 /// ```
+///
+/// Messages are kept in separate channels, named by the path of the request URL, so that
+/// several independent groups of nodes can share one server. For example, `/testnet?id=1`
+/// reads from the `testnet` channel while `/?id=1` reads from the default, unnamed, channel.
+/// At most [`MAX_CHANNELS`] channels are created, posting to a new channel beyond that fails.
 #[derive(Default)]
-pub struct Server(MemState);
+pub struct Server(HashMap<String, MemState>);
+
+/// The maximum number of channels a server keeps, so clients can't grow its memory without
+/// bound by posting to arbitrary paths.
+pub const MAX_CHANNELS: usize = 256;
+
+/// Limits on how long a connection may hold on to the server.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    /// How long a connection may stay open without starting a request.
+    pub idle: Duration,
+    /// How long reading a request and writing its response may take once the request started.
+    pub request: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            idle: Duration::from_secs(30),
+            request: Duration::from_secs(10),
+        }
+    }
+}
 
 impl Server {
     pub async fn run<T: ToSocketAddrs>(addr: T) {
+        Self::run_with_timeouts(addr, Timeouts::default()).await
+    }
+
+    /// Listens on `addr` and serves every connection on its own task, so a slow or stalled
+    /// client doesn't hold up the others. Connections are kept alive for further requests
+    /// unless the client asks for them to be closed.
+    pub async fn run_with_timeouts<T: ToSocketAddrs>(addr: T, timeouts: Timeouts) {
+        Self::serve(addr, timeouts, None).await
+    }
+
+    /// Like [`Server::run_with_timeouts`], but terminates TLS on every connection.
+    pub async fn run_tls<T: ToSocketAddrs>(
+        addr: T,
+        timeouts: Timeouts,
+        tls_config: Arc<ServerConfig>,
+    ) {
+        Self::serve(addr, timeouts, Some(TlsAcceptor::from(tls_config))).await
+    }
+
+    async fn serve<T: ToSocketAddrs>(addr: T, timeouts: Timeouts, tls: Option<TlsAcceptor>) {
         let listener = TcpListener::bind(addr).await.unwrap();
         async fn handle_stream(
-            stream: std::io::Result<(TcpStream, SocketAddr)>,
-            server: &mut Server,
+            stream: impl AsyncRead + AsyncWrite + Unpin + Send,
+            server: &Mutex<Server>,
+            timeouts: Timeouts,
         ) -> Result<(), Error> {
-            let (stream, _) = stream?;
-            server.update(&mut BufReader::new(stream)).await?;
-            Ok(())
+            let mut stream = BufReader::new(stream);
+            loop {
+                // wait for the client to start sending a request.
+                let received = timeout(timeouts.idle, stream.fill_buf())
+                    .await
+                    .map_err(timed_out)??;
+                if received.is_empty() {
+                    // the client closed the connection.
+                    return Ok(());
+                }
+                let keep_alive = timeout(timeouts.request, async {
+                    let request = Request::read(&mut stream).await?;
+                    let keep_alive = request.keep_alive();
+                    // the lock is only held while the state is updated, never during IO.
+                    let mut response = server.lock().await.respond(request).await?;
+                    if !keep_alive {
+                        response
+                            .headers
+                            .insert("connection".to_string(), "close".to_string());
+                    }
+                    response.write(&mut stream).await?;
+                    Ok::<_, Error>(keep_alive)
+                })
+                .await
+                .map_err(timed_out)??;
+                if !keep_alive {
+                    return Ok(());
+                }
+            }
         }
 
-        let mut server = Server::default();
+        async fn handle_connection(
+            stream: TcpStream,
+            tls: Option<TlsAcceptor>,
+            server: &Mutex<Server>,
+            timeouts: Timeouts,
+        ) -> Result<(), Error> {
+            match tls {
+                Some(tls) => {
+                    let stream = timeout(timeouts.request, tls.accept(stream))
+                        .await
+                        .map_err(timed_out)??;
+                    handle_stream(stream, server, timeouts).await
+                }
+                None => handle_stream(stream, server, timeouts).await,
+            }
+        }
+
+        let server = Arc::new(Mutex::new(Server::default()));
         loop {
-            let maybe_stream = listener.accept().await;
-            println!("Received stream: {maybe_stream:?}");
-            if let Err(e) = handle_stream(maybe_stream, &mut server).await {
-                eprintln!("IO error: {e}");
+            match listener.accept().await {
+                Ok((stream, address)) => {
+                    println!("Received stream: {address}");
+                    let server = server.clone();
+                    let tls = tls.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, tls, &server, timeouts).await {
+                            eprintln!("IO error: {e}");
+                        }
+                    });
+                }
+                Err(e) => eprintln!("IO error: {e}"),
             }
         }
     }
 
     pub async fn update(&mut self, io: &mut impl IoStream) -> Result<(), Error> {
         let request = Request::read(io).await?;
+        let response = self.respond(request).await?;
+        response.write(io).await?;
+        Ok(())
+    }
+
+    /// Applies a request to the state and returns the response to it.
+    pub async fn respond(&mut self, request: Request) -> Result<Response, Error> {
+        let channel = request.url.url_path().trim_matches('/').to_string();
 
         let content = match request.method {
             Method::GET => {
                 let query = *request.url.url_query().get("id").to_io_result()?;
-                self.0.get(query.to_string()).await?
+                // reading never creates a channel, so arbitrary paths can't grow the state.
+                match self.0.get_mut(&channel) {
+                    Some(state) => state.get(query.to_string()).await?,
+                    None => Vec::default(),
+                }
             }
             Method::POST => {
-                self.0.post(request.content).await?;
+                if !self.0.contains_key(&channel) && self.0.len() >= MAX_CHANNELS {
+                    return Ok(Response::new(
+                        503,
+                        "Service Unavailable".to_string(),
+                        Default::default(),
+                        Default::default(),
+                    ));
+                }
+                let state = self.0.entry(channel).or_default();
+                match request.url.url_query().get("to") {
+                    Some(to) => {
+                        let recipients = to
+                            .split(',')
+                            .filter(|id| !id.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                        state.post_to(request.content, recipients).await?
+                    }
+                    None => state.post(request.content).await?,
+                }
                 Vec::default()
             }
         };
-        let response = Response::new(200, "OK".to_string(), Default::default(), content);
-        response.write(io).await?;
-        println!("SERVER RESPONSE: {response:?}");
-        Ok(())
+        Ok(Response::new(
+            200,
+            "OK".to_string(),
+            Default::default(),
+            content,
+        ))
     }
     async fn raw_call(&mut self, msg: &[u8]) -> Result<Vec<u8>, Error> {
         let mut result = Vec::default();
@@ -95,6 +232,10 @@ impl Server {
     }
 }
 
+fn timed_out(e: Elapsed) -> Error {
+    Error::new(ErrorKind::TimedOut, e)
+}
+
 #[async_trait]
 impl Call for Server {
     async fn call(&mut self, request: Request) -> Result<Response, Error> {
@@ -125,6 +266,7 @@ mod test {
             let response = server.raw_call(REQUEST.as_bytes()).await.unwrap();
             const RESPONSE: &str = "\
                 HTTP/1.1 200 OK\r\n\
+                content-length:0\r\n\
                 \r\n";
             assert_eq!(from_utf8(&response).unwrap(), RESPONSE);
         }
@@ -147,9 +289,62 @@ mod test {
             let response = server.raw_call(REQUEST.as_bytes()).await.unwrap();
             const RESPONSE: &str = "\
                 HTTP/1.1 200 OK\r\n\
+                content-length:0\r\n\
                 \r\n";
             assert_eq!(from_utf8(&response).unwrap(), RESPONSE);
         }
+        {
+            const REQUEST: &str = "\
+                POST /?to=y,z HTTP/1.1\r\n\
+                Content-Length: 6\r\n\
+                \r\n\
+                Hello?";
+            server.raw_call(REQUEST.as_bytes()).await.unwrap();
+        }
+        {
+            const REQUEST: &str = "\
+                GET /?id=x HTTP/1.1\r\n\
+                \r\n";
+            let response = server.raw_call(REQUEST.as_bytes()).await.unwrap();
+            const RESPONSE: &str = "\
+                HTTP/1.1 200 OK\r\n\
+                content-length:0\r\n\
+                \r\n";
+            assert_eq!(from_utf8(&response).unwrap(), RESPONSE);
+        }
+        {
+            const REQUEST: &str = "\
+                GET /?id=z HTTP/1.1\r\n\
+                \r\n";
+            let response = server.raw_call(REQUEST.as_bytes()).await.unwrap();
+            assert_eq!(
+                from_utf8(&response).unwrap(),
+                "HTTP/1.1 200 OK\r\ncontent-length:6\r\n\r\nHello!"
+            );
+            let response = server.raw_call(REQUEST.as_bytes()).await.unwrap();
+            assert_eq!(
+                from_utf8(&response).unwrap(),
+                "HTTP/1.1 200 OK\r\ncontent-length:6\r\n\r\nHello?"
+            );
+        }
+        {
+            const REQUEST: &str = "\
+                POST /testnet HTTP/1.1\r\n\
+                Content-Length: 7\r\n\
+                \r\n\
+                Testnet";
+            server.raw_call(REQUEST.as_bytes()).await.unwrap();
+        }
+        {
+            const REQUEST: &str = "\
+                GET /testnet/?id=z HTTP/1.1\r\n\
+                \r\n";
+            let response = server.raw_call(REQUEST.as_bytes()).await.unwrap();
+            assert_eq!(
+                from_utf8(&response).unwrap(),
+                "HTTP/1.1 200 OK\r\ncontent-length:7\r\n\r\nTestnet"
+            );
+        }
         /* invalid request
         Disabling this test because we should not be interested in supporting
         invalid HTTP clients that cannot write proper payloads. Our server only
@@ -166,4 +361,68 @@ mod test {
             assert!(response.is_err());
         }*/
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn channel_isolation_test() {
+        let mut server = Server::default();
+        let post = |url: &str, content: &str| {
+            Request::new(
+                Method::POST,
+                url.to_string(),
+                Default::default(),
+                content.as_bytes().to_vec(),
+            )
+        };
+        let get = |url: &str| {
+            Request::new(
+                Method::GET,
+                url.to_string(),
+                Default::default(),
+                Default::default(),
+            )
+        };
+        server.call(post("/", "default")).await.unwrap();
+        server.call(post("/testnet", "testnet")).await.unwrap();
+        server.call(post("/devnet", "devnet")).await.unwrap();
+
+        for (channel, message) in [
+            ("", "default"),
+            ("testnet", "testnet"),
+            ("devnet", "devnet"),
+        ] {
+            let response = server.call(get(&format!("/{channel}?id=1"))).await.unwrap();
+            assert_eq!(response.content, message.as_bytes());
+            let response = server.call(get(&format!("/{channel}?id=1"))).await.unwrap();
+            assert!(response.content.is_empty());
+        }
+        let response = server.call(get("/staging?id=1")).await.unwrap();
+        assert!(response.content.is_empty());
+        assert!(!server.0.contains_key("staging"));
+        assert_eq!(server.0.len(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn channel_limit_test() {
+        let mut server = Server::default();
+        let post = |url: String| {
+            Request::new(
+                Method::POST,
+                url,
+                Default::default(),
+                "Hello!".as_bytes().to_vec(),
+            )
+        };
+        for i in 0..MAX_CHANNELS {
+            let response = server.call(post(format!("/{i}"))).await.unwrap();
+            assert_eq!(response.code, 200);
+        }
+        let response = server.call(post("/new".to_string())).await.unwrap();
+        assert_eq!(response.code, 503);
+        assert!(!server.0.contains_key("new"));
+        assert_eq!(server.0.len(), MAX_CHANNELS);
+
+        // existing channels still accept messages.
+        let response = server.call(post("/0".to_string())).await.unwrap();
+        assert_eq!(response.code, 200);
+    }
 }
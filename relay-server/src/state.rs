@@ -12,4 +12,6 @@ use std::io::Error;
 pub trait State {
     async fn get(&mut self, node_id: String) -> Result<Vec<u8>, Error>;
     async fn post(&mut self, msg: Vec<u8>) -> Result<(), Error>;
+    /// Stores a message which is only returned to the nodes listed in `recipients`.
+    async fn post_to(&mut self, msg: Vec<u8>, recipients: Vec<String>) -> Result<(), Error>;
 }
@@ -0,0 +1,126 @@
+use std::io::{BufReader, Error};
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use yarpc::to_io_result::{err, ToIoResult};
+
+/// Builds the TLS configuration of the server from a PEM encoded certificate chain and a PEM
+/// encoded PKCS #8, PKCS #1 or SEC1 private key.
+pub fn tls_config(
+    cert_path: impl AsRef<Path>,
+    key_path: impl AsRef<Path>,
+) -> Result<Arc<ServerConfig>, Error> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(std::fs::File::open(cert_path)?))?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        return err("no certificate found");
+    }
+    let key = private_key(key_path)?;
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .to_io_result()?;
+    Ok(Arc::new(config))
+}
+
+fn private_key(key_path: impl AsRef<Path>) -> Result<PrivateKey, Error> {
+    let mut reader = BufReader::new(std::fs::File::open(key_path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    err("no private key found")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::BufReader;
+    use tokio::net::TcpStream;
+    use tokio_rustls::rustls::{self, Certificate, ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+    use yarpc::http::{Call, Method, Request};
+
+    use super::tls_config;
+    use crate::{Server, Timeouts};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tls_test() {
+        let ca = rcgen::Certificate::from_params({
+            let mut params = rcgen::CertificateParams::new(vec![]);
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            params
+        })
+        .unwrap();
+        let leaf = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        let dir = tempdir::TempDir::new("relay-server-tls").unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        std::fs::write(&cert_path, leaf.serialize_pem_with_signer(&ca).unwrap()).unwrap();
+        std::fs::write(&key_path, leaf.serialize_private_key_pem()).unwrap();
+
+        let addr = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let config = tls_config(&cert_path, &key_path).unwrap();
+        tokio::spawn(Server::run_tls(addr, Timeouts::default(), config));
+        let mut tcp = TcpStream::connect(addr).await;
+        while tcp.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            tcp = TcpStream::connect(addr).await;
+        }
+
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(&Certificate(ca.serialize_der().unwrap()))
+            .unwrap();
+        let connector = TlsConnector::from(Arc::new(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        ));
+        let domain = rustls::ServerName::try_from("localhost").unwrap();
+        let mut stream = BufReader::new(connector.connect(domain, tcp.unwrap()).await.unwrap());
+
+        let post = Request::new(
+            Method::POST,
+            "/".to_string(),
+            Default::default(),
+            "Hello!".as_bytes().to_vec(),
+        );
+        assert_eq!(stream.call(post).await.unwrap().code, 200);
+        let get = Request::new(
+            Method::GET,
+            "/?id=1".to_string(),
+            Default::default(),
+            Default::default(),
+        );
+        let response = stream.call(get).await.unwrap();
+        assert_eq!(from_utf8(&response.content), Ok("Hello!"));
+
+        // a client which doesn't trust the certificate can't connect.
+        let connector = TlsConnector::from(Arc::new(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(RootCertStore::empty())
+                .with_no_client_auth(),
+        ));
+        let domain = rustls::ServerName::try_from("localhost").unwrap();
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        assert!(connector.connect(domain, tcp).await.is_err());
+    }
+}
@@ -15,6 +15,14 @@ curl 'http://127.0.0.1:9776' -X POST -d 'Msg # 2'
 curl 'http://127.0.0.1:9776/?id=1'
 curl 'http://127.0.0.1:9776/?id=4'
 curl 'http://127.0.0.1:9776/?id=4'
+# try a directed message
+curl 'http://127.0.0.1:9776/?to=1,5' -X POST -d 'Msg # 3'
+curl 'http://127.0.0.1:9776/?id=4'
+curl 'http://127.0.0.1:9776/?id=5'
+# try a named channel
+curl 'http://127.0.0.1:9776/testnet' -X POST -d 'Msg # 4'
+curl 'http://127.0.0.1:9776/testnet?id=1'
+curl 'http://127.0.0.1:9776/?id=1'
 # try an empty message
 curl 'http://127.0.0.1:9776' -X POST
 curl 'http://127.0.0.1:9776/?id=1'
@@ -8,7 +8,7 @@ use bitcoin::{
 };
 use clap::Parser;
 
-use crate::commands::utils;
+use crate::commands::utils::{self, BackendArgs};
 
 #[derive(Parser, Debug, Clone)]
 pub struct BroadcastArgs {
@@ -19,8 +19,9 @@ pub struct BroadcastArgs {
     tx: String,
 }
 
-pub fn broadcast_tx(broadcast: &BroadcastArgs) -> anyhow::Result<()> {
-    let blockchain = utils::init_blockchain()?;
+pub fn broadcast_tx(broadcast: &BroadcastArgs, backend: &BackendArgs) -> anyhow::Result<()> {
+    let blockchain =
+        utils::init_blockchain(backend, broadcast.network, utils::DEFAULT_WALLET_NAME)?;
     let tx = Transaction::deserialize(
         &array_bytes::hex2bytes(&broadcast.tx).map_err(|e| anyhow!("{:?}", e))?,
     )?;
@@ -0,0 +1,51 @@
+use std::io::stdout;
+
+use anyhow::anyhow;
+use bdk::FeeRate;
+use bitcoin::Txid;
+use clap::Parser;
+
+use crate::commands::utils::{reorder_outputs, BackendArgs, FundingArgs};
+
+#[derive(Parser, Debug, Clone)]
+pub struct BumpFeeArgs {
+    #[command(flatten)]
+    funding: FundingArgs,
+
+    /// The new fee rate in sat/vB
+    #[clap(long)]
+    fee_rate: f32,
+
+    /// The id of the unconfirmed transaction to replace, which must signal RBF
+    txid: Txid,
+}
+
+pub fn bump_fee(bump: &BumpFeeArgs, backend: &BackendArgs) -> anyhow::Result<()> {
+    let wallet = bump.funding.wallet(backend)?;
+    let original_tx = wallet
+        .get_tx(&bump.txid, true)?
+        .and_then(|details| details.transaction)
+        .ok_or(anyhow!(
+            "Transaction {} not found in the funding wallet",
+            bump.txid
+        ))?;
+
+    let mut tx_builder = wallet.build_fee_bump(bump.txid)?;
+    tx_builder
+        .fee_rate(FeeRate::from_sat_per_vb(bump.fee_rate))
+        .enable_rbf();
+    let (mut psbt, _) = tx_builder.finish()?;
+
+    // The sBTC outputs keep their original positions, as only the change amount changes
+    psbt.unsigned_tx.output = reorder_outputs(
+        psbt.unsigned_tx.output,
+        original_tx
+            .output
+            .into_iter()
+            .map(|output| (output.script_pubkey, output.value)),
+    );
+
+    serde_json::to_writer_pretty(stdout(), &bump.funding.finish(&wallet, psbt)?)?;
+
+    Ok(())
+}
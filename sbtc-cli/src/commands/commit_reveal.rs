@@ -0,0 +1,408 @@
+use std::{iter::repeat, str::FromStr};
+
+use anyhow::anyhow;
+use bdk::{database::MemoryDatabase, Wallet};
+use bitcoin::{
+    blockdata::{opcodes, script::Builder},
+    psbt::PartiallySignedTransaction,
+    secp256k1::{Secp256k1, XOnlyPublicKey},
+    util::{
+        address::{Payload, WitnessVersion},
+        taproot::{LeafVersion, TaprootBuilder, TaprootSpendInfo},
+    },
+    Address as BitcoinAddress, Network, OutPoint, PackedLockTime, PublicKey, Script, Sequence,
+    Transaction, TxIn, TxOut, Witness,
+};
+use clap::Parser;
+use serde::Serialize;
+
+use crate::commands::utils::{build_op_return_script, magic_bytes, FundedTransaction, FundingArgs};
+
+/// The taproot internal key of commit outputs. It is the BIP 341 point with no known discrete
+/// logarithm, so commit outputs can only be spent through their reveal or reclaim script.
+const UNSPENDABLE_INTERNAL_KEY: &str =
+    "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// The length the operation data is padded to, before the reveal fee and memo
+pub const OPERATION_DATA_LENGTH: usize = 78;
+
+/// The maximum length of a memo, keeping the data well within the script push limit
+const MAX_MEMO_LENGTH: usize = 256;
+
+/// The op code of the OP_RETURN output of reveal transactions
+const REVEAL_OP_CODE: u8 = b'w';
+
+#[derive(Parser, Debug, Clone)]
+pub struct CommitArgs {
+    /// Dkg wallet taproot address, whose key reveals the commitment
+    #[clap(short, long)]
+    pub dkg_wallet: String,
+
+    /// The amount of sats committed on top of the operation to pay for the reveal transaction
+    #[clap(long)]
+    pub reveal_fee: u64,
+
+    /// Number of blocks after which an unrevealed commitment can be reclaimed
+    #[clap(long, default_value_t = 144)]
+    pub reclaim_lock_time: u16,
+
+    /// Hex encoded memo to commit along with the operation
+    #[clap(long)]
+    pub memo: Option<String>,
+}
+
+impl CommitArgs {
+    /// The commitment to the given operation data, reclaimable by the given key.
+    pub fn commitment(
+        &self,
+        operation_data: Vec<u8>,
+        reclaim_key: &PublicKey,
+    ) -> anyhow::Result<Commitment> {
+        let data = self.commitment_data(operation_data)?;
+        let dkg_address = BitcoinAddress::from_str(&self.dkg_wallet)?;
+        Commitment::new(
+            &data,
+            taproot_key(&dkg_address)?,
+            reclaim_key.inner.x_only_public_key().0,
+            self.reclaim_lock_time,
+        )
+    }
+
+    /// The data committed to: the operation data padded to `OPERATION_DATA_LENGTH` bytes,
+    /// followed by the reveal fee and the memo.
+    pub fn commitment_data(&self, operation_data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let memo = match &self.memo {
+            Some(memo) => array_bytes::hex2bytes(memo)
+                .map_err(|_| anyhow!("Failed to parse hex memo: {}", memo))?,
+            None => vec![],
+        };
+        if memo.len() > MAX_MEMO_LENGTH {
+            return Err(anyhow!(
+                "Memo is {} bytes long, more than the maximum of {}",
+                memo.len(),
+                MAX_MEMO_LENGTH
+            ));
+        }
+        if operation_data.len() > OPERATION_DATA_LENGTH {
+            return Err(anyhow!(
+                "Operation data is {} bytes long, more than the maximum of {}",
+                operation_data.len(),
+                OPERATION_DATA_LENGTH
+            ));
+        }
+
+        Ok(operation_data
+            .into_iter()
+            .chain(repeat(0))
+            .take(OPERATION_DATA_LENGTH)
+            .chain(self.reveal_fee.to_be_bytes())
+            .chain(memo)
+            .collect())
+    }
+}
+
+/// A taproot output committing to sBTC operation data, which either the peg wallet reveals by
+/// spending it, or its creator reclaims once the lock time has passed.
+pub struct Commitment {
+    reveal_script: Script,
+    spend_info: TaprootSpendInfo,
+}
+
+impl Commitment {
+    pub fn new(
+        data: &[u8],
+        peg_wallet_key: XOnlyPublicKey,
+        reclaim_key: XOnlyPublicKey,
+        reclaim_lock_time: u16,
+    ) -> anyhow::Result<Self> {
+        let reveal_script = Builder::new()
+            .push_slice(data)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_slice(&peg_wallet_key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script();
+        let reclaim_script = Builder::new()
+            .push_int(reclaim_lock_time.into())
+            .push_opcode(opcodes::all::OP_CSV)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_slice(&reclaim_key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script();
+
+        let secp = Secp256k1::new();
+        let internal_key = XOnlyPublicKey::from_str(UNSPENDABLE_INTERNAL_KEY)?;
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, reveal_script.clone())?
+            .add_leaf(1, reclaim_script)?
+            .finalize(&secp, internal_key)
+            .map_err(|_| anyhow!("Could not build the commitment taproot tree"))?;
+
+        Ok(Self {
+            reveal_script,
+            spend_info,
+        })
+    }
+
+    pub fn address(&self, network: Network) -> BitcoinAddress {
+        BitcoinAddress::p2tr_tweaked(self.spend_info.output_key(), network)
+    }
+
+    /// The input spending the commit output through the reveal script.
+    /// Its witness still lacks the peg wallet signature, which must be inserted first.
+    pub fn reveal_input(&self, commit_outpoint: OutPoint) -> anyhow::Result<TxIn> {
+        let control_block = self
+            .spend_info
+            .control_block(&(self.reveal_script.clone(), LeafVersion::TapScript))
+            .ok_or(anyhow!("Reveal script missing from the commitment"))?;
+
+        Ok(TxIn {
+            previous_output: commit_outpoint,
+            script_sig: Script::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::from_vec(vec![
+                self.reveal_script.to_bytes(),
+                control_block.serialize(),
+            ]),
+        })
+    }
+}
+
+/// The key of a taproot address.
+pub fn taproot_key(address: &BitcoinAddress) -> anyhow::Result<XOnlyPublicKey> {
+    match &address.payload {
+        Payload::WitnessProgram {
+            version: WitnessVersion::V1,
+            program,
+        } => Ok(XOnlyPublicKey::from_slice(program)?),
+        _ => Err(anyhow!("{} is not a taproot address", address)),
+    }
+}
+
+/// The key that can reclaim a commitment: the given public key, or else the key of a single key
+/// funding wallet.
+pub fn reclaim_key(
+    reclaim_public_key: Option<&str>,
+    funding: &FundingArgs,
+) -> anyhow::Result<PublicKey> {
+    match reclaim_public_key {
+        Some(public_key) => Ok(PublicKey::from_str(public_key)?),
+        None => funding.single_key()?.ok_or(anyhow!(
+            "A reclaim public key is required when funding from an HD wallet or a descriptor"
+        )),
+    }
+}
+
+/// The transaction paying the given amount to the commitment, funded by the wallet.
+pub fn commit_psbt(
+    wallet: &Wallet<MemoryDatabase>,
+    commitment: &Commitment,
+    amount: u64,
+) -> anyhow::Result<PartiallySignedTransaction> {
+    let mut tx_builder = wallet.build_tx();
+    tx_builder.add_recipient(commitment.address(wallet.network()).script_pubkey(), amount);
+    let (psbt, _) = tx_builder.finish()?;
+
+    Ok(psbt)
+}
+
+/// The unsigned transaction revealing the commitment, with the OP_RETURN output marking it as a
+/// reveal placed first, followed by the given outputs.
+pub fn reveal_tx(
+    commitment: &Commitment,
+    commit_outpoint: OutPoint,
+    outputs: impl IntoIterator<Item = (Script, u64)>,
+    network: Network,
+) -> anyhow::Result<Transaction> {
+    let op_return_script = build_op_return_script(
+        &magic_bytes(&network)
+            .into_iter()
+            .chain([REVEAL_OP_CODE])
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(Transaction {
+        version: 2,
+        lock_time: PackedLockTime::ZERO,
+        input: vec![commitment.reveal_input(commit_outpoint)?],
+        output: [(op_return_script, 0)]
+            .into_iter()
+            .chain(outputs)
+            .map(|(script_pubkey, value)| TxOut {
+                value,
+                script_pubkey,
+            })
+            .collect(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct CommitTransactionData {
+    #[serde(flatten)]
+    pub transaction: FundedTransaction,
+    pub commit_address: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{blockdata::script::Instruction, util::taproot::ControlBlock, PrivateKey};
+
+    use super::*;
+
+    fn reclaim_public_key() -> PublicKey {
+        PrivateKey::from_slice(&[1; 32], Network::Testnet)
+            .unwrap()
+            .public_key(&Secp256k1::new())
+    }
+
+    fn commit_args(memo: Option<&str>) -> CommitArgs {
+        let secp = Secp256k1::new();
+        let dkg_key = PrivateKey::from_slice(&[2; 32], Network::Testnet)
+            .unwrap()
+            .public_key(&secp)
+            .inner
+            .x_only_public_key()
+            .0;
+        CommitArgs {
+            dkg_wallet: BitcoinAddress::p2tr(&secp, dkg_key, None, Network::Testnet).to_string(),
+            reveal_fee: 1000,
+            reclaim_lock_time: 144,
+            memo: memo.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_commitment_data_layout() {
+        let data = commit_args(Some("01020304"))
+            .commitment_data(vec![b'<', 5, 6])
+            .unwrap();
+        assert_eq!(data.len(), OPERATION_DATA_LENGTH + 8 + 4);
+        assert_eq!(data[..3], [b'<', 5, 6]);
+        assert!(data[3..OPERATION_DATA_LENGTH].iter().all(|byte| *byte == 0));
+        assert_eq!(
+            data[OPERATION_DATA_LENGTH..OPERATION_DATA_LENGTH + 8],
+            1000u64.to_be_bytes()
+        );
+        assert_eq!(data[OPERATION_DATA_LENGTH + 8..], [1, 2, 3, 4]);
+
+        // Without a memo the data ends with the reveal fee
+        let data = commit_args(None).commitment_data(vec![b'<']).unwrap();
+        assert_eq!(data.len(), OPERATION_DATA_LENGTH + 8);
+    }
+
+    #[test]
+    fn test_commitment_data_limits() {
+        let args = commit_args(None);
+        assert!(args.commitment_data(vec![0; OPERATION_DATA_LENGTH]).is_ok());
+        assert!(args
+            .commitment_data(vec![0; OPERATION_DATA_LENGTH + 1])
+            .is_err());
+
+        let memo = "00".repeat(MAX_MEMO_LENGTH);
+        assert!(commit_args(Some(&memo)).commitment_data(vec![]).is_ok());
+        let memo = "00".repeat(MAX_MEMO_LENGTH + 1);
+        assert!(commit_args(Some(&memo)).commitment_data(vec![]).is_err());
+        assert!(commit_args(Some("0g")).commitment_data(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_commitment_round_trip() {
+        let args = commit_args(Some("1337"));
+        let data = args.commitment_data(vec![b'<', 5, 6]).unwrap();
+        let commitment = args
+            .commitment(vec![b'<', 5, 6], &reclaim_public_key())
+            .unwrap();
+
+        // The reveal witness pushes the committed data first, then the peg wallet key check
+        let input = commitment.reveal_input(OutPoint::null()).unwrap();
+        let witness = input.witness.to_vec();
+        assert_eq!(witness.len(), 2);
+        let reveal_script = Script::from(witness[0].clone());
+        let instructions = reveal_script
+            .instructions()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let dkg_key = taproot_key(&BitcoinAddress::from_str(&args.dkg_wallet).unwrap()).unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::PushBytes(&data),
+                Instruction::Op(opcodes::all::OP_DROP),
+                Instruction::PushBytes(&dkg_key.serialize()),
+                Instruction::Op(opcodes::all::OP_CHECKSIG),
+            ]
+        );
+
+        // The control block proves the commit address commits to the reveal script
+        let control_block = ControlBlock::from_slice(&witness[1]).unwrap();
+        let output_key = taproot_key(&commitment.address(Network::Testnet)).unwrap();
+        assert!(control_block.verify_taproot_commitment(
+            &Secp256k1::new(),
+            output_key,
+            &reveal_script
+        ));
+    }
+
+    #[test]
+    fn test_reveal_tx() {
+        let commitment = commit_args(None)
+            .commitment(vec![b'<'], &reclaim_public_key())
+            .unwrap();
+        let output_script = Script::new_v1_p2tr_tweaked(commitment.spend_info.output_key());
+        let tx = reveal_tx(
+            &commitment,
+            OutPoint::null(),
+            [(output_script.clone(), 1337)],
+            Network::Regtest,
+        )
+        .unwrap();
+
+        // The outputs of generate_peg_in_reveal_test_vector in the test-vectors crate
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.output[0].value, 0);
+        assert_eq!(
+            tx.output[0].script_pubkey,
+            Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_slice(&[105, 100, b'w'])
+                .into_script()
+        );
+        assert_eq!(tx.output[1].value, 1337);
+        assert_eq!(tx.output[1].script_pubkey, output_script);
+        assert_eq!(tx.input[0].previous_output, OutPoint::null());
+    }
+
+    #[test]
+    fn test_reclaim_key() {
+        let public_key = reclaim_public_key().to_string();
+        let wif = PrivateKey::from_slice(&[1; 32], Network::Testnet)
+            .unwrap()
+            .to_wif();
+        let funding =
+            |args: &[&str]| FundingArgs::try_parse_from(["sbtc"].iter().chain(args)).unwrap();
+
+        // WIFs are secrets, so they are not accepted as plain arguments
+        assert!(FundingArgs::try_parse_from(["sbtc", "--wif", &wif]).is_err());
+        std::env::set_var("SBTC_CLI_TEST_RECLAIM_WIF", wif);
+        let wif_funding = funding(&["--wif", "env:SBTC_CLI_TEST_RECLAIM_WIF"]);
+        assert_eq!(
+            reclaim_key(None, &wif_funding).unwrap(),
+            reclaim_public_key()
+        );
+        let public_key_funding = funding(&["--unsigned", "--public-key", &public_key]);
+        assert_eq!(
+            reclaim_key(None, &public_key_funding).unwrap(),
+            reclaim_public_key()
+        );
+
+        let descriptor = format!("wpkh({})", public_key);
+        let descriptor_funding = funding(&["--unsigned", "--descriptor", &descriptor]);
+        assert!(reclaim_key(None, &descriptor_funding).is_err());
+        let other_key = PrivateKey::from_slice(&[2; 32], Network::Testnet)
+            .unwrap()
+            .public_key(&Secp256k1::new());
+        assert_eq!(
+            reclaim_key(Some(&other_key.to_string()), &descriptor_funding).unwrap(),
+            other_key
+        );
+    }
+}
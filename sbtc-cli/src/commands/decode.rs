@@ -0,0 +1,801 @@
+use std::{io::stdout, str::FromStr};
+
+use anyhow::anyhow;
+use bdk::blockchain::GetTx;
+use bitcoin::{
+    blockdata::{opcodes, script::Instruction},
+    psbt::serialize::Deserialize,
+    secp256k1::{
+        ecdsa::{RecoverableSignature, RecoveryId},
+        Secp256k1,
+    },
+    Address as BitcoinAddress, Network, PublicKey, Script, Transaction, TxOut, Txid, Witness,
+};
+use blockstack_lib::{types::chainstate::StacksAddress, util::hash::Hash160, vm::ContractName};
+use clap::Parser;
+use serde::Serialize;
+
+use crate::commands::{
+    commit_reveal::OPERATION_DATA_LENGTH,
+    deposit::{CONTRACT_PRINCIPAL, STANDARD_PRINCIPAL},
+    utils::{self, stacks_address, BackendArgs},
+    withdraw::withdrawal_message,
+};
+
+#[derive(Parser, Debug, Clone)]
+pub struct DecodeArgs {
+    /// The network the transaction belongs to
+    #[clap(short, long, default_value_t = Network::Testnet)]
+    network: Network,
+
+    /// The transaction in hex, or the id of a transaction to fetch from the Bitcoin backend
+    tx: String,
+}
+
+#[derive(Serialize, Debug)]
+struct DecodedTransaction {
+    tx_id: String,
+    #[serde(flatten)]
+    operation: Operation,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    Deposit {
+        recipient: String,
+        amount: u64,
+        peg_wallet: String,
+        /// The reveal fee committed to by a deposit revealed from a commitment
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reveal_fee: Option<u64>,
+        memo: String,
+    },
+    WithdrawalRequest {
+        recipient: String,
+        amount: u64,
+        fulfillment_fee: u64,
+        peg_wallet: String,
+        recovery_id: u8,
+        signature: String,
+        sender_public_key: String,
+        sender_stacks_address: String,
+        /// The reveal fee committed to by a withdrawal request revealed from a commitment
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reveal_fee: Option<u64>,
+        memo: String,
+    },
+    Fulfillment {
+        chain_tip: String,
+        recipient: String,
+        amount: u64,
+        memo: String,
+    },
+    Handoff {
+        reward_cycle: u64,
+        peg_wallet: String,
+    },
+}
+
+pub fn decode_tx(decode: &DecodeArgs, backend: &BackendArgs) -> anyhow::Result<()> {
+    let tx = match Txid::from_str(&decode.tx) {
+        Ok(txid) => {
+            let blockchain =
+                utils::init_blockchain(backend, decode.network, utils::DEFAULT_WALLET_NAME)?;
+            blockchain
+                .get_tx(&txid)?
+                .ok_or(anyhow!("Transaction {} not found", txid))?
+        }
+        Err(_) => Transaction::deserialize(
+            &array_bytes::hex2bytes(&decode.tx).map_err(|e| anyhow!("{:?}", e))?,
+        )?,
+    };
+
+    serde_json::to_writer_pretty(
+        stdout(),
+        &DecodedTransaction {
+            tx_id: tx.txid().to_string(),
+            operation: decode_operation(&tx, decode.network)?,
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn decode_operation(tx: &Transaction, network: Network) -> anyhow::Result<Operation> {
+    let data = tx
+        .output
+        .first()
+        .and_then(|output| op_return_data(&output.script_pubkey))
+        .ok_or(anyhow!("The first output is not an OP_RETURN output"))?;
+    if data.len() < 3 {
+        return Err(anyhow!("OP_RETURN data is too short for an sBTC operation"));
+    }
+
+    let (magic, op, data) = (&data[..2], data[2], &data[3..]);
+    if magic != utils::magic_bytes(&network) {
+        let other_network = [Network::Bitcoin, Network::Testnet, Network::Regtest]
+            .into_iter()
+            .find(|other| magic == utils::magic_bytes(other));
+        return Err(match other_network {
+            Some(other) => anyhow!("Magic bytes belong to {}, not {}", other, network),
+            None => anyhow!("Not an sBTC operation, unknown magic bytes {:?}", magic),
+        });
+    }
+
+    match op {
+        b'<' => decode_deposit(tx, data, network),
+        b'>' => decode_withdrawal_request(tx, data, network),
+        b'!' => decode_fulfillment(tx, data, network),
+        b'H' => decode_handoff(tx, data, network),
+        b'w' => decode_reveal(tx, network),
+        op => Err(anyhow!("Unknown sBTC op code {:?}", op as char)),
+    }
+}
+
+/// Wire format, following the magic bytes and op code:
+/// Stacks address version (1) | Stacks address hash (20) | contract name (40, optional) | memo
+fn decode_deposit(tx: &Transaction, data: &[u8], network: Network) -> anyhow::Result<Operation> {
+    if data.len() < 21 {
+        return Err(anyhow!("Deposit data is too short for a Stacks address"));
+    }
+    if data.len() > 21 && data.len() < 61 {
+        return Err(anyhow!(
+            "Deposit data is {} bytes long, which cuts off the contract name",
+            data.len()
+        ));
+    }
+    let address_hash =
+        Hash160::from_vec(&data[1..21].to_vec()).ok_or(anyhow!("Invalid Stacks address hash"))?;
+    let address = StacksAddress::new(data[0], address_hash);
+
+    let contract_name = data
+        .get(21..61)
+        .map(|bytes| std::str::from_utf8(bytes).map(|name| name.trim_end_matches('\0')))
+        .transpose()
+        .map_err(|_| anyhow!("Contract name is not valid UTF-8"))?
+        .unwrap_or_default();
+    let recipient = if contract_name.is_empty() {
+        address.to_string()
+    } else {
+        principal(address, contract_name)?
+    };
+
+    deposit(
+        tx,
+        recipient,
+        None,
+        data.get(61..).unwrap_or_default(),
+        network,
+    )
+}
+
+/// Wire format, following the op code:
+/// principal type (1) | Stacks address version (1) | Stacks address hash (20) |
+/// contract name length (1, contract principals only) | contract name | padding
+fn decode_deposit_reveal(
+    tx: &Transaction,
+    data: &[u8],
+    reveal_fee: Option<u64>,
+    memo: &[u8],
+    network: Network,
+) -> anyhow::Result<Operation> {
+    let address_hash =
+        Hash160::from_vec(&data[2..22].to_vec()).ok_or(anyhow!("Invalid Stacks address hash"))?;
+    let address = StacksAddress::new(data[1], address_hash);
+
+    let recipient = match data[0] {
+        STANDARD_PRINCIPAL => address.to_string(),
+        CONTRACT_PRINCIPAL => {
+            let length = usize::from(data[22]);
+            let contract_name = data
+                .get(23..23 + length)
+                .ok_or(anyhow!(
+                    "Contract name of {} bytes does not fit in the deposit data",
+                    length
+                ))
+                .and_then(|bytes| {
+                    std::str::from_utf8(bytes)
+                        .map_err(|_| anyhow!("Contract name is not valid UTF-8"))
+                })?;
+            principal(address, contract_name)?
+        }
+        principal_type => return Err(anyhow!("Unknown principal type {}", principal_type)),
+    };
+
+    deposit(tx, recipient, reveal_fee, memo, network)
+}
+
+fn principal(address: StacksAddress, contract_name: &str) -> anyhow::Result<String> {
+    ContractName::try_from(contract_name.to_string())
+        .map_err(|_| anyhow!("Invalid contract name: {}", contract_name))?;
+    Ok(format!("{}.{}", address, contract_name))
+}
+
+fn deposit(
+    tx: &Transaction,
+    recipient: String,
+    reveal_fee: Option<u64>,
+    memo: &[u8],
+    network: Network,
+) -> anyhow::Result<Operation> {
+    let peg_wallet_output = output(tx, 1, "peg wallet")?;
+    check_dust(peg_wallet_output, "Deposit amount")?;
+
+    Ok(Operation::Deposit {
+        recipient,
+        amount: peg_wallet_output.value,
+        peg_wallet: taproot_address(peg_wallet_output, network)?,
+        reveal_fee,
+        memo: array_bytes::bytes2hex("", memo),
+    })
+}
+
+/// Wire format, following the magic bytes and op code:
+/// amount (8) | recovery id (1) | signature (64) | memo
+fn decode_withdrawal_request(
+    tx: &Transaction,
+    data: &[u8],
+    network: Network,
+) -> anyhow::Result<Operation> {
+    if data.len() < 73 {
+        return Err(anyhow!(
+            "Withdrawal data is too short for an amount and signature"
+        ));
+    }
+    withdrawal_request(tx, &data[..73], None, &data[73..], network)
+}
+
+/// The withdrawal request of the amount (8) | recovery id (1) | signature (64) in the data.
+fn withdrawal_request(
+    tx: &Transaction,
+    data: &[u8],
+    reveal_fee: Option<u64>,
+    memo: &[u8],
+    network: Network,
+) -> anyhow::Result<Operation> {
+    let amount = u64::from_be_bytes(data[..8].try_into()?);
+    let recovery_id = data[8];
+    let signature = &data[9..73];
+
+    let recipient_output = output(tx, 1, "recipient")?;
+    let peg_wallet_output = output(tx, 2, "fulfillment fee")?;
+    check_dust(peg_wallet_output, "Fulfillment fee")?;
+
+    let secp = Secp256k1::new();
+    let sender_public_key = secp
+        .recover_ecdsa(
+            &withdrawal_message(&recipient_output.script_pubkey, amount),
+            &RecoverableSignature::from_compact(
+                signature,
+                RecoveryId::from_i32(recovery_id.into())?,
+            )?,
+        )
+        .map_err(|_| anyhow!("Could not recover the sender from the withdrawal signature"))?;
+    // Like the Stacks node, attribute the request to the compressed public key
+    let sender_public_key = PublicKey::new(sender_public_key);
+
+    Ok(Operation::WithdrawalRequest {
+        recipient: address(recipient_output, network)?,
+        amount,
+        fulfillment_fee: peg_wallet_output.value,
+        peg_wallet: taproot_address(peg_wallet_output, network)?,
+        recovery_id,
+        signature: array_bytes::bytes2hex("", signature),
+        sender_public_key: sender_public_key.to_string(),
+        sender_stacks_address: stacks_address(&sender_public_key, network)?.to_string(),
+        reveal_fee,
+        memo: array_bytes::bytes2hex("", memo),
+    })
+}
+
+/// Wire format, following the magic bytes and op code:
+/// Stacks chain tip (32) | memo
+fn decode_fulfillment(
+    tx: &Transaction,
+    data: &[u8],
+    network: Network,
+) -> anyhow::Result<Operation> {
+    if data.len() < 32 {
+        return Err(anyhow!("Fulfillment data is too short for a chain tip"));
+    }
+    let recipient_output = output(tx, 1, "recipient")?;
+
+    Ok(Operation::Fulfillment {
+        chain_tip: array_bytes::bytes2hex("", &data[..32]),
+        recipient: address(recipient_output, network)?,
+        amount: recipient_output.value,
+        memo: array_bytes::bytes2hex("", &data[32..]),
+    })
+}
+
+/// Wire format, following the magic bytes and op code:
+/// reward cycle (8) | padding
+fn decode_handoff(tx: &Transaction, data: &[u8], network: Network) -> anyhow::Result<Operation> {
+    let reward_cycle = data
+        .get(..8)
+        .ok_or(anyhow!("Hand-off data is too short for a reward cycle"))?;
+
+    Ok(Operation::Handoff {
+        reward_cycle: u64::from_be_bytes(reward_cycle.try_into()?),
+        peg_wallet: taproot_address(output(tx, 1, "peg wallet")?, network)?,
+    })
+}
+
+/// Reveal transactions carry the operation in the commitment script spent by their first input,
+/// which starts with the committed data:
+/// op code (1) | operation data, padded to OPERATION_DATA_LENGTH | reveal fee (8, optional) | memo
+fn decode_reveal(tx: &Transaction, network: Network) -> anyhow::Result<Operation> {
+    let data = tx
+        .input
+        .first()
+        .and_then(|input| commitment_data(&input.witness))
+        .ok_or(anyhow!("The first input does not reveal a commitment"))?;
+    if data.len() < OPERATION_DATA_LENGTH {
+        return Err(anyhow!(
+            "Revealed data is {} bytes long, shorter than the {} bytes of operation data",
+            data.len(),
+            OPERATION_DATA_LENGTH
+        ));
+    }
+
+    let (operation_data, rest) = data.split_at(OPERATION_DATA_LENGTH);
+    let (reveal_fee, memo) = match rest.len() {
+        0 => (None, rest),
+        1..=7 => return Err(anyhow!("Revealed data is too short for a reveal fee")),
+        _ => (Some(u64::from_be_bytes(rest[..8].try_into()?)), &rest[8..]),
+    };
+
+    let (op, data) = (operation_data[0], &operation_data[1..]);
+    match op {
+        b'<' => decode_deposit_reveal(tx, data, reveal_fee, memo, network),
+        b'>' => withdrawal_request(tx, &data[..73], reveal_fee, memo, network),
+        op => Err(anyhow!("Unknown sBTC reveal op code {:?}", op as char)),
+    }
+}
+
+/// The data dropped at the start of the script spent by a taproot script path witness, whose
+/// script is followed by its control block.
+fn commitment_data(witness: &Witness) -> Option<Vec<u8>> {
+    let script = Script::from(witness.second_to_last()?.to_vec());
+    let mut instructions = script.instructions();
+    match (instructions.next()?.ok()?, instructions.next()?.ok()?) {
+        (Instruction::PushBytes(data), Instruction::Op(op)) if op == opcodes::all::OP_DROP => {
+            Some(data.to_vec())
+        }
+        _ => None,
+    }
+}
+
+/// The data pushed after OP_RETURN, if the script is an OP_RETURN script.
+fn op_return_data(script: &Script) -> Option<Vec<u8>> {
+    if !script.is_op_return() {
+        return None;
+    }
+
+    let mut data = vec![];
+    for instruction in script.instructions().skip(1) {
+        match instruction.ok()? {
+            Instruction::PushBytes(bytes) => data.extend_from_slice(bytes),
+            Instruction::Op(_) => return None,
+        }
+    }
+    Some(data)
+}
+
+fn output<'a>(tx: &'a Transaction, index: usize, name: &str) -> anyhow::Result<&'a TxOut> {
+    tx.output
+        .get(index)
+        .ok_or(anyhow!("Missing the {} output at index {}", name, index))
+}
+
+fn check_dust(output: &TxOut, name: &str) -> anyhow::Result<()> {
+    let dust_amount = output.script_pubkey.dust_value().to_sat();
+    if output.value < dust_amount {
+        return Err(anyhow!(
+            "{} {} is less than the dust amount: {}",
+            name,
+            output.value,
+            dust_amount
+        ));
+    }
+    Ok(())
+}
+
+fn address(output: &TxOut, network: Network) -> anyhow::Result<String> {
+    BitcoinAddress::from_script(&output.script_pubkey, network)
+        .map(|address| address.to_string())
+        .map_err(|_| anyhow!("Output script {} has no address", output.script_pubkey))
+}
+
+fn taproot_address(output: &TxOut, network: Network) -> anyhow::Result<String> {
+    if !output.script_pubkey.is_v1_p2tr() {
+        return Err(anyhow!(
+            "Peg wallet output script {} is not a taproot script",
+            output.script_pubkey
+        ));
+    }
+    address(output, network)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::{once, repeat};
+
+    use bitcoin::{
+        blockdata::script::Builder, hashes::Hash, secp256k1::SecretKey, OutPoint, PackedLockTime,
+        PubkeyHash, Sequence, TxIn,
+    };
+    use blockstack_lib::address::C32_ADDRESS_VERSION_TESTNET_SINGLESIG;
+
+    use super::*;
+    use crate::commands::{
+        commit_reveal::{reveal_tx, CommitArgs},
+        utils::build_op_return_script,
+    };
+
+    // Arbitrary key, copy-pasted from src/chainstate/stacks/tests/accounting.rs
+    const SENDER_SECRET_KEY: &str =
+        "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c";
+
+    const PEG_WALLET: [u8; 32] = [4; 32];
+
+    fn peg_wallet_script() -> Script {
+        Builder::new()
+            .push_int(1)
+            .push_slice(&PEG_WALLET)
+            .into_script()
+    }
+
+    fn recipient_script() -> Script {
+        Script::new_p2pkh(&PubkeyHash::from_inner([5; 20]))
+    }
+
+    fn script_address(script: &Script, network: Network) -> String {
+        BitcoinAddress::from_script(script, network)
+            .unwrap()
+            .to_string()
+    }
+
+    fn stacks_address() -> StacksAddress {
+        StacksAddress::new(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, Hash160([1; 20]))
+    }
+
+    fn transaction(input: Vec<TxIn>, output: Vec<(Script, u64)>) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input,
+            output: output
+                .into_iter()
+                .map(|(script_pubkey, value)| TxOut {
+                    value,
+                    script_pubkey,
+                })
+                .collect(),
+        }
+    }
+
+    /// A transaction with the sBTC operation data in its OP_RETURN output.
+    fn op_return_tx(data: &[u8], outputs: Vec<(Script, u64)>) -> Transaction {
+        let op_return_output = (
+            build_op_return_script(
+                &utils::magic_bytes(&Network::Testnet)
+                    .into_iter()
+                    .chain(data.iter().copied())
+                    .collect::<Vec<_>>(),
+            ),
+            0,
+        );
+        transaction(vec![], once(op_return_output).chain(outputs).collect())
+    }
+
+    /// A reveal transaction spending a commitment script that drops the data, as in the test
+    /// vectors: the witness holds the script followed by a control block, and the OP_RETURN
+    /// output has the regtest magic bytes.
+    fn reveal_test_vector(data: &[u8], outputs: Vec<(Script, u64)>) -> Transaction {
+        let witness_script = Builder::new()
+            .push_slice(data)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_opcode(opcodes::all::OP_DUP)
+            .push_opcode(opcodes::all::OP_HASH160)
+            .push_slice(&PEG_WALLET)
+            .push_opcode(opcodes::all::OP_EQUAL)
+            .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
+            .into_script();
+        let input = TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Script::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::from_vec(vec![witness_script.to_bytes(), [60; 97].to_vec()]),
+        };
+        let op_return_output = (build_op_return_script(&[105, 100, b'w']), 0);
+
+        transaction(vec![input], once(op_return_output).chain(outputs).collect())
+    }
+
+    /// The layout of peg_in_data in the test vectors
+    fn peg_in_data(contract_name: Option<&str>, fee_subsidy: Option<u64>) -> Vec<u8> {
+        let principal_type = match contract_name {
+            Some(_) => CONTRACT_PRINCIPAL,
+            None => STANDARD_PRINCIPAL,
+        };
+
+        once(b'<')
+            .chain(once(principal_type))
+            .chain(once(C32_ADDRESS_VERSION_TESTNET_SINGLESIG))
+            .chain([1; 20])
+            .chain(
+                contract_name
+                    .map(|name| once(name.len() as u8).chain(name.as_bytes().to_vec()))
+                    .into_iter()
+                    .flatten(),
+            )
+            .chain(repeat(0))
+            .take(78)
+            .chain(fee_subsidy.map(u64::to_be_bytes).into_iter().flatten())
+            .collect()
+    }
+
+    /// The layout of peg_out_request_data in the test vectors, signed by the sender key
+    fn peg_out_request_data(amount: u64, fee_subsidy: Option<u64>) -> Vec<u8> {
+        let secret_key =
+            SecretKey::from_slice(&array_bytes::hex2bytes(SENDER_SECRET_KEY).unwrap()).unwrap();
+        let (recovery_id, signature) = Secp256k1::new()
+            .sign_ecdsa_recoverable(
+                &withdrawal_message(&recipient_script(), amount),
+                &secret_key,
+            )
+            .serialize_compact();
+
+        once(b'>')
+            .chain(amount.to_be_bytes())
+            .chain(once(recovery_id.to_i32() as u8))
+            .chain(signature)
+            .chain(repeat(0))
+            .take(78)
+            .chain(fee_subsidy.map(u64::to_be_bytes).into_iter().flatten())
+            .collect()
+    }
+
+    fn sender_public_key() -> PublicKey {
+        let secret_key =
+            SecretKey::from_slice(&array_bytes::hex2bytes(SENDER_SECRET_KEY).unwrap()).unwrap();
+        PublicKey::new(bitcoin::secp256k1::PublicKey::from_secret_key(
+            &Secp256k1::new(),
+            &secret_key,
+        ))
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        let script = Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_slice(b"T2")
+            .push_slice(b"<")
+            .into_script();
+        assert_eq!(op_return_data(&script), Some(b"T2<".to_vec()));
+        assert_eq!(
+            op_return_data(&build_op_return_script(b"T2<")),
+            Some(b"T2<".to_vec())
+        );
+
+        let script = Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_slice(b"T2")
+            .push_int(1)
+            .into_script();
+        assert_eq!(op_return_data(&script), None);
+        assert_eq!(op_return_data(&recipient_script()), None);
+    }
+
+    #[test]
+    fn test_decode_operation_validation() {
+        let tx = transaction(vec![], vec![(recipient_script(), 1000)]);
+        assert!(decode_operation(&tx, Network::Testnet).is_err());
+
+        let tx = transaction(vec![], vec![(build_op_return_script(b"T2"), 0)]);
+        assert!(decode_operation(&tx, Network::Testnet).is_err());
+
+        let tx = op_return_tx(b"<", vec![]);
+        let error = decode_operation(&tx, Network::Bitcoin).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Magic bytes belong to testnet, not bitcoin"
+        );
+
+        let tx = transaction(vec![], vec![(build_op_return_script(b"zz<"), 0)]);
+        let error = decode_operation(&tx, Network::Testnet).unwrap_err();
+        assert!(error.to_string().starts_with("Not an sBTC operation"));
+
+        let tx = op_return_tx(b"q", vec![]);
+        let error = decode_operation(&tx, Network::Testnet).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown sBTC op code 'q'");
+    }
+
+    #[test]
+    fn test_decode_deposit() {
+        let address_data: Vec<u8> = once(C32_ADDRESS_VERSION_TESTNET_SINGLESIG)
+            .chain([1; 20])
+            .collect();
+        let contract_name: Vec<u8> = b"sbtc-receiver-contract"
+            .iter()
+            .copied()
+            .chain(repeat(0))
+            .take(40)
+            .collect();
+        let outputs = vec![(peg_wallet_script(), 1337)];
+
+        let data: Vec<u8> = once(b'<').chain(address_data.clone()).collect();
+        let Operation::Deposit {
+            recipient,
+            amount,
+            peg_wallet,
+            reveal_fee,
+            memo,
+        } = decode_operation(&op_return_tx(&data, outputs.clone()), Network::Testnet).unwrap()
+        else {
+            panic!("Not decoded as a deposit");
+        };
+        assert_eq!(recipient, stacks_address().to_string());
+        assert_eq!(amount, 1337);
+        assert_eq!(
+            peg_wallet,
+            script_address(&peg_wallet_script(), Network::Testnet)
+        );
+        assert_eq!(reveal_fee, None);
+        assert_eq!(memo, "");
+
+        let data: Vec<u8> = once(b'<')
+            .chain(address_data.clone())
+            .chain(contract_name.clone())
+            .chain([1, 2])
+            .collect();
+        let Operation::Deposit {
+            recipient, memo, ..
+        } = decode_operation(&op_return_tx(&data, outputs.clone()), Network::Testnet).unwrap()
+        else {
+            panic!("Not decoded as a deposit");
+        };
+        assert_eq!(
+            recipient,
+            format!("{}.sbtc-receiver-contract", stacks_address())
+        );
+        assert_eq!(memo, "0102");
+
+        // A contract name cut short is not silently dropped
+        for length in [22, 40, 60] {
+            let data: Vec<u8> = once(b'<')
+                .chain(address_data.clone())
+                .chain(contract_name.clone())
+                .take(1 + length)
+                .collect();
+            assert!(
+                decode_operation(&op_return_tx(&data, outputs.clone()), Network::Testnet).is_err()
+            );
+        }
+
+        let data: Vec<u8> = once(b'<').chain(address_data).collect();
+        let dust_outputs = vec![(peg_wallet_script(), 100)];
+        assert!(decode_operation(&op_return_tx(&data, dust_outputs), Network::Testnet).is_err());
+    }
+
+    #[test]
+    fn test_decode_deposit_reveal() {
+        let outputs = vec![(peg_wallet_script(), 1337)];
+
+        for (contract_name, fee_subsidy) in [
+            (Some("sbtc-receiver-contract"), None),
+            (None, None),
+            (None, Some(2000)),
+        ] {
+            let data = peg_in_data(contract_name, fee_subsidy);
+            let tx = reveal_test_vector(&data, outputs.clone());
+
+            let Operation::Deposit {
+                recipient,
+                amount,
+                reveal_fee,
+                memo,
+                ..
+            } = decode_operation(&tx, Network::Regtest).unwrap()
+            else {
+                panic!("Not decoded as a deposit");
+            };
+            let expected_recipient = match contract_name {
+                Some(name) => format!("{}.{}", stacks_address(), name),
+                None => stacks_address().to_string(),
+            };
+            assert_eq!(recipient, expected_recipient);
+            assert_eq!(amount, 1337);
+            assert_eq!(reveal_fee, fee_subsidy);
+            assert_eq!(memo, "");
+        }
+
+        let mut data = peg_in_data(None, None);
+        data[1] = 0x07;
+        let tx = reveal_test_vector(&data, outputs.clone());
+        assert!(decode_operation(&tx, Network::Regtest).is_err());
+
+        let data = peg_in_data(None, None);
+        let tx = reveal_test_vector(&data[..77], outputs);
+        assert!(decode_operation(&tx, Network::Regtest).is_err());
+    }
+
+    #[test]
+    fn test_decode_withdrawal_request_reveal() {
+        let outputs = vec![(recipient_script(), 546), (peg_wallet_script(), 1000)];
+
+        for fee_subsidy in [None, Some(2000)] {
+            let tx = reveal_test_vector(&peg_out_request_data(1337, fee_subsidy), outputs.clone());
+
+            let Operation::WithdrawalRequest {
+                recipient,
+                amount,
+                fulfillment_fee,
+                sender_public_key: decoded_sender_public_key,
+                reveal_fee,
+                ..
+            } = decode_operation(&tx, Network::Regtest).unwrap()
+            else {
+                panic!("Not decoded as a withdrawal request");
+            };
+            assert_eq!(
+                recipient,
+                script_address(&recipient_script(), Network::Regtest)
+            );
+            assert_eq!(amount, 1337);
+            assert_eq!(fulfillment_fee, 1000);
+            assert_eq!(decoded_sender_public_key, sender_public_key().to_string());
+            assert_eq!(reveal_fee, fee_subsidy);
+        }
+
+        // The signature covers the recipient script, so another recipient changes the sender
+        let other_outputs = vec![(peg_wallet_script(), 546), (peg_wallet_script(), 1000)];
+        let tx = reveal_test_vector(&peg_out_request_data(1337, None), other_outputs);
+        let Operation::WithdrawalRequest {
+            sender_public_key: decoded_sender_public_key,
+            ..
+        } = decode_operation(&tx, Network::Regtest).unwrap()
+        else {
+            panic!("Not decoded as a withdrawal request");
+        };
+        assert_ne!(decoded_sender_public_key, sender_public_key().to_string());
+    }
+
+    #[test]
+    fn test_decode_cli_reveal() {
+        let commit = CommitArgs {
+            dkg_wallet: "tb1pewpc7x6nnea8clm2vn2d8xvpdwvkhucmfdwmm0p6vk2u5xgmwlzsdx3g6w"
+                .to_string(),
+            reveal_fee: 2000,
+            reclaim_lock_time: 144,
+            memo: Some("0102".to_string()),
+        };
+        let commitment = commit
+            .commitment(peg_in_data(None, None)[..23].to_vec(), &sender_public_key())
+            .unwrap();
+        let tx = reveal_tx(
+            &commitment,
+            OutPoint::null(),
+            [(peg_wallet_script(), 1337)],
+            Network::Testnet,
+        )
+        .unwrap();
+
+        let Operation::Deposit {
+            recipient,
+            amount,
+            reveal_fee,
+            memo,
+            ..
+        } = decode_operation(&tx, Network::Testnet).unwrap()
+        else {
+            panic!("Not decoded as a deposit");
+        };
+        assert_eq!(recipient, stacks_address().to_string());
+        assert_eq!(amount, 1337);
+        assert_eq!(reveal_fee, Some(2000));
+        assert_eq!(memo, "0102");
+    }
+}
@@ -1,21 +1,36 @@
 use std::{io::stdout, iter::once, str::FromStr};
 
 use anyhow::anyhow;
-use bdk::{database::MemoryDatabase, SignOptions, Wallet};
+use bdk::{database::MemoryDatabase, Wallet};
 use bitcoin::{
     psbt::{serialize::Serialize, PartiallySignedTransaction},
-    Address as BitcoinAddress, Network, PrivateKey,
+    Address as BitcoinAddress, Network, OutPoint, PublicKey, Script,
+};
+use blockstack_lib::{
+    types::{chainstate::StacksAddress, Address},
+    vm::ContractName,
 };
-use blockstack_lib::types::{chainstate::StacksAddress, Address};
 use clap::Parser;
 
-use crate::commands::utils;
+use crate::commands::commit_reveal::{
+    commit_psbt, reclaim_key, reveal_tx, CommitArgs, CommitTransactionData, OPERATION_DATA_LENGTH,
+};
+use crate::commands::utils::{self, BackendArgs, FeeArgs, FundingArgs};
+
+/// Principal type byte of a standard principal
+pub const STANDARD_PRINCIPAL: u8 = 0x05;
+
+/// Principal type byte of a contract principal
+pub const CONTRACT_PRINCIPAL: u8 = 0x06;
+
+/// The longest contract name that fits in the committed deposit data, after the op code,
+/// principal type, address version, address hash and name length
+const MAX_CONTRACT_NAME_LENGTH: usize = OPERATION_DATA_LENGTH - 24;
 
 #[derive(Parser, Debug, Clone)]
 pub struct DepositArgs {
-    /// P2WPKH BTC private key in WIF format
-    #[clap(short, long)]
-    wif: String,
+    #[command(flatten)]
+    funding: FundingArgs,
 
     /// Stacks address that will receive sBTC
     #[clap(short, long)]
@@ -28,33 +43,75 @@ pub struct DepositArgs {
     /// Dkg wallet address
     #[clap(short, long)]
     dkg_wallet: String,
+
+    #[command(flatten)]
+    fee: FeeArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DepositCommitArgs {
+    #[command(flatten)]
+    funding: FundingArgs,
+
+    /// Public key in hex that can reclaim the commitment, defaults to the key of a WIF or
+    /// public key funding wallet
+    #[clap(long)]
+    reclaim_public_key: Option<String>,
+
+    /// Stacks principal that will receive sBTC, either an address or an address.contract-name
+    #[clap(short, long)]
+    recipient: String,
+
+    /// The amount of sats to send
+    #[clap(short, long)]
+    amount: u64,
+
+    #[command(flatten)]
+    commit: CommitArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DepositRevealArgs {
+    /// The commit output to reveal, as txid:vout
+    #[clap(short, long)]
+    outpoint: OutPoint,
+
+    /// Public key in hex that can reclaim the commitment, as given to the commit
+    #[clap(long)]
+    reclaim_public_key: String,
+
+    /// Stacks principal that will receive sBTC, either an address or an address.contract-name
+    #[clap(short, long)]
+    recipient: String,
+
+    /// The amount of sats sent
+    #[clap(short, long)]
+    amount: u64,
+
+    #[command(flatten)]
+    commit: CommitArgs,
+
+    /// The network the commitment was made on
+    #[clap(short, long, default_value_t = Network::Testnet)]
+    network: Network,
 }
 
-pub fn build_deposit_tx(deposit: &DepositArgs) -> anyhow::Result<()> {
-    let private_key = PrivateKey::from_wif(&deposit.wif)?;
-    let wallet = utils::setup_wallet(private_key)?;
+pub fn build_deposit_tx(deposit: &DepositArgs, backend: &BackendArgs) -> anyhow::Result<()> {
+    let wallet = deposit.funding.wallet(backend)?;
     let recipient = StacksAddress::from_string(&deposit.recipient)
         .ok_or(anyhow::anyhow!("Could not parse recipient Stacks address"))?;
     let dkg_address = BitcoinAddress::from_str(&deposit.dkg_wallet)?;
 
-    let mut psbt = deposit_psbt(
+    let psbt = deposit_psbt(
         &wallet,
         &recipient,
         &dkg_address,
         deposit.amount,
-        &private_key.network,
+        &deposit.fee,
+        &wallet.network(),
     )?;
 
-    wallet.sign(&mut psbt, SignOptions::default())?;
-    let tx = psbt.extract_tx();
-
-    serde_json::to_writer_pretty(
-        stdout(),
-        &utils::TransactionData {
-            tx_id: tx.txid().to_string(),
-            tx_hex: array_bytes::bytes2hex("", tx.serialize()),
-        },
-    )?;
+    serde_json::to_writer_pretty(stdout(), &deposit.funding.finish(&wallet, psbt)?)?;
 
     Ok(())
 }
@@ -64,9 +121,11 @@ fn deposit_psbt(
     recipient: &StacksAddress,
     dkg_address: &BitcoinAddress,
     amount: u64,
+    fee: &FeeArgs,
     network: &Network,
 ) -> anyhow::Result<PartiallySignedTransaction> {
     let mut tx_builder = wallet.build_tx();
+    fee.apply(&mut tx_builder);
 
     let op_return_script = utils::build_op_return_script(&deposit_data(recipient, network));
     let dkg_script = dkg_address.script_pubkey();
@@ -101,3 +160,190 @@ fn deposit_data(recipient: &StacksAddress, network: &Network) -> Vec<u8> {
         .chain(recipient.to_bytes())
         .collect()
 }
+
+pub fn build_deposit_commit_tx(
+    deposit: &DepositCommitArgs,
+    backend: &BackendArgs,
+) -> anyhow::Result<()> {
+    let reclaim_key = reclaim_key(deposit.reclaim_public_key.as_deref(), &deposit.funding)?;
+    let commitment = deposit
+        .commit
+        .commitment(deposit_reveal_data(&deposit.recipient)?, &reclaim_key)?;
+    deposit_reveal_output(&deposit.commit.dkg_wallet, deposit.amount)?;
+
+    let wallet = deposit.funding.wallet(backend)?;
+    let psbt = commit_psbt(
+        &wallet,
+        &commitment,
+        deposit.amount + deposit.commit.reveal_fee,
+    )?;
+
+    serde_json::to_writer_pretty(
+        stdout(),
+        &CommitTransactionData {
+            transaction: deposit.funding.finish(&wallet, psbt)?,
+            commit_address: commitment.address(wallet.network()).to_string(),
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn build_deposit_reveal_tx(deposit: &DepositRevealArgs) -> anyhow::Result<()> {
+    let reclaim_key = PublicKey::from_str(&deposit.reclaim_public_key)?;
+    let commitment = deposit
+        .commit
+        .commitment(deposit_reveal_data(&deposit.recipient)?, &reclaim_key)?;
+
+    let tx = reveal_tx(
+        &commitment,
+        deposit.outpoint,
+        [deposit_reveal_output(
+            &deposit.commit.dkg_wallet,
+            deposit.amount,
+        )?],
+        deposit.network,
+    )?;
+
+    serde_json::to_writer_pretty(
+        stdout(),
+        &utils::TransactionData {
+            tx_id: tx.txid().to_string(),
+            tx_hex: array_bytes::bytes2hex("", tx.serialize()),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// The output of a deposit reveal transaction following its OP_RETURN output.
+fn deposit_reveal_output(dkg_wallet: &str, amount: u64) -> anyhow::Result<(Script, u64)> {
+    let dkg_script = BitcoinAddress::from_str(dkg_wallet)?.script_pubkey();
+    let dust_amount = dkg_script.dust_value().to_sat();
+
+    if amount < dust_amount {
+        return Err(anyhow!(
+            "Provided amount {} is less than the dust amount: {}",
+            amount,
+            dust_amount
+        ));
+    }
+
+    Ok((dkg_script, amount))
+}
+
+/// The deposit data committed to in the reveal script, for a standard or contract principal.
+fn deposit_reveal_data(recipient: &str) -> anyhow::Result<Vec<u8>> {
+    let (address, contract_name) = match recipient.split_once('.') {
+        Some((address, name)) => {
+            let name = ContractName::try_from(name.to_string())
+                .map_err(|_| anyhow!("Invalid contract name: {}", name))?;
+            if name.as_str().len() > MAX_CONTRACT_NAME_LENGTH {
+                return Err(anyhow!(
+                    "Contract name {} is {} characters long, more than the {} that fit in the deposit data",
+                    name.as_str(),
+                    name.as_str().len(),
+                    MAX_CONTRACT_NAME_LENGTH
+                ));
+            }
+            (address, Some(name))
+        }
+        None => (recipient, None),
+    };
+    let address = StacksAddress::from_string(address)
+        .ok_or(anyhow!("Could not parse recipient Stacks address"))?;
+
+    let mut data = vec![b'<'];
+    match contract_name {
+        Some(name) => {
+            data.push(CONTRACT_PRINCIPAL);
+            data.push(address.version);
+            data.extend(address.to_bytes());
+            data.push(name.as_str().len() as u8);
+            data.extend(name.as_str().as_bytes());
+        }
+        None => {
+            data.push(STANDARD_PRINCIPAL);
+            data.push(address.version);
+            data.extend(address.to_bytes());
+        }
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use blockstack_lib::{address::C32_ADDRESS_VERSION_TESTNET_SINGLESIG, util::hash::Hash160};
+
+    use super::*;
+
+    fn recipient() -> StacksAddress {
+        StacksAddress::new(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, Hash160([1; 20]))
+    }
+
+    #[test]
+    fn test_deposit_reveal_data() {
+        let data = deposit_reveal_data(&recipient().to_string()).unwrap();
+        let expected: Vec<u8> = [
+            b'<',
+            STANDARD_PRINCIPAL,
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        ]
+        .into_iter()
+        .chain([1; 20])
+        .collect();
+        assert_eq!(data, expected);
+
+        let contract = format!("{}.sbtc-receiver-contract", recipient());
+        let data = deposit_reveal_data(&contract).unwrap();
+        let expected: Vec<u8> = [
+            b'<',
+            CONTRACT_PRINCIPAL,
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        ]
+        .into_iter()
+        .chain([1; 20])
+        .chain([22])
+        .chain(*b"sbtc-receiver-contract")
+        .collect();
+        assert_eq!(data, expected);
+
+        assert!(deposit_reveal_data("not-an-address").is_err());
+        assert!(deposit_reveal_data(&format!("{}.1nvalid", recipient())).is_err());
+    }
+
+    #[test]
+    fn test_deposit_reveal_data_contract_name_length() {
+        let name = "a".repeat(MAX_CONTRACT_NAME_LENGTH);
+        let data = deposit_reveal_data(&format!("{}.{}", recipient(), name)).unwrap();
+        assert_eq!(data.len(), OPERATION_DATA_LENGTH);
+
+        // Longer names are valid contract names, but do not fit in the data
+        let name = "a".repeat(MAX_CONTRACT_NAME_LENGTH + 1);
+        assert!(ContractName::try_from(name.clone()).is_ok());
+        let error = deposit_reveal_data(&format!("{}.{}", recipient(), name)).unwrap_err();
+        assert!(error.to_string().contains("characters long"));
+    }
+
+    #[test]
+    fn test_deposit_commitment_data() {
+        let commit = CommitArgs {
+            dkg_wallet: String::new(),
+            reveal_fee: 1000,
+            reclaim_lock_time: 144,
+            memo: None,
+        };
+        let contract = format!("{}.sbtc-receiver-contract", recipient());
+        let data = commit
+            .commitment_data(deposit_reveal_data(&contract).unwrap())
+            .unwrap();
+        // peg_in_data in the test-vectors crate, with a fee subsidy of 1000 sats
+        let expected = array_bytes::hex2bytes(
+            "3c061a010101010101010101010101010101010101010116736274632d72656365697665722d636f6e74726163740000000000000000000000000000000000000000000000000000000000000000000000000000\
+             03e8",
+        )
+        .unwrap();
+        assert_eq!(data, expected);
+    }
+}
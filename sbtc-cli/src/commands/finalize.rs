@@ -0,0 +1,154 @@
+use std::{io::stdout, str::FromStr};
+
+use anyhow::anyhow;
+use bdk::miniscript::psbt::PsbtExt;
+use bitcoin::{
+    psbt::{serialize::Serialize, PartiallySignedTransaction},
+    secp256k1::Secp256k1,
+    Transaction,
+};
+use clap::Parser;
+
+use crate::commands::utils::TransactionData;
+
+#[derive(Parser, Debug, Clone)]
+pub struct FinalizeArgs {
+    /// The signed PSBT in base64
+    psbt: String,
+}
+
+pub fn finalize_psbt(finalize: &FinalizeArgs) -> anyhow::Result<()> {
+    let psbt = PartiallySignedTransaction::from_str(finalize.psbt.trim())?;
+    let tx = finalized_tx(psbt)?;
+
+    serde_json::to_writer_pretty(
+        stdout(),
+        &TransactionData {
+            tx_id: tx.txid().to_string(),
+            tx_hex: array_bytes::bytes2hex("", tx.serialize()),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Finalize the inputs of the signed PSBT and extract its transaction.
+fn finalized_tx(mut psbt: PartiallySignedTransaction) -> anyhow::Result<Transaction> {
+    let secp = Secp256k1::new();
+
+    // Signers may have finalized their inputs already
+    for index in 0..psbt.inputs.len() {
+        let input = &psbt.inputs[index];
+        if input.final_script_witness.is_none() && input.final_script_sig.is_none() {
+            psbt.finalize_inp_mut(&secp, index)
+                .map_err(|e| anyhow!("Failed to finalize input {}: {}", index, e))?;
+        }
+    }
+
+    Ok(psbt.extract_tx())
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        secp256k1::Message, util::sighash::SighashCache, EcdsaSig, EcdsaSighashType, OutPoint,
+        PackedLockTime, PrivateKey, Script, Sequence, TxIn, TxOut, Witness,
+    };
+
+    use super::*;
+
+    const WIF: &str = "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA";
+    const INPUT_AMOUNT: u64 = 10_000;
+
+    /// A PSBT spending a P2WPKH output of the WIF, and the script of that output.
+    fn p2wpkh_psbt() -> (PartiallySignedTransaction, Script) {
+        let public_key = PrivateKey::from_wif(WIF)
+            .unwrap()
+            .public_key(&Secp256k1::new());
+        let script_pubkey = Script::new_v0_p2wpkh(&public_key.wpubkey_hash().unwrap());
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::from_str(
+                    "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b:0",
+                )
+                .unwrap(),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: INPUT_AMOUNT - 1_000,
+                script_pubkey: script_pubkey.clone(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: INPUT_AMOUNT,
+            script_pubkey: script_pubkey.clone(),
+        });
+
+        (psbt, script_pubkey)
+    }
+
+    fn sign(psbt: &mut PartiallySignedTransaction, script_pubkey: &Script) -> EcdsaSig {
+        let secp = Secp256k1::new();
+        let private_key = PrivateKey::from_wif(WIF).unwrap();
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .segwit_signature_hash(
+                0,
+                &script_pubkey.p2wpkh_script_code().unwrap(),
+                INPUT_AMOUNT,
+                EcdsaSighashType::All,
+            )
+            .unwrap();
+        let signature = EcdsaSig::sighash_all(
+            secp.sign_ecdsa(&Message::from_slice(&sighash).unwrap(), &private_key.inner),
+        );
+        psbt.inputs[0]
+            .partial_sigs
+            .insert(private_key.public_key(&secp), signature);
+
+        signature
+    }
+
+    #[test]
+    fn test_finalize_signed_input() {
+        let (mut psbt, script_pubkey) = p2wpkh_psbt();
+        let signature = sign(&mut psbt, &script_pubkey);
+        let public_key = PrivateKey::from_wif(WIF)
+            .unwrap()
+            .public_key(&Secp256k1::new());
+
+        // The PSBT survives the base64 round trip through the command line
+        let psbt = PartiallySignedTransaction::from_str(&psbt.to_string()).unwrap();
+        let tx = finalized_tx(psbt).unwrap();
+
+        assert_eq!(
+            tx.input[0].witness.to_vec(),
+            vec![signature.to_vec(), public_key.to_bytes()]
+        );
+        assert!(tx.input[0].script_sig.is_empty());
+    }
+
+    #[test]
+    fn test_finalize_keeps_finalized_input() {
+        let (mut psbt, _) = p2wpkh_psbt();
+        let witness = Witness::from_vec(vec![vec![1], vec![2]]);
+        psbt.inputs[0].final_script_witness = Some(witness.clone());
+
+        let tx = finalized_tx(psbt).unwrap();
+
+        assert_eq!(tx.input[0].witness, witness);
+    }
+
+    #[test]
+    fn test_finalize_unsigned_input() {
+        let (psbt, _) = p2wpkh_psbt();
+
+        let error = finalized_tx(psbt).unwrap_err();
+
+        assert!(error.to_string().starts_with("Failed to finalize input 0"));
+    }
+}
@@ -9,6 +9,7 @@ use bdk::{
 use bitcoin::{
     schnorr::TweakedPublicKey,
     secp256k1::{rand::random, Secp256k1},
+    util::bip32::ExtendedPrivKey,
     Address as BitcoinAddress, Network, PrivateKey,
 };
 use blockstack_lib::{
@@ -94,12 +95,15 @@ fn parse_private_key_from_hex(private_key: &str, network: Network) -> anyhow::Re
 }
 
 fn private_key_from_mnemonic(network: Network, mnemonic: Mnemonic) -> anyhow::Result<PrivateKey> {
+    Ok(xprv_from_mnemonic(network, mnemonic)?.to_priv())
+}
+
+/// The BIP 32 master key of the mnemonic.
+pub fn xprv_from_mnemonic(network: Network, mnemonic: Mnemonic) -> anyhow::Result<ExtendedPrivKey> {
     let extended_key: ExtendedKey<BareCtx> = mnemonic.into_extended_key()?;
-    let private_key = extended_key
+    extended_key
         .into_xprv(network)
-        .ok_or(anyhow!("Could not create an extended private key"))?;
-
-    Ok(private_key.to_priv())
+        .ok_or(anyhow!("Could not create an extended private key"))
 }
 
 fn generate_credentials(
@@ -1,5 +1,10 @@
 pub mod broadcast;
+pub mod bump_fee;
+pub mod commit_reveal;
+pub mod decode;
 pub mod deposit;
+pub mod finalize;
 pub mod generate;
+pub mod status;
 pub mod utils;
 pub mod withdraw;
@@ -0,0 +1,448 @@
+use std::io::stdout;
+
+use anyhow::anyhow;
+use bdk::{
+    bitcoincore_rpc::{self, RpcApi},
+    electrum_client::{self, ElectrumApi},
+};
+use bitcoin::{Network, OutPoint, Transaction, Txid};
+use clap::Parser;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::commands::{
+    decode::{decode_operation, Operation},
+    utils::{bitcoind_rpc_credentials, BackendArgs},
+};
+
+/// The Stacks node queried when none is configured
+const DEFAULT_STACKS_NODE_URL: &str = "http://localhost:20443";
+
+/// The output of a withdrawal request spent by its fulfillment
+const FULFILLMENT_FEE_VOUT: u32 = 2;
+
+/// The number of transactions requested per page from the Stacks API, which allows at most 50
+const TRANSACTIONS_PAGE_SIZE: usize = 50;
+
+#[derive(Parser, Debug, Clone)]
+pub struct StatusArgs {
+    /// The network the operation was made on
+    #[clap(short, long, default_value_t = Network::Testnet)]
+    network: Network,
+
+    /// Stacks node RPC URL
+    #[clap(long, env = "SBTC_STACKS_NODE_URL", default_value = DEFAULT_STACKS_NODE_URL)]
+    stacks_node_url: Url,
+
+    /// The sBTC contract, as address.contract-name, used to find the mint or burn transaction.
+    /// Requires the Stacks node URL to serve the Stacks API
+    #[clap(long)]
+    sbtc_contract: Option<String>,
+
+    /// The id of the deposit or withdrawal request transaction
+    txid: Txid,
+}
+
+#[derive(Serialize, Debug)]
+struct OperationStatus {
+    tx_id: String,
+    #[serde(flatten)]
+    operation: Operation,
+    bitcoin: Confirmation,
+    stacks: Option<StacksStatus>,
+    fulfillment: Option<FulfillmentStatus>,
+}
+
+#[derive(Serialize, Debug)]
+struct Confirmation {
+    block_height: Option<u64>,
+    confirmations: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct StacksStatus {
+    /// Whether the Stacks node registered the operation, which the signers then mint or burn for
+    registered: bool,
+    tx_id: Option<String>,
+    tx_status: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct FulfillmentStatus {
+    fulfilled: bool,
+    tx_id: Option<String>,
+    bitcoin: Option<Confirmation>,
+}
+
+enum BitcoinClient {
+    Electrum(electrum_client::Client),
+    Bitcoind(bitcoincore_rpc::Client),
+}
+
+impl BitcoinClient {
+    fn new(backend: &BackendArgs) -> anyhow::Result<Self> {
+        let Some(bitcoind_rpc) = &backend.bitcoind_rpc else {
+            return Ok(Self::Electrum(electrum_client::Client::new(
+                &backend.electrum_url,
+            )?));
+        };
+
+        let (url, credentials) = bitcoind_rpc_credentials(bitcoind_rpc)?;
+        let auth = match credentials {
+            Some((username, password)) => bitcoincore_rpc::Auth::UserPass(username, password),
+            None => bitcoincore_rpc::Auth::None,
+        };
+        Ok(Self::Bitcoind(bitcoincore_rpc::Client::new(&url, auth)?))
+    }
+
+    fn transaction(&self, txid: &Txid) -> anyhow::Result<Transaction> {
+        Ok(match self {
+            Self::Electrum(client) => client.transaction_get(txid)?,
+            Self::Bitcoind(client) => client.get_raw_transaction(txid, None)?,
+        })
+    }
+
+    fn confirmation(&self, tx: &Transaction) -> anyhow::Result<Confirmation> {
+        let txid = tx.txid();
+        match self {
+            Self::Electrum(client) => {
+                let script = tx
+                    .output
+                    .iter()
+                    .find(|output| !output.script_pubkey.is_op_return())
+                    .map(|output| &output.script_pubkey)
+                    .ok_or(anyhow!("Transaction {} has no spendable output", txid))?;
+                let block_height = client
+                    .script_get_history(script)?
+                    .into_iter()
+                    .find(|entry| entry.tx_hash == txid && entry.height > 0)
+                    .map(|entry| entry.height as u64);
+                let tip_height = client.block_headers_subscribe()?.height as u64;
+
+                Ok(Confirmation {
+                    block_height,
+                    confirmations: block_height
+                        .map(|height| tip_height.saturating_sub(height) + 1)
+                        .unwrap_or_default(),
+                })
+            }
+            Self::Bitcoind(client) => {
+                let info = client.get_raw_transaction_info(&txid, None)?;
+                let block_height = match &info.blockhash {
+                    Some(block_hash) => {
+                        Some(client.get_block_header_info(block_hash)?.height as u64)
+                    }
+                    None => None,
+                };
+
+                Ok(Confirmation {
+                    block_height,
+                    confirmations: info.confirmations.unwrap_or_default().into(),
+                })
+            }
+        }
+    }
+
+    /// Find the transaction spending the fulfillment fee output of the withdrawal request.
+    fn fulfillment(&self, request: &Transaction) -> anyhow::Result<FulfillmentStatus> {
+        let fulfillment_outpoint = OutPoint::new(request.txid(), FULFILLMENT_FEE_VOUT);
+        let fulfillment_output =
+            request
+                .output
+                .get(FULFILLMENT_FEE_VOUT as usize)
+                .ok_or(anyhow!(
+                    "Withdrawal request is missing its fulfillment fee output"
+                ))?;
+
+        match self {
+            Self::Electrum(client) => {
+                for entry in client.script_get_history(&fulfillment_output.script_pubkey)? {
+                    if entry.tx_hash == request.txid() {
+                        continue;
+                    }
+                    let tx = client.transaction_get(&entry.tx_hash)?;
+                    if tx
+                        .input
+                        .iter()
+                        .any(|input| input.previous_output == fulfillment_outpoint)
+                    {
+                        return Ok(FulfillmentStatus {
+                            fulfilled: true,
+                            tx_id: Some(tx.txid().to_string()),
+                            bitcoin: Some(self.confirmation(&tx)?),
+                        });
+                    }
+                }
+
+                Ok(FulfillmentStatus {
+                    fulfilled: false,
+                    tx_id: None,
+                    bitcoin: None,
+                })
+            }
+            // bitcoind can only tell whether the output has been spent, not by which transaction
+            Self::Bitcoind(client) => Ok(FulfillmentStatus {
+                fulfilled: client
+                    .get_tx_out(&request.txid(), FULFILLMENT_FEE_VOUT, Some(true))?
+                    .is_none(),
+                tx_id: None,
+                bitcoin: None,
+            }),
+        }
+    }
+}
+
+pub fn operation_status(status: &StatusArgs, backend: &BackendArgs) -> anyhow::Result<()> {
+    let bitcoin_client = BitcoinClient::new(backend)?;
+    let tx = bitcoin_client.transaction(&status.txid)?;
+    let operation = decode_operation(&tx, status.network)?;
+    let (burn_op, contract_function) = match &operation {
+        Operation::Deposit { .. } => ("peg_in", "mint!"),
+        Operation::WithdrawalRequest { .. } => ("peg_out_request", "burn!"),
+        _ => {
+            return Err(anyhow!(
+                "Transaction {} is neither a deposit nor a withdrawal request",
+                status.txid
+            ))
+        }
+    };
+
+    let bitcoin = bitcoin_client.confirmation(&tx)?;
+    let stacks = match bitcoin.block_height {
+        Some(block_height) => Some(stacks_status(
+            status,
+            block_height,
+            burn_op,
+            contract_function,
+        )?),
+        None => None,
+    };
+    let fulfillment = match &operation {
+        Operation::WithdrawalRequest { .. } => Some(bitcoin_client.fulfillment(&tx)?),
+        _ => None,
+    };
+
+    serde_json::to_writer_pretty(
+        stdout(),
+        &OperationStatus {
+            tx_id: status.txid.to_string(),
+            operation,
+            bitcoin,
+            stacks,
+            fulfillment,
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Whether the Stacks node registered the operation at its burn block, and the mint or burn
+/// transaction calling the sBTC contract with the operation's txid, if a contract is given.
+fn stacks_status(
+    status: &StatusArgs,
+    block_height: u64,
+    burn_op: &str,
+    contract_function: &str,
+) -> anyhow::Result<StacksStatus> {
+    let txid = status.txid.to_string();
+    let client = reqwest::blocking::Client::new();
+
+    let burn_ops: Value = client
+        .get(
+            status
+                .stacks_node_url
+                .join(&format!("/v2/burn_ops/{}/{}", block_height, burn_op))?,
+        )
+        .send()?
+        .error_for_status()?
+        .json()?;
+    let registered = burn_ops[burn_op]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|op| op["txid"].as_str() == Some(txid.as_str()));
+
+    let Some(sbtc_contract) = &status.sbtc_contract else {
+        return Ok(StacksStatus {
+            registered,
+            tx_id: None,
+            tx_status: None,
+        });
+    };
+
+    let contract_call = find_contract_call(
+        |offset| {
+            Ok(client
+                .get(status.stacks_node_url.join(&format!(
+                    "/extended/v1/address/{}/transactions?limit={}&offset={}",
+                    sbtc_contract, TRANSACTIONS_PAGE_SIZE, offset
+                ))?)
+                .send()?
+                .error_for_status()?
+                .json()?)
+        },
+        contract_function,
+        &txid,
+    )?;
+
+    Ok(StacksStatus {
+        registered,
+        tx_id: contract_call
+            .as_ref()
+            .and_then(|tx| tx["tx_id"].as_str().map(ToString::to_string)),
+        tx_status: contract_call
+            .as_ref()
+            .and_then(|tx| tx["tx_status"].as_str().map(ToString::to_string)),
+    })
+}
+
+/// Find the call of the contract function with the txid among its arguments, going through the
+/// pages of the contract's transactions until it is found or none are left.
+fn find_contract_call(
+    mut transactions_page: impl FnMut(usize) -> anyhow::Result<Value>,
+    contract_function: &str,
+    txid: &str,
+) -> anyhow::Result<Option<Value>> {
+    let mut offset = 0;
+    loop {
+        let page = transactions_page(offset)?;
+        let results = page["results"].as_array().cloned().unwrap_or_default();
+        if let Some(contract_call) = results
+            .iter()
+            .find(|tx| is_contract_call(tx, contract_function, txid))
+        {
+            return Ok(Some(contract_call.clone()));
+        }
+
+        offset += results.len();
+        let total = page["total"].as_u64().unwrap_or_default() as usize;
+        if results.is_empty() || offset >= total {
+            return Ok(None);
+        }
+    }
+}
+
+fn is_contract_call(tx: &Value, contract_function: &str, txid: &str) -> bool {
+    let call = &tx["contract_call"];
+    call["function_name"].as_str() == Some(contract_function)
+        && call["function_args"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|arg| arg["repr"].as_str().unwrap_or_default().contains(txid))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    fn contract_call(tx_id: &str, function_name: &str, txid: &str) -> Value {
+        json!({
+            "tx_id": tx_id,
+            "tx_status": "success",
+            "contract_call": {
+                "function_name": function_name,
+                "function_args": [
+                    { "repr": "u1337" },
+                    { "repr": format!("0x{}", txid) },
+                ],
+            },
+        })
+    }
+
+    /// The contract's transactions served in pages as by the Stacks API.
+    fn pages(transactions: Vec<Value>) -> impl FnMut(usize) -> anyhow::Result<Value> {
+        move |offset| {
+            Ok(json!({
+                "limit": TRANSACTIONS_PAGE_SIZE,
+                "offset": offset,
+                "total": transactions.len(),
+                "results": transactions
+                    .iter()
+                    .skip(offset)
+                    .take(TRANSACTIONS_PAGE_SIZE)
+                    .collect::<Vec<_>>(),
+            }))
+        }
+    }
+
+    fn other_transactions(count: usize) -> Vec<Value> {
+        (0..count)
+            .map(|index| contract_call(&format!("0x{:064x}", index), "mint!", &"00".repeat(32)))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_contract_call_on_later_page() {
+        let mut transactions = other_transactions(2 * TRANSACTIONS_PAGE_SIZE + 3);
+        transactions.push(contract_call("0xabcd", "mint!", TXID));
+
+        let mut offsets = vec![];
+        let mut transactions_page = pages(transactions);
+        let found = find_contract_call(
+            |offset| {
+                offsets.push(offset);
+                transactions_page(offset)
+            },
+            "mint!",
+            TXID,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(found["tx_id"], "0xabcd");
+        assert_eq!(
+            offsets,
+            vec![0, TRANSACTIONS_PAGE_SIZE, 2 * TRANSACTIONS_PAGE_SIZE]
+        );
+    }
+
+    #[test]
+    fn test_find_contract_call_stops_after_last_page() {
+        let mut offsets = vec![];
+        let mut transactions_page = pages(other_transactions(TRANSACTIONS_PAGE_SIZE + 1));
+        let found = find_contract_call(
+            |offset| {
+                offsets.push(offset);
+                transactions_page(offset)
+            },
+            "mint!",
+            TXID,
+        )
+        .unwrap();
+
+        assert!(found.is_none());
+        assert_eq!(offsets, vec![0, TRANSACTIONS_PAGE_SIZE]);
+
+        let found = find_contract_call(pages(vec![]), "mint!", TXID).unwrap();
+        assert!(found.is_none());
+    }
+
+    #[test]
+    fn test_find_contract_call_matches_function() {
+        let transactions = vec![
+            contract_call("0x01", "burn!", TXID),
+            contract_call("0x02", "mint!", TXID),
+        ];
+
+        let found = find_contract_call(pages(transactions.clone()), "mint!", TXID)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found["tx_id"], "0x02");
+        let found = find_contract_call(pages(transactions), "burn!", TXID)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found["tx_id"], "0x01");
+    }
+
+    #[test]
+    fn test_find_contract_call_propagates_errors() {
+        let result = find_contract_call(|_| Err(anyhow!("Stacks API unavailable")), "mint!", TXID);
+        assert!(result.is_err());
+    }
+}
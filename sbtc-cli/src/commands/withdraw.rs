@@ -1,26 +1,75 @@
 use std::{io::stdout, iter::once, str::FromStr};
 
 use anyhow::anyhow;
-use bdk::{database::MemoryDatabase, SignOptions, Wallet};
+use bdk::keys::bip39::Mnemonic;
+use bdk::{database::MemoryDatabase, Wallet};
 use bitcoin::{
-    psbt::{serialize::Serialize, PartiallySignedTransaction},
-    secp256k1::{Message, Secp256k1},
-    Address as BitcoinAddress, Network, PrivateKey,
+    psbt::{serialize::Serialize as _, PartiallySignedTransaction},
+    secp256k1::{ecdsa::RecoveryId, Message, Secp256k1},
+    util::bip32::DerivationPath,
+    Address as BitcoinAddress, Network, OutPoint, PrivateKey, PublicKey, Script,
 };
 use clap::Parser;
+use serde::Serialize;
 
-use crate::commands::utils::TransactionData;
-use crate::commands::utils::{build_op_return_script, magic_bytes, reorder_outputs, setup_wallet};
+use crate::commands::commit_reveal::{
+    commit_psbt, reclaim_key, reveal_tx, CommitArgs, CommitTransactionData,
+};
+use crate::commands::generate::xprv_from_mnemonic;
+use crate::commands::utils::{build_op_return_script, magic_bytes, reorder_outputs};
+use crate::commands::utils::{
+    stacks_address, BackendArgs, FeeArgs, FundedTransaction, FundingArgs, SecretSource,
+    TransactionData,
+};
+
+/// Derivation path of the first account of Stacks wallets
+const STACKS_DERIVATION_PATH: &str = "m/44'/5757'/0'/0/0";
+
+#[derive(Parser, Debug, Clone)]
+#[command(group(clap::ArgGroup::new("sender").required(true)))]
+pub struct SenderArgs {
+    /// Stacks private key of the sBTC sender in hex, as in the stacks-coordinator config, read
+    /// from env:<VARIABLE>, file:<PATH> or - for standard input
+    #[clap(long, group = "sender")]
+    sender_private_key: Option<SecretSource>,
+
+    /// BIP 39 mnemonic of the sBTC sender's Stacks wallet, whose first account is used, read
+    /// from env:<VARIABLE>, file:<PATH> or - for standard input
+    #[clap(long, group = "sender")]
+    sender_mnemonic: Option<SecretSource>,
+}
+
+impl SenderArgs {
+    /// The private key signing the withdrawal request.
+    fn private_key(&self, network: Network) -> anyhow::Result<PrivateKey> {
+        match (&self.sender_private_key, &self.sender_mnemonic) {
+            (Some(private_key), _) => parse_stacks_private_key(&private_key.read()?, network),
+            (None, Some(mnemonic)) => {
+                let xprv = xprv_from_mnemonic(network, Mnemonic::parse(mnemonic.read()?)?)?;
+                let path = DerivationPath::from_str(STACKS_DERIVATION_PATH)?;
+                Ok(xprv.derive_priv(&Secp256k1::new(), &path)?.to_priv())
+            }
+            (None, None) => Err(anyhow!("No sender key provided")),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WithdrawalData {
+    #[serde(flatten)]
+    transaction: FundedTransaction,
+    sender_stacks_address: String,
+    /// The request signature with the recovery id last rather than first
+    clarity_signature: String,
+}
 
 #[derive(Parser, Debug, Clone)]
 pub struct WithdrawalArgs {
-    /// P2WPKH BTC private key in WIF format
-    #[clap(short, long)]
-    wif: String,
+    #[command(flatten)]
+    funding: FundingArgs,
 
-    /// P2WPKH sBTC sender private key in WIF format
-    #[clap(short, long)]
-    sender_wif: String,
+    #[command(flatten)]
+    sender: SenderArgs,
 
     /// Bitcoin address that will receive BTC
     #[clap(short, long)]
@@ -37,29 +86,173 @@ pub struct WithdrawalArgs {
     /// Dkg wallet address
     #[clap(short, long)]
     dkg_wallet: String,
+
+    #[command(flatten)]
+    fee: FeeArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct WithdrawalCommitArgs {
+    #[command(flatten)]
+    funding: FundingArgs,
+
+    /// Public key in hex that can reclaim the commitment, defaults to the key of a WIF or
+    /// public key funding wallet
+    #[clap(long)]
+    reclaim_public_key: Option<String>,
+
+    #[command(flatten)]
+    sender: SenderArgs,
+
+    /// Bitcoin address that will receive BTC
+    #[clap(short, long)]
+    recipient: String,
+
+    /// The amount of sats to send
+    #[clap(short, long)]
+    amount: u64,
+
+    /// The amount of sats to send as the fulfillment fee
+    #[clap(short, long)]
+    fulfillment_fee: u64,
+
+    #[command(flatten)]
+    commit: CommitArgs,
 }
 
-pub fn build_withdrawal_tx(withdrawal: &WithdrawalArgs) -> anyhow::Result<()> {
-    let private_key = PrivateKey::from_wif(&withdrawal.wif)?;
+#[derive(Parser, Debug, Clone)]
+pub struct WithdrawalRevealArgs {
+    /// The commit output to reveal, as txid:vout
+    #[clap(short, long)]
+    outpoint: OutPoint,
+
+    /// Public key in hex that can reclaim the commitment, as given to the commit
+    #[clap(long)]
+    reclaim_public_key: String,
+
+    #[command(flatten)]
+    sender: SenderArgs,
 
-    let wallet = setup_wallet(private_key)?;
+    /// Bitcoin address that will receive BTC
+    #[clap(short, long)]
+    recipient: String,
 
-    let sender_private_key = PrivateKey::from_wif(&withdrawal.sender_wif)?;
+    /// The amount of sats sent
+    #[clap(short, long)]
+    amount: u64,
+
+    /// The amount of sats sent as the fulfillment fee
+    #[clap(short, long)]
+    fulfillment_fee: u64,
+
+    #[command(flatten)]
+    commit: CommitArgs,
+
+    /// The network the commitment was made on
+    #[clap(short, long, default_value_t = Network::Testnet)]
+    network: Network,
+}
+
+pub fn build_withdrawal_tx(
+    withdrawal: &WithdrawalArgs,
+    backend: &BackendArgs,
+) -> anyhow::Result<()> {
+    let wallet = withdrawal.funding.wallet(backend)?;
+
+    let sender_private_key = withdrawal.sender.private_key(wallet.network())?;
     let recipient = BitcoinAddress::from_str(&withdrawal.recipient)?;
     let dkg_address = BitcoinAddress::from_str(&withdrawal.dkg_wallet)?;
+    let (recovery_id, signature) =
+        sign_withdrawal(&recipient, withdrawal.amount, &sender_private_key);
 
-    let mut psbt = withdrawal_psbt(
+    let psbt = withdrawal_psbt(
         &wallet,
         &sender_private_key,
         &recipient,
         &dkg_address,
         withdrawal.amount,
         withdrawal.fulfillment_fee,
-        &private_key.network,
+        &withdrawal.fee,
     )?;
 
-    wallet.sign(&mut psbt, SignOptions::default())?;
-    let tx = psbt.extract_tx();
+    serde_json::to_writer_pretty(
+        stdout(),
+        &WithdrawalData {
+            transaction: withdrawal.funding.finish(&wallet, psbt)?,
+            sender_stacks_address: stacks_address(
+                &sender_private_key.public_key(&Secp256k1::new()),
+                wallet.network(),
+            )?
+            .to_string(),
+            clarity_signature: array_bytes::bytes2hex(
+                "",
+                clarity_signature(recovery_id, signature),
+            ),
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn build_withdrawal_commit_tx(
+    withdrawal: &WithdrawalCommitArgs,
+    backend: &BackendArgs,
+) -> anyhow::Result<()> {
+    let reclaim_key = reclaim_key(
+        withdrawal.reclaim_public_key.as_deref(),
+        &withdrawal.funding,
+    )?;
+    let sender_private_key = withdrawal.sender.private_key(withdrawal.funding.network)?;
+    let recipient = BitcoinAddress::from_str(&withdrawal.recipient)?;
+
+    let commitment = withdrawal.commit.commitment(
+        withdrawal_request_data(&recipient, withdrawal.amount, &sender_private_key),
+        &reclaim_key,
+    )?;
+    let reveal_outputs = withdrawal_reveal_outputs(
+        &recipient,
+        &withdrawal.commit.dkg_wallet,
+        withdrawal.fulfillment_fee,
+    )?;
+
+    let wallet = withdrawal.funding.wallet(backend)?;
+    let psbt = commit_psbt(
+        &wallet,
+        &commitment,
+        reveal_outputs.iter().map(|(_, amount)| amount).sum::<u64>() + withdrawal.commit.reveal_fee,
+    )?;
+
+    serde_json::to_writer_pretty(
+        stdout(),
+        &CommitTransactionData {
+            transaction: withdrawal.funding.finish(&wallet, psbt)?,
+            commit_address: commitment.address(wallet.network()).to_string(),
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn build_withdrawal_reveal_tx(withdrawal: &WithdrawalRevealArgs) -> anyhow::Result<()> {
+    let reclaim_key = PublicKey::from_str(&withdrawal.reclaim_public_key)?;
+    let sender_private_key = withdrawal.sender.private_key(withdrawal.network)?;
+    let recipient = BitcoinAddress::from_str(&withdrawal.recipient)?;
+
+    let commitment = withdrawal.commit.commitment(
+        withdrawal_request_data(&recipient, withdrawal.amount, &sender_private_key),
+        &reclaim_key,
+    )?;
+
+    let tx = reveal_tx(
+        &commitment,
+        withdrawal.outpoint,
+        withdrawal_reveal_outputs(
+            &recipient,
+            &withdrawal.commit.dkg_wallet,
+            withdrawal.fulfillment_fee,
+        )?,
+        withdrawal.network,
+    )?;
 
     serde_json::to_writer_pretty(
         stdout(),
@@ -72,6 +265,32 @@ pub fn build_withdrawal_tx(withdrawal: &WithdrawalArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The outputs of a withdrawal reveal transaction following its OP_RETURN output:
+/// dust to the recipient and the fulfillment fee to the dkg wallet.
+fn withdrawal_reveal_outputs(
+    recipient: &BitcoinAddress,
+    dkg_wallet: &str,
+    fulfillment_fee: u64,
+) -> anyhow::Result<[(Script, u64); 2]> {
+    let recipient_script = recipient.script_pubkey();
+    let dkg_wallet_script = BitcoinAddress::from_str(dkg_wallet)?.script_pubkey();
+    let recipient_dust_amount = recipient_script.dust_value().to_sat();
+    let dkg_wallet_dust_amount = dkg_wallet_script.dust_value().to_sat();
+
+    if fulfillment_fee < dkg_wallet_dust_amount {
+        return Err(anyhow!(
+            "Provided fulfillment fee {} is less than the dust amount: {}",
+            fulfillment_fee,
+            dkg_wallet_dust_amount
+        ));
+    }
+
+    Ok([
+        (recipient_script, recipient_dust_amount),
+        (dkg_wallet_script, fulfillment_fee),
+    ])
+}
+
 fn withdrawal_psbt(
     wallet: &Wallet<MemoryDatabase>,
     sender_private_key: &PrivateKey,
@@ -79,7 +298,7 @@ fn withdrawal_psbt(
     dkg_address: &BitcoinAddress,
     amount: u64,
     fulfillment_fee: u64,
-    network: &Network,
+    fee: &FeeArgs,
 ) -> anyhow::Result<PartiallySignedTransaction> {
     let recipient_script = recipient.script_pubkey();
     let dkg_wallet_script = dkg_address.script_pubkey();
@@ -100,10 +319,11 @@ fn withdrawal_psbt(
         recipient,
         amount,
         sender_private_key,
-        network,
+        &wallet.network(),
     ));
 
     let mut tx_builder = wallet.build_tx();
+    fee.apply(&mut tx_builder);
 
     let outputs = [
         (op_return_script, 0),
@@ -128,22 +348,230 @@ fn withdrawal_data(
     sender_private_key: &PrivateKey,
     network: &Network,
 ) -> Vec<u8> {
-    let mut msg = amount.to_be_bytes().to_vec();
-    msg.extend_from_slice(recipient.script_pubkey().as_bytes());
+    magic_bytes(network)
+        .into_iter()
+        .chain(withdrawal_request_data(
+            recipient,
+            amount,
+            sender_private_key,
+        ))
+        .collect()
+}
 
-    let msg_hash = sha256::digest(msg.as_slice());
-    let msg_hash_bytes = array_bytes::hex2bytes(msg_hash).unwrap();
-    let msg_ecdsa = Message::from_slice(&msg_hash_bytes).unwrap();
+/// The withdrawal request, without magic bytes, signed by the sBTC sender.
+fn withdrawal_request_data(
+    recipient: &BitcoinAddress,
+    amount: u64,
+    sender_private_key: &PrivateKey,
+) -> Vec<u8> {
+    let (recovery_id, signature) = sign_withdrawal(recipient, amount, sender_private_key);
+
+    once(b'>')
+        .chain(amount.to_be_bytes())
+        .chain(once(recovery_id.to_i32() as u8))
+        .chain(signature)
+        .collect()
+}
 
-    let (recovery_id, signature) = Secp256k1::new()
+/// Sign the withdrawal request, returning the recovery id and compact signature.
+fn sign_withdrawal(
+    recipient: &BitcoinAddress,
+    amount: u64,
+    sender_private_key: &PrivateKey,
+) -> (RecoveryId, [u8; 64]) {
+    let msg_ecdsa = withdrawal_message(&recipient.script_pubkey(), amount);
+
+    Secp256k1::new()
         .sign_ecdsa_recoverable(&msg_ecdsa, &sender_private_key.inner)
-        .serialize_compact();
+        .serialize_compact()
+}
 
-    magic_bytes(network)
+/// The signature with the recovery id last, rather than first as in the withdrawal request.
+fn clarity_signature(recovery_id: RecoveryId, signature: [u8; 64]) -> Vec<u8> {
+    signature
         .into_iter()
-        .chain(once(b'>'))
-        .chain(amount.to_be_bytes())
         .chain(once(recovery_id.to_i32() as u8))
-        .chain(signature)
         .collect()
 }
+
+/// Parse a Stacks private key in hex: 32 bytes, followed by 01 as its public key is compressed.
+/// Keys of uncompressed public keys are rejected, since the Stacks node attributes withdrawal
+/// requests to the address of the compressed public key recovered from their signature.
+fn parse_stacks_private_key(private_key: &str, network: Network) -> anyhow::Result<PrivateKey> {
+    let bytes = array_bytes::hex2bytes(private_key)
+        .map_err(|_| anyhow!("Failed to parse hex string: {}", private_key))?;
+    match (bytes.len(), bytes.last()) {
+        (33, Some(1)) => Ok(PrivateKey::from_slice(&bytes[..32], network)?),
+        (32, _) => Err(anyhow!(
+            "Stacks private keys of uncompressed public keys are not supported"
+        )),
+        _ => Err(anyhow!(
+            "Stacks private keys are 32 bytes, followed by 01 for compressed public keys"
+        )),
+    }
+}
+
+/// The message signed by the sBTC sender of a withdrawal request: the hash of the amount
+/// followed by the recipient script.
+pub fn withdrawal_message(recipient_script: &Script, amount: u64) -> Message {
+    let mut msg = amount.to_be_bytes().to_vec();
+    msg.extend_from_slice(recipient_script.as_bytes());
+
+    let msg_hash = sha256::digest(msg.as_slice());
+    let msg_hash_bytes = array_bytes::hex2bytes(msg_hash).unwrap();
+    Message::from_slice(&msg_hash_bytes).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{secp256k1::ecdsa::RecoverableSignature, PackedLockTime, Transaction, TxOut};
+    use blockstack_lib::{
+        address::{
+            AddressHashMode, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        },
+        types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey},
+        util::secp256k1::MessageSignature,
+    };
+
+    use super::*;
+    use crate::commands::decode::{decode_operation, Operation};
+
+    // Arbitrary key, copy-pasted from src/chainstate/stacks/tests/accounting.rs
+    const STACKS_PRIVATE_KEY: &str =
+        "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01";
+
+    fn expected_stacks_address(private_key: &str, network: Network) -> StacksAddress {
+        let version = match network {
+            Network::Bitcoin => C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            _ => C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        };
+        let public_key = StacksPublicKey::from_private(
+            &StacksPrivateKey::from_hex(private_key).expect("Invalid Stacks private key"),
+        );
+        StacksAddress::from_public_keys(
+            version,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![public_key],
+        )
+        .unwrap()
+    }
+
+    fn withdrawal_request(
+        sender_private_key: &PrivateKey,
+        recipient: &BitcoinAddress,
+        amount: u64,
+        network: Network,
+    ) -> Transaction {
+        let secp = Secp256k1::new();
+        let dkg_address = BitcoinAddress::p2tr(
+            &secp,
+            sender_private_key
+                .public_key(&secp)
+                .inner
+                .x_only_public_key()
+                .0,
+            None,
+            network,
+        );
+        let op_return_script = build_op_return_script(&withdrawal_data(
+            recipient,
+            amount,
+            sender_private_key,
+            &network,
+        ));
+
+        Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![],
+            output: vec![
+                TxOut {
+                    value: 0,
+                    script_pubkey: op_return_script,
+                },
+                TxOut {
+                    value: recipient.script_pubkey().dust_value().to_sat(),
+                    script_pubkey: recipient.script_pubkey(),
+                },
+                TxOut {
+                    value: 1000,
+                    script_pubkey: dkg_address.script_pubkey(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_withdrawal_request_round_trip() {
+        for network in [Network::Testnet, Network::Bitcoin] {
+            let private_key = parse_stacks_private_key(STACKS_PRIVATE_KEY, network).unwrap();
+            let recipient =
+                BitcoinAddress::p2pkh(&private_key.public_key(&Secp256k1::new()), network);
+            let tx = withdrawal_request(&private_key, &recipient, 1337, network);
+
+            let Operation::WithdrawalRequest {
+                recipient: decoded_recipient,
+                amount,
+                sender_public_key,
+                sender_stacks_address,
+                ..
+            } = decode_operation(&tx, network).unwrap()
+            else {
+                panic!("Not decoded as a withdrawal request");
+            };
+            assert_eq!(decoded_recipient, recipient.to_string());
+            assert_eq!(amount, 1337);
+            assert_eq!(
+                sender_public_key,
+                private_key.public_key(&Secp256k1::new()).to_string()
+            );
+            assert_eq!(
+                sender_stacks_address,
+                expected_stacks_address(STACKS_PRIVATE_KEY, network).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_withdrawal_signature_recovery() {
+        let network = Network::Testnet;
+        let private_key = parse_stacks_private_key(STACKS_PRIVATE_KEY, network).unwrap();
+        let public_key = private_key.public_key(&Secp256k1::new());
+        let recipient = BitcoinAddress::p2pkh(&public_key, network);
+        let message = withdrawal_message(&recipient.script_pubkey(), 42);
+
+        // The wire format, with the recovery id first, as parsed by the Stacks node
+        let data = withdrawal_request_data(&recipient, 42, &private_key);
+        let signature = MessageSignature::from_raw(&data[9..74].to_vec());
+        let recovered = StacksPublicKey::recover_to_pubkey(&message[..], &signature).unwrap();
+        assert_eq!(recovered.to_bytes_compressed(), public_key.to_bytes());
+
+        // The Clarity format, with the recovery id last
+        let (recovery_id, signature) = sign_withdrawal(&recipient, 42, &private_key);
+        let clarity_signature = clarity_signature(recovery_id, signature);
+        let signature = RecoverableSignature::from_compact(
+            &clarity_signature[..64],
+            RecoveryId::from_i32(clarity_signature[64].into()).unwrap(),
+        )
+        .unwrap();
+        let recovered = Secp256k1::new()
+            .recover_ecdsa(&message, &signature)
+            .unwrap();
+        assert_eq!(recovered, public_key.inner);
+    }
+
+    #[test]
+    fn test_parse_stacks_private_key() {
+        let private_key = parse_stacks_private_key(STACKS_PRIVATE_KEY, Network::Testnet).unwrap();
+        assert!(private_key.compressed);
+
+        // The Stacks node would attribute the request to the compressed public key instead
+        assert!(parse_stacks_private_key(&STACKS_PRIVATE_KEY[..64], Network::Testnet).is_err());
+
+        assert!(parse_stacks_private_key(&STACKS_PRIVATE_KEY[..62], Network::Testnet).is_err());
+        let invalid_flag = format!("{}02", &STACKS_PRIVATE_KEY[..64]);
+        assert!(parse_stacks_private_key(&invalid_flag, Network::Testnet).is_err());
+    }
+}
@@ -1,9 +1,20 @@
 use clap::Parser;
 
 use crate::commands::broadcast::{broadcast_tx, BroadcastArgs};
-use crate::commands::deposit::{build_deposit_tx, DepositArgs};
+use crate::commands::bump_fee::{bump_fee, BumpFeeArgs};
+use crate::commands::decode::{decode_tx, DecodeArgs};
+use crate::commands::deposit::{
+    build_deposit_commit_tx, build_deposit_reveal_tx, build_deposit_tx, DepositArgs,
+    DepositCommitArgs, DepositRevealArgs,
+};
+use crate::commands::finalize::{finalize_psbt, FinalizeArgs};
 use crate::commands::generate::{generate, GenerateArgs};
-use crate::commands::withdraw::{build_withdrawal_tx, WithdrawalArgs};
+use crate::commands::status::{operation_status, StatusArgs};
+use crate::commands::utils::BackendArgs;
+use crate::commands::withdraw::{
+    build_withdrawal_commit_tx, build_withdrawal_reveal_tx, build_withdrawal_tx, WithdrawalArgs,
+    WithdrawalCommitArgs, WithdrawalRevealArgs,
+};
 
 mod commands;
 
@@ -11,13 +22,24 @@ mod commands;
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    #[command(flatten)]
+    backend: BackendArgs,
 }
 
 #[derive(clap::Subcommand, Debug, Clone)]
 enum Command {
     Deposit(DepositArgs),
     Withdraw(WithdrawalArgs),
+    DepositCommit(DepositCommitArgs),
+    DepositReveal(DepositRevealArgs),
+    WithdrawCommit(WithdrawalCommitArgs),
+    WithdrawReveal(WithdrawalRevealArgs),
     Broadcast(BroadcastArgs),
+    BumpFee(BumpFeeArgs),
+    Decode(DecodeArgs),
+    Finalize(FinalizeArgs),
+    Status(StatusArgs),
     GenerateFrom(GenerateArgs),
 }
 
@@ -25,9 +47,21 @@ fn main() -> Result<(), anyhow::Error> {
     let args = Cli::parse();
 
     match args.command {
-        Command::Deposit(deposit_args) => build_deposit_tx(&deposit_args),
-        Command::Withdraw(withdrawal_args) => build_withdrawal_tx(&withdrawal_args),
-        Command::Broadcast(broadcast_args) => broadcast_tx(&broadcast_args),
+        Command::Deposit(deposit_args) => build_deposit_tx(&deposit_args, &args.backend),
+        Command::Withdraw(withdrawal_args) => build_withdrawal_tx(&withdrawal_args, &args.backend),
+        Command::DepositCommit(deposit_args) => {
+            build_deposit_commit_tx(&deposit_args, &args.backend)
+        }
+        Command::DepositReveal(deposit_args) => build_deposit_reveal_tx(&deposit_args),
+        Command::WithdrawCommit(withdrawal_args) => {
+            build_withdrawal_commit_tx(&withdrawal_args, &args.backend)
+        }
+        Command::WithdrawReveal(withdrawal_args) => build_withdrawal_reveal_tx(&withdrawal_args),
+        Command::Broadcast(broadcast_args) => broadcast_tx(&broadcast_args, &args.backend),
+        Command::BumpFee(bump_fee_args) => bump_fee(&bump_fee_args, &args.backend),
+        Command::Decode(decode_args) => decode_tx(&decode_args, &args.backend),
+        Command::Finalize(finalize_args) => finalize_psbt(&finalize_args),
+        Command::Status(status_args) => operation_status(&status_args, &args.backend),
         Command::GenerateFrom(generate_args) => generate(&generate_args),
     }
 }
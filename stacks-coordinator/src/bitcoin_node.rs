@@ -144,6 +144,18 @@ impl LocalhostBitcoinNode {
         }
     }
 
+    /// Get the height of the most-work fully-validated chain
+    pub async fn get_block_count(&self) -> Result<u64, Error> {
+        debug!("Retrieving block count...");
+        let params: [(); 0] = [];
+        self.call("getblockcount", params)
+            .await?
+            .as_u64()
+            .ok_or(Error::InvalidResponseJSON(
+                "No block height in getblockcount response".to_string(),
+            ))
+    }
+
     /// Make the Bitcoin RPC method call with the corresponding paramenters
     async fn call(&self, method: &str, params: impl Serialize) -> Result<serde_json::Value, Error> {
         self.call_path(method, params, None).await
@@ -50,6 +50,10 @@ pub struct RawConfig {
     pub transaction_fee: u64,
     /// Frost specific config options. Must be specified if signer_config_path is not used
     pub http_relay_url: Option<String>,
+    /// The relay channel shared with the signers. Uses the relay's default channel if omitted.
+    pub http_relay_channel: Option<String>,
+    /// Path to the PEM encoded CA certificate the relay's TLS certificate must be issued by.
+    pub http_relay_ca_certificate: Option<String>,
     pub frost_state_file: Option<String>,
     pub network_private_key: Option<String>,
     /// Controls how many seconds to wait between polls
@@ -124,6 +128,8 @@ pub struct Config {
     pub transaction_fee: u64,
     /// Frost specific config options. Must be specified if signer_config_path is not used
     pub http_relay_url: Option<String>,
+    pub http_relay_channel: Option<String>,
+    pub http_relay_ca_certificate: Option<String>,
     pub network_private_key: Option<String>,
     /// Controls how many seconds to wait between polls
     pub polling_interval: u64,
@@ -173,6 +179,8 @@ impl TryFrom<RawConfig> for Config {
             stacks_version,
             transaction_fee: config.transaction_fee,
             http_relay_url: config.http_relay_url,
+            http_relay_channel: config.http_relay_channel,
+            http_relay_ca_certificate: config.http_relay_ca_certificate,
             network_private_key: config.network_private_key,
             polling_interval: config.polling_interval.unwrap_or(DEFAULT_POLLING_INTERVAL),
         })
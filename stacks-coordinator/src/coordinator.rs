@@ -1,4 +1,5 @@
 use bitcoin::{
+    consensus::serialize,
     psbt::Prevouts,
     util::{
         base58,
@@ -13,7 +14,7 @@ use frost_coordinator::{
 use frost_signer::{
     config::Config as SignerConfig,
     net::{Error as HttpNetError, HttpNetListen},
-    signing_round::DkgPublicShare,
+    signing_round::{DkgPublicShare, SigningContext},
 };
 use std::{
     collections::BTreeMap,
@@ -185,6 +186,8 @@ trait CoordinatorHelpers: Coordinator {
         let (mut tx, prevouts) = self.fee_wallet().bitcoin().fulfill_peg_out(op, utxos)?;
         let sighash_tx = tx.clone();
         let mut sighash_cache = SighashCache::new(&sighash_tx);
+        let serialized_tx = serialize(&sighash_tx);
+        let serialized_prevouts: Vec<Vec<u8>> = prevouts.iter().map(serialize).collect();
         // Sign the transaction
         for index in 0..tx.input.len() {
             let taproot_sighash = sighash_cache
@@ -194,9 +197,16 @@ trait CoordinatorHelpers: Coordinator {
                     SchnorrSighashType::Default,
                 )
                 .map_err(Error::SigningError)?;
+            // Signers derive the sighash from the transaction to check it is the one they approved
+            let context = SigningContext {
+                txid: op.txid.to_hex(),
+                transaction: serialized_tx.clone(),
+                prevouts: serialized_prevouts.clone(),
+                input_index: index as u64,
+            };
             let (_frost_sig, schnorr_proof) = self
                 .frost_coordinator_mut()
-                .sign_message(&taproot_sighash.as_hash())
+                .sign_transaction_message(&taproot_sighash.as_hash(), &context)
                 .await?;
 
             debug!(
@@ -358,15 +368,17 @@ async fn create_frost_coordinator_from_contract(
     )
     .map_err(|_| Error::ConfigError("Invalid network_private_key.".to_string()))?;
     let http_relay_url = config.http_relay_url.clone().unwrap_or(String::new());
-    create_coordinator(&SignerConfig::new(
+    let signer_config = SignerConfig::new(
         keys_threshold.try_into().unwrap(),
         coordinator_public_key,
         public_keys,
         signer_key_ids,
         network_private_key,
         http_relay_url,
-    ))
-    .map_err(|e| Error::ConfigError(e.to_string()))
+        config.http_relay_channel.clone(),
+        config.http_relay_ca_certificate.clone(),
+    );
+    create_coordinator(&signer_config).map_err(|e| Error::ConfigError(e.to_string()))
 }
 
 async fn create_frost_coordinator(
@@ -6,6 +6,7 @@ use std::{
 };
 
 use clap::{Parser, Subcommand};
+use serde::Serialize;
 
 #[derive(Clone, Debug)]
 pub enum Network {
@@ -31,6 +32,79 @@ impl FromStr for Network {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("Could not parse Severity: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Could not parse OutputFormat: {}", s)),
+        }
+    }
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct AnalyzeArgs {
+    /// Length in seconds of the time windows log matches are aggregated in
+    #[arg(short, long, default_value_t = 600)]
+    pub window: u64,
+
+    /// Lowest severity of the findings to report
+    #[arg(short, long, default_value_t = Severity::Info)]
+    pub min_severity: Severity,
+
+    /// Print findings as text or JSON
+    #[arg(short, long, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct BlocksArgs {
     // How many recent blocks to take into account
@@ -38,14 +112,36 @@ pub struct BlocksArgs {
     pub blocks: u64,
 }
 
+#[derive(Parser, Clone, Debug)]
+pub struct HealthArgs {
+    /// URL to the bitcoind RPC API the node follows, with credentials if required
+    #[arg(long, env = "DOCTOR_BITCOIND_URL")]
+    pub bitcoind_url: Option<String>,
+
+    /// How many blocks the node's burnchain tip may trail the bitcoind tip
+    #[arg(long, default_value_t = 3)]
+    pub max_burn_block_lag: u64,
+
+    /// How many inbound and outbound peers the node should at least have
+    #[arg(long, default_value_t = 1)]
+    pub min_peers: usize,
+
+    /// How many seconds old the node's burnchain tip may be, read from the node databases when
+    /// no bitcoind URL is given
+    #[arg(long, default_value_t = 7200)]
+    pub max_tip_age: u64,
+}
+
 #[derive(Subcommand, Clone, Debug)]
 pub enum Commands {
-    /// Analyze miner
-    Analyze,
+    /// Analyze node logs for known failure patterns
+    Analyze(AnalyzeArgs),
     /// Print burn fee information
     Burns(BlocksArgs),
     /// Print reorg information
     Reorgs(BlocksArgs),
+    /// Check node sync, peers and network through the RPC API
+    Health(HealthArgs),
     /// Print related environment variables that are set
     Env,
 }
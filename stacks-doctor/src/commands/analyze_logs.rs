@@ -1,10 +1,38 @@
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{BufRead, BufReader},
     path::PathBuf,
 };
 
 use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::to_string_pretty;
+
+use super::log_rules::{Rule, RULES};
+use crate::cli::{AnalyzeArgs, OutputFormat, Severity};
+
+#[derive(Serialize)]
+struct Finding {
+    rule: &'static str,
+    severity: Severity,
+    description: &'static str,
+    window_start: u64,
+    window_end: u64,
+    count: usize,
+    first_line: usize,
+    last_line: usize,
+    resolved: bool,
+    sample: String,
+}
+
+#[derive(Serialize)]
+struct Response {
+    message: String,
+    lines: usize,
+    window: u64,
+    findings: Vec<Finding>,
+}
 
 /*
 Run commands below to get a sample log file locally and analyze it:
@@ -17,24 +45,133 @@ cargo run -p stacks-node --bin stacks-node -- testnet 2>&1 | tee node.log;
 stacks-doctor -l /path/to/node.log analyze
 ```
 */
-pub fn analyze_logs(log_file: PathBuf) -> Result<()> {
-    let file = BufReader::new(File::open(log_file).context("Could not open log file")?);
-    let mut is_okay = true;
-
-    file.lines().map_while(Result::ok).for_each(|line| {
-        if line.contains("mined anchored block") {
-            is_okay = true;
-        } else if line.contains("Failure mining") {
-            is_okay = false;
-            println!("Found problem in logs: {}", line);
+pub fn analyze_logs(log_file: PathBuf, args: &AnalyzeArgs) -> Result<()> {
+    let mut file = BufReader::new(File::open(log_file).context("Could not open log file")?);
+    let window = args.window.max(1);
+
+    let mut lines = 0;
+    let mut timestamp = 0;
+    // Keyed by window start and rule index to list findings in time order
+    let mut findings: BTreeMap<(u64, usize), Finding> = BTreeMap::new();
+    let mut last_resolved = [0; RULES.len()];
+
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = file
+            .read_until(b'\n', &mut buf)
+            .context("Could not read log file")?;
+        if read == 0 {
+            break;
         }
-    });
+        // Logs may hold invalid UTF-8, e.g. from binary payloads, which must not end the analysis
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        lines += 1;
+        let line_number = lines;
+        // Continuation lines such as backtraces belong to the last timestamped line
+        timestamp = line_timestamp(line).unwrap_or(timestamp);
+        let window_start = timestamp - timestamp % window;
+
+        for (rule_index, rule) in RULES.iter().enumerate() {
+            if rule.resolves(line) {
+                last_resolved[rule_index] = line_number;
+            }
+            if !rule.matches(line) {
+                continue;
+            }
+
+            findings
+                .entry((window_start, rule_index))
+                .and_modify(|finding| {
+                    finding.count += 1;
+                    finding.last_line = line_number;
+                })
+                .or_insert_with(|| new_finding(rule, window_start, window, line_number, line));
+        }
+    }
 
-    if is_okay {
-        println!("No problems detected in logs");
+    let findings: Vec<Finding> = findings
+        .into_iter()
+        .filter(|((_, rule_index), finding)| {
+            finding.count >= RULES[*rule_index].threshold && finding.severity >= args.min_severity
+        })
+        .map(|((_, rule_index), mut finding)| {
+            finding.resolved = last_resolved[rule_index] > finding.last_line;
+            finding
+        })
+        .collect();
+
+    let is_okay = findings
+        .iter()
+        .all(|finding| finding.resolved || finding.severity < Severity::Warning);
+    let message = if is_okay {
+        "No problems detected in logs"
     } else {
-        println!("Problems detected in logs");
+        "Problems detected in logs"
+    };
+
+    match args.format {
+        OutputFormat::Text => {
+            findings.iter().for_each(|finding| {
+                println!(
+                    "[{}] {}: {} matches between {} and {} on lines {}-{}{}",
+                    finding.severity,
+                    finding.rule,
+                    finding.count,
+                    finding.window_start,
+                    finding.window_end,
+                    finding.first_line,
+                    finding.last_line,
+                    if finding.resolved { " (resolved)" } else { "" }
+                );
+                println!("    {}", finding.description);
+                println!("    {}", finding.sample);
+            });
+            println!("{}", message);
+        }
+        OutputFormat::Json => {
+            let res = Response {
+                message: message.to_string(),
+                lines,
+                window,
+                findings,
+            };
+
+            println!("{}", to_string_pretty(&res).unwrap());
+        }
     }
 
     Ok(())
 }
+
+fn new_finding(
+    rule: &Rule,
+    window_start: u64,
+    window: u64,
+    line_number: usize,
+    line: &str,
+) -> Finding {
+    Finding {
+        rule: rule.name,
+        severity: rule.severity,
+        description: rule.description,
+        window_start,
+        window_end: window_start + window,
+        count: 1,
+        first_line: line_number,
+        last_line: line_number,
+        resolved: false,
+        sample: line.to_string(),
+    }
+}
+
+/// Unix timestamp of a stacks-node log line, e.g. `INFO [1686744000.123456] [file.rs:10] ...`
+fn line_timestamp(line: &str) -> Option<u64> {
+    let rest = &line[line.find('[')? + 1..];
+    let end = rest.find(']')?;
+    rest[..end]
+        .parse::<f64>()
+        .ok()
+        .map(|timestamp| timestamp as u64)
+}
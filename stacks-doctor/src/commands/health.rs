@@ -0,0 +1,311 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{blocking::Client, Url};
+use rusqlite::{params, OpenFlags};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, to_string_pretty, Value};
+
+use crate::cli::{HealthArgs, Network};
+
+const MAINNET_NETWORK_ID: u32 = 0x00000001;
+const TESTNET_NETWORK_ID: u32 = 0x80000000;
+
+#[derive(Deserialize)]
+struct Info {
+    peer_version: u32,
+    network_id: u32,
+    server_version: String,
+    burn_block_height: u64,
+    stable_burn_block_height: u64,
+    stacks_tip_height: u64,
+    stacks_tip: String,
+    /// Only reported by newer nodes
+    #[serde(default)]
+    is_fully_synced: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct Neighbors {
+    sample: Vec<Value>,
+    inbound: Vec<Value>,
+    outbound: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Pox {
+    reward_cycle_id: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockchainInfo {
+    chain: String,
+    blocks: u64,
+    headers: u64,
+}
+
+#[derive(Serialize)]
+struct Node {
+    server_version: String,
+    network_id: u32,
+    peer_version: u32,
+    stacks_tip_height: u64,
+    stacks_tip: String,
+    burn_block_height: u64,
+    stable_burn_block_height: u64,
+    is_fully_synced: Option<bool>,
+    reward_cycle_id: u64,
+    inbound_peers: usize,
+    outbound_peers: usize,
+    sample_peers: usize,
+}
+
+#[derive(Serialize)]
+struct Bitcoind {
+    chain: String,
+    blocks: u64,
+    headers: u64,
+    burn_block_lag: u64,
+}
+
+#[derive(Serialize)]
+struct BurnchainTip {
+    block_height: u64,
+    timestamp: u64,
+    age: u64,
+}
+
+#[derive(Serialize)]
+struct Problem {
+    check: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct Response {
+    message: String,
+    healthy: bool,
+    problems: Vec<Problem>,
+    node: Node,
+    bitcoind: Option<Bitcoind>,
+    burnchain_tip: Option<BurnchainTip>,
+}
+
+pub fn health(
+    network: Network,
+    rpc_url: &str,
+    db_dir: Option<&Path>,
+    args: &HealthArgs,
+) -> Result<()> {
+    let client = Client::new();
+    let rpc_url = Url::parse(rpc_url).context("Could not parse RPC URL")?;
+
+    let info: Info = get(&client, &rpc_url, "/v2/info")?;
+    let neighbors: Neighbors = get(&client, &rpc_url, "/v2/neighbors")?;
+    let pox: Pox = get(&client, &rpc_url, "/v2/pox")?;
+
+    let mut problems = Vec::new();
+
+    let expected_network_id = match network {
+        Network::Mainnet => MAINNET_NETWORK_ID,
+        Network::Testnet => TESTNET_NETWORK_ID,
+    };
+    if info.network_id != expected_network_id {
+        problems.push(Problem {
+            check: "network",
+            message: format!(
+                "Node reports network id {:#010x}, expected {:#010x} for {}",
+                info.network_id, expected_network_id, network
+            ),
+        });
+    }
+
+    let peers = neighbors.inbound.len() + neighbors.outbound.len();
+    if peers < args.min_peers {
+        problems.push(Problem {
+            check: "peers",
+            message: format!(
+                "Node is connected to {} peers, expected at least {}",
+                peers, args.min_peers
+            ),
+        });
+    }
+
+    if info.is_fully_synced == Some(false) {
+        problems.push(Problem {
+            check: "sync",
+            message: "Node reports that it is not fully synced".to_string(),
+        });
+    }
+
+    let bitcoind = match &args.bitcoind_url {
+        Some(bitcoind_url) => {
+            let blockchain_info = blockchain_info(&client, bitcoind_url)?;
+            let burn_block_lag = blockchain_info
+                .blocks
+                .saturating_sub(info.burn_block_height);
+
+            let expected_chains: &[&str] = match network {
+                Network::Mainnet => &["main"],
+                Network::Testnet => &["test", "regtest"],
+            };
+            if !expected_chains.contains(&blockchain_info.chain.as_str()) {
+                problems.push(Problem {
+                    check: "network",
+                    message: format!(
+                        "bitcoind is on chain {}, expected {} for {}",
+                        blockchain_info.chain,
+                        expected_chains.join(" or "),
+                        network
+                    ),
+                });
+            }
+            if burn_block_lag > args.max_burn_block_lag {
+                problems.push(Problem {
+                    check: "sync",
+                    message: format!(
+                        "Node burnchain tip {} is {} blocks behind bitcoind tip {}",
+                        info.burn_block_height, burn_block_lag, blockchain_info.blocks
+                    ),
+                });
+            }
+            if blockchain_info.headers > blockchain_info.blocks {
+                problems.push(Problem {
+                    check: "sync",
+                    message: format!(
+                        "bitcoind is still syncing, {} of {} blocks",
+                        blockchain_info.blocks, blockchain_info.headers
+                    ),
+                });
+            }
+
+            Some(Bitcoind {
+                chain: blockchain_info.chain,
+                blocks: blockchain_info.blocks,
+                headers: blockchain_info.headers,
+                burn_block_lag,
+            })
+        }
+        None => None,
+    };
+
+    // Without bitcoind to compare with, a stalled burnchain shows as an old tip
+    let burnchain_tip = match (&bitcoind, db_dir) {
+        (None, Some(db_dir)) => {
+            let burnchain_tip = burnchain_tip(&network, db_dir)?;
+            if burnchain_tip.age > args.max_tip_age {
+                problems.push(Problem {
+                    check: "sync",
+                    message: format!(
+                        "Node burnchain tip {} is {} seconds old",
+                        burnchain_tip.block_height, burnchain_tip.age
+                    ),
+                });
+            }
+            Some(burnchain_tip)
+        }
+        (None, None) if info.is_fully_synced.is_none() => {
+            eprintln!("Burnchain sync not checked, pass a bitcoind URL or the database directory");
+            None
+        }
+        _ => None,
+    };
+
+    let res = Response {
+        message: if problems.is_empty() {
+            "No problems detected".to_string()
+        } else {
+            format!("{} problems detected", problems.len())
+        },
+        healthy: problems.is_empty(),
+        problems,
+        node: Node {
+            server_version: info.server_version,
+            network_id: info.network_id,
+            peer_version: info.peer_version,
+            stacks_tip_height: info.stacks_tip_height,
+            stacks_tip: info.stacks_tip,
+            burn_block_height: info.burn_block_height,
+            stable_burn_block_height: info.stable_burn_block_height,
+            is_fully_synced: info.is_fully_synced,
+            reward_cycle_id: pox.reward_cycle_id,
+            inbound_peers: neighbors.inbound.len(),
+            outbound_peers: neighbors.outbound.len(),
+            sample_peers: neighbors.sample.len(),
+        },
+        bitcoind,
+        burnchain_tip,
+    };
+
+    println!("{}", to_string_pretty(&res).unwrap());
+
+    Ok(())
+}
+
+fn get<T: DeserializeOwned>(client: &Client, rpc_url: &Url, path: &str) -> Result<T> {
+    client
+        .get(rpc_url.join(path)?)
+        .send()
+        .and_then(|res| res.error_for_status())
+        .with_context(|| format!("Could not query {}", path))?
+        .json()
+        .with_context(|| format!("Could not parse response from {}", path))
+}
+
+/// The highest block of the node's burnchain database, and how long ago it was mined.
+fn burnchain_tip(network: &Network, db_dir: &Path) -> Result<BurnchainTip> {
+    let mode = match network {
+        Network::Mainnet => "mainnet/",
+        Network::Testnet => "xenon/",
+    };
+    let db_file = db_dir.join(mode).join("burnchain/burnchain.sqlite");
+    let conn = rusqlite::Connection::open_with_flags(db_file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context("Could not open database connection")?;
+
+    let (block_height, timestamp): (i64, i64) = conn
+        .query_row(
+            "SELECT block_height, timestamp FROM burnchain_db_block_headers ORDER BY block_height DESC LIMIT 1",
+            params![],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .context("Could not get the burnchain tip")?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    Ok(BurnchainTip {
+        block_height: block_height as u64,
+        timestamp: timestamp as u64,
+        age: now.saturating_sub(timestamp as u64),
+    })
+}
+
+/// Query `getblockchaininfo`, taking the RPC credentials from the URL if present.
+fn blockchain_info(client: &Client, bitcoind_url: &str) -> Result<BlockchainInfo> {
+    let mut url = Url::parse(bitcoind_url).context("Could not parse bitcoind URL")?;
+    let username = url.username().to_string();
+    let password = url.password().map(ToString::to_string);
+    url.set_username("")
+        .and_then(|_| url.set_password(None))
+        .map_err(|_| anyhow!("Could not strip credentials from bitcoind URL"))?;
+
+    let mut request = client.post(url).json(&json!({
+        "jsonrpc": "1.0",
+        "id": "stacks-doctor",
+        "method": "getblockchaininfo",
+        "params": [],
+    }));
+    if !username.is_empty() {
+        request = request.basic_auth(username, password);
+    }
+
+    let mut res: Value = request
+        .send()
+        .and_then(|res| res.error_for_status())
+        .context("Could not query bitcoind")?
+        .json()
+        .context("Could not parse bitcoind response")?;
+
+    serde_json::from_value(res["result"].take()).context("Unexpected getblockchaininfo result")
+}
@@ -0,0 +1,79 @@
+use crate::cli::Severity;
+
+/// A known stacks-node failure pattern
+pub struct Rule {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub severity: Severity,
+    /// A line matches the rule if it contains any of these
+    pub patterns: &'static [&'static str],
+    /// A line containing any of these resolves the earlier matches of the rule
+    pub resolved_by: &'static [&'static str],
+    /// How many matches within a time window make a finding
+    pub threshold: usize,
+}
+
+impl Rule {
+    pub fn matches(&self, line: &str) -> bool {
+        self.patterns.iter().any(|pattern| line.contains(pattern))
+    }
+
+    pub fn resolves(&self, line: &str) -> bool {
+        self.resolved_by
+            .iter()
+            .any(|pattern| line.contains(pattern))
+    }
+}
+
+/// Log messages are matched as stacks-node 2.4 formats them, the file each comes from is noted
+/// on its rule
+pub const RULES: &[Rule] = &[
+    // testnet/stacks-node/src/neon_node.rs
+    Rule {
+        name: "mining_failure",
+        description: "Miner failed to mine an anchored block or to submit its block commit",
+        severity: Severity::Error,
+        patterns: &[
+            "Relayer: Failure mining anchored block",
+            "Relayer: Failed to submit Bitcoin transaction",
+        ],
+        resolved_by: &["Miner: mined anchored block"],
+        threshold: 1,
+    },
+    // testnet/stacks-node/src/burnchains/bitcoin_regtest_controller.rs
+    Rule {
+        name: "burnchain_sync_stall",
+        description: "Node repeatedly failed to sync the burnchain from bitcoind",
+        severity: Severity::Error,
+        patterns: &["Unable to sync with burnchain", "Bitcoin RPC failure"],
+        resolved_by: &[],
+        threshold: 3,
+    },
+    // stackslib/src/chainstate/coordinator/mod.rs
+    Rule {
+        name: "pox_anchor_miss",
+        description: "Node is missing a PoX anchor block and cannot process the reward cycle",
+        severity: Severity::Error,
+        patterns: &["Burnchain block processing stops due to missing affirmed anchor stacks block"],
+        resolved_by: &[],
+        threshold: 1,
+    },
+    // testnet/stacks-node/src/burnchains/bitcoin_regtest_controller.rs
+    Rule {
+        name: "block_commit_rbf",
+        description: "Miner replaced its block commit up to the max RBF fee rate, its fee rate may be too low",
+        severity: Severity::Warning,
+        patterns: &["RBF'd block commits reached"],
+        resolved_by: &[],
+        threshold: 3,
+    },
+    // stackslib/src/net/rpc.rs, only logged with STACKS_LOG_DEBUG=1
+    Rule {
+        name: "mempool_rejection",
+        description: "Mempool rejected many transactions",
+        severity: Severity::Info,
+        patterns: &["Mempool rejected POSTed transaction"],
+        resolved_by: &[],
+        threshold: 10,
+    },
+];
@@ -1,9 +1,12 @@
 mod analyze_logs;
 mod burns;
 mod env;
+mod health;
+mod log_rules;
 mod reorgs;
 
 pub use analyze_logs::analyze_logs;
 pub use burns::burns;
 pub use env::show_env;
+pub use health::health;
 pub use reorgs::reorgs;
@@ -1,6 +1,6 @@
 use clap::Parser;
 use cli::Commands;
-use commands::{analyze_logs, burns, reorgs, show_env};
+use commands::{analyze_logs, burns, health, reorgs, show_env};
 
 use crate::cli::Args;
 
@@ -11,9 +11,9 @@ fn main() {
     let args = Args::parse();
 
     match &args.cmd {
-        Commands::Analyze => {
+        Commands::Analyze(inner_args) => {
             if let Some(log_file) = args.log_file {
-                analyze_logs(log_file)
+                analyze_logs(log_file, inner_args)
             } else {
                 eprintln!("Log file path needs to be passed");
                 Ok(())
@@ -35,6 +35,14 @@ fn main() {
                 Ok(())
             }
         }
+        Commands::Health(inner_args) => {
+            if let Some(rpc_url) = &args.rpc_url {
+                health(args.network, rpc_url, args.db_dir.as_deref(), inner_args)
+            } else {
+                eprintln!("RPC URL needs to be passed");
+                Ok(())
+            }
+        }
         Commands::Env => show_env(),
     }
     .unwrap();
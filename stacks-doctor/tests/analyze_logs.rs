@@ -0,0 +1,124 @@
+use std::{path::Path, process::Command};
+
+use serde_json::Value;
+
+fn analyze(fixture: &str, args: &[&str]) -> String {
+    let log_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(fixture);
+    let output = Command::new(env!("CARGO_BIN_EXE_stacks-doctor"))
+        .arg("--log-file")
+        .arg(log_file)
+        .arg("analyze")
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn analyze_json(fixture: &str, args: &[&str]) -> Value {
+    let output = analyze(fixture, &[&["--format", "json"][..], args].concat());
+    serde_json::from_str(&output).unwrap()
+}
+
+fn rules(res: &Value) -> Vec<&str> {
+    res["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| finding["rule"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_healthy_log() {
+    let res = analyze_json("healthy.log", &[]);
+
+    assert_eq!(res["message"], "No problems detected in logs");
+    assert_eq!(res["lines"], 5);
+    assert!(rules(&res).is_empty());
+}
+
+#[test]
+fn test_burnchain_sync_stall() {
+    let res = analyze_json("burnchain_stall.log", &[]);
+
+    assert_eq!(res["message"], "Problems detected in logs");
+    assert_eq!(rules(&res), ["burnchain_sync_stall"]);
+
+    let finding = &res["findings"][0];
+    assert_eq!(finding["severity"], "error");
+    assert_eq!(finding["count"], 4);
+    assert_eq!(finding["window_start"], 1686744000);
+    assert_eq!(finding["window_end"], 1686744600);
+    assert_eq!(finding["first_line"], 2);
+    assert_eq!(finding["last_line"], 5);
+    assert_eq!(finding["resolved"], false);
+}
+
+#[test]
+fn test_mixed_log() {
+    let res = analyze_json("mixed.log", &[]);
+
+    assert_eq!(res["message"], "Problems detected in logs");
+    assert_eq!(
+        rules(&res),
+        ["mining_failure", "block_commit_rbf", "pox_anchor_miss"]
+    );
+
+    let findings = res["findings"].as_array().unwrap();
+    assert_eq!(findings[0]["resolved"], true);
+    assert_eq!(findings[1]["severity"], "warning");
+    assert_eq!(findings[1]["count"], 3);
+    assert_eq!(findings[2]["window_start"], 1686744600);
+    assert_eq!(findings[2]["resolved"], false);
+}
+
+#[test]
+fn test_invalid_utf8() {
+    // An invalid byte on the first line must not hide the failure after it
+    let res = analyze_json("invalid_utf8.log", &[]);
+
+    assert_eq!(res["message"], "Problems detected in logs");
+    assert_eq!(res["lines"], 2);
+    assert_eq!(rules(&res), ["mining_failure"]);
+    assert_eq!(res["findings"][0]["first_line"], 2);
+}
+
+#[test]
+fn test_window_aggregation() {
+    // Six mempool rejections in each of two ten minute windows stay below the threshold of ten
+    let res = analyze_json("mixed.log", &["--window", "600"]);
+    assert!(!rules(&res).contains(&"mempool_rejection"));
+
+    let res = analyze_json("mixed.log", &["--window", "3600"]);
+    let finding = res["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|finding| finding["rule"] == "mempool_rejection")
+        .unwrap();
+    assert_eq!(finding["severity"], "info");
+    assert_eq!(finding["count"], 12);
+    assert_eq!(finding["window_end"], 1686747600);
+}
+
+#[test]
+fn test_min_severity() {
+    let res = analyze_json("mixed.log", &["--min-severity", "error"]);
+
+    assert_eq!(rules(&res), ["mining_failure", "pox_anchor_miss"]);
+}
+
+#[test]
+fn test_text_output() {
+    let output = analyze("mixed.log", &[]);
+
+    assert!(output.contains("[error] mining_failure: 1 matches"));
+    assert!(output.contains("(resolved)"));
+    assert!(output.contains("[warning] block_commit_rbf: 3 matches"));
+    assert!(output.contains("missing affirmed anchor stacks block"));
+    assert!(output.ends_with("Problems detected in logs\n"));
+}
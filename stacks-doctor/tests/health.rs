@@ -0,0 +1,235 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::params;
+use serde_json::{json, Value};
+
+const TESTNET_NETWORK_ID: u32 = 0x80000000;
+
+/// Serve the JSON responses by request path, the way the node RPC API and bitcoind would.
+fn serve(responses: Vec<(&'static str, Value)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let path = request_path(&mut stream);
+            let response = match responses.iter().find(|(p, _)| *p == path) {
+                Some((_, body)) => {
+                    let body = body.to_string();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                }
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    url
+}
+
+/// Read the request, returning its path.
+fn request_path(stream: &mut TcpStream) -> String {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn node(burn_block_height: u64, peers: usize, is_fully_synced: Option<bool>) -> String {
+    let mut info = json!({
+        "peer_version": 4207599113u32,
+        "network_id": TESTNET_NETWORK_ID,
+        "server_version": "stacks-node 2.4.0.0.0",
+        "burn_block_height": burn_block_height,
+        "stable_burn_block_height": burn_block_height - 7,
+        "stacks_tip_height": 100000,
+        "stacks_tip": "a1".repeat(32),
+    });
+    if let Some(is_fully_synced) = is_fully_synced {
+        info["is_fully_synced"] = json!(is_fully_synced);
+    }
+
+    serve(vec![
+        ("/v2/info", info),
+        (
+            "/v2/neighbors",
+            json!({
+                "sample": [],
+                "inbound": vec![json!({}); peers],
+                "outbound": [],
+            }),
+        ),
+        ("/v2/pox", json!({ "reward_cycle_id": 600 })),
+    ])
+}
+
+fn bitcoind(blocks: u64) -> String {
+    let url = serve(vec![(
+        "/",
+        json!({
+            "result": { "chain": "test", "blocks": blocks, "headers": blocks },
+            "error": null,
+            "id": "stacks-doctor",
+        }),
+    )]);
+    url.replace("http://", "http://user:pass@")
+}
+
+/// A node directory whose burnchain database has a tip mined the given seconds ago.
+fn node_dir(name: &str, tip_age: u64) -> PathBuf {
+    let db_dir =
+        std::env::temp_dir().join(format!("stacks-doctor-{}-{}", name, std::process::id()));
+    let burnchain_dir = db_dir.join("xenon/burnchain");
+    std::fs::create_dir_all(&burnchain_dir).unwrap();
+    let db_file = burnchain_dir.join("burnchain.sqlite");
+    let _ = std::fs::remove_file(&db_file);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let conn = rusqlite::Connection::open(db_file).unwrap();
+    conn.execute(
+        "CREATE TABLE burnchain_db_block_headers (block_height INTEGER, block_hash TEXT, parent_block_hash TEXT, num_txs INTEGER, timestamp INTEGER)",
+        params![],
+    )
+    .unwrap();
+    for (block_height, age) in [(2_430_000, tip_age + 600), (2_430_001, tip_age)] {
+        conn.execute(
+            "INSERT INTO burnchain_db_block_headers VALUES (?1, '', '', 1, ?2)",
+            [block_height as i64, (now - age) as i64],
+        )
+        .unwrap();
+    }
+
+    db_dir
+}
+
+fn health(rpc_url: &str, db_dir: Option<&Path>, args: &[&str]) -> Value {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_stacks-doctor"));
+    command.args(["--network", "testnet", "--rpc-url", rpc_url]);
+    if let Some(db_dir) = db_dir {
+        command.arg("--db-dir").arg(db_dir);
+    }
+    let output = command.arg("health").args(args).output().unwrap();
+
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn checks(res: &Value) -> Vec<&str> {
+    res["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|problem| problem["check"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_healthy_node() {
+    let res = health(
+        &node(2_430_001, 2, None),
+        None,
+        &["--bitcoind-url", &bitcoind(2_430_002)],
+    );
+
+    assert_eq!(res["message"], "No problems detected");
+    assert_eq!(res["healthy"], true);
+    assert_eq!(res["bitcoind"]["burn_block_lag"], 1);
+    assert_eq!(res["node"]["inbound_peers"], 2);
+}
+
+#[test]
+fn test_burnchain_lag() {
+    let res = health(
+        &node(2_430_001, 2, None),
+        None,
+        &["--bitcoind-url", &bitcoind(2_430_011)],
+    );
+
+    assert_eq!(res["healthy"], false);
+    assert_eq!(checks(&res), ["sync"]);
+    assert_eq!(res["bitcoind"]["burn_block_lag"], 10);
+}
+
+#[test]
+fn test_burnchain_tip_age() {
+    let db_dir = node_dir("recent", 60);
+    let res = health(&node(2_430_001, 2, None), Some(&db_dir), &[]);
+    assert_eq!(res["healthy"], true);
+    assert_eq!(res["burnchain_tip"]["block_height"], 2_430_001);
+
+    let db_dir = node_dir("stalled", 3 * 3600);
+    let res = health(&node(2_430_001, 2, None), Some(&db_dir), &[]);
+    assert_eq!(res["healthy"], false);
+    assert_eq!(checks(&res), ["sync"]);
+
+    let res = health(
+        &node(2_430_001, 2, None),
+        Some(&db_dir),
+        &["--max-tip-age", "86400"],
+    );
+    assert_eq!(res["healthy"], true);
+}
+
+#[test]
+fn test_node_sync_status() {
+    let res = health(&node(2_430_001, 2, Some(false)), None, &[]);
+    assert_eq!(res["healthy"], false);
+    assert_eq!(checks(&res), ["sync"]);
+    assert_eq!(res["node"]["is_fully_synced"], false);
+
+    let res = health(&node(2_430_001, 2, Some(true)), None, &[]);
+    assert_eq!(res["healthy"], true);
+}
+
+#[test]
+fn test_peers_and_network() {
+    let res = health(&node(2_430_001, 0, Some(true)), None, &[]);
+    assert_eq!(checks(&res), ["peers"]);
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_stacks-doctor"));
+    let output = command
+        .args(["--network", "mainnet", "--rpc-url"])
+        .arg(node(2_430_001, 2, Some(true)))
+        .arg("health")
+        .output()
+        .unwrap();
+    let res: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(checks(&res), ["network"]);
+}
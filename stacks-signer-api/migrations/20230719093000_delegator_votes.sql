@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS delegator_weights (
+    public_key TEXT NOT NULL PRIMARY KEY,
+    weight INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS delegator_votes (
+    txid TEXT NOT NULL,
+    signing_delegator TEXT NOT NULL,
+    vote_choice TEXT NOT NULL,
+    vote_weight INTEGER NOT NULL,
+
+    PRIMARY KEY(txid, signing_delegator),
+    FOREIGN KEY(txid) REFERENCES transactions(txid) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS delegator_nonces (
+    public_key TEXT NOT NULL PRIMARY KEY,
+    nonce INTEGER NOT NULL
+);
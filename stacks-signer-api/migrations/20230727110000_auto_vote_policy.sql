@@ -0,0 +1,18 @@
+ALTER TABLE config ADD COLUMN auto_approve_daily_max_amount INTEGER;
+
+CREATE TABLE IF NOT EXISTS auto_approve_addresses (
+    address TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS auto_approve_kind_max_amounts (
+    transaction_kind TEXT NOT NULL PRIMARY KEY,
+    max_amount INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS auto_approvals (
+    txid TEXT NOT NULL PRIMARY KEY,
+    transaction_amount INTEGER NOT NULL,
+    approved_at INTEGER NOT NULL,
+
+    FOREIGN KEY(txid) REFERENCES transactions(txid) ON DELETE CASCADE
+);
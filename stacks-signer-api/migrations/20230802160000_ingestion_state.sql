@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS ingestion_state (
+    id INTEGER NOT NULL PRIMARY KEY,
+    last_processed_block_height INTEGER NOT NULL
+);
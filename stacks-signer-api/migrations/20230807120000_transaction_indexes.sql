@@ -0,0 +1,19 @@
+ALTER TABLE transactions ADD COLUMN transaction_originator_chain TEXT NOT NULL DEFAULT 'bitcoin';
+ALTER TABLE transactions ADD COLUMN transaction_debit_chain TEXT NOT NULL DEFAULT 'bitcoin';
+ALTER TABLE transactions ADD COLUMN transaction_credit_chain TEXT NOT NULL DEFAULT 'bitcoin';
+
+-- Stacks addresses are c32 encoded with a version of P, M, T or N, no Bitcoin address starts that way
+UPDATE transactions SET
+    transaction_originator_chain = CASE WHEN transaction_originator_address GLOB 'S[PMTN]*' THEN 'stacks' ELSE 'bitcoin' END,
+    transaction_debit_chain = CASE WHEN transaction_debit_address GLOB 'S[PMTN]*' THEN 'stacks' ELSE 'bitcoin' END,
+    transaction_credit_chain = CASE WHEN transaction_credit_address GLOB 'S[PMTN]*' THEN 'stacks' ELSE 'bitcoin' END;
+
+CREATE INDEX IF NOT EXISTS transactions_kind ON transactions (transaction_kind, txid);
+CREATE INDEX IF NOT EXISTS transactions_block_height ON transactions (transaction_block_height, txid);
+CREATE INDEX IF NOT EXISTS transactions_deadline_block_height ON transactions (transaction_deadline_block_height, txid);
+CREATE INDEX IF NOT EXISTS transactions_amount ON transactions (transaction_amount, txid);
+CREATE INDEX IF NOT EXISTS transactions_fees ON transactions (transaction_fees, txid);
+CREATE INDEX IF NOT EXISTS transactions_originator_address ON transactions (transaction_originator_address);
+CREATE INDEX IF NOT EXISTS transactions_debit_address ON transactions (transaction_debit_address);
+CREATE INDEX IF NOT EXISTS transactions_credit_address ON transactions (transaction_credit_address);
+CREATE INDEX IF NOT EXISTS votes_vote_status ON votes (vote_status);
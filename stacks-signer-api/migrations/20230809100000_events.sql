@@ -0,0 +1,44 @@
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_kind TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    signing_delegator TEXT,
+    vote_choice TEXT,
+    vote_status TEXT,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+CREATE TRIGGER transaction_ingested_event
+    AFTER INSERT ON transactions
+    FOR EACH ROW
+        BEGIN
+            INSERT INTO events (event_kind, txid) VALUES ('transaction_ingested', NEW.txid);
+        END;
+
+CREATE TRIGGER vote_cast_event
+    AFTER INSERT ON delegator_votes
+    FOR EACH ROW
+        BEGIN
+            INSERT INTO events (
+                event_kind, txid, signing_delegator, vote_choice
+            ) VALUES (
+                'vote_cast', NEW.txid, NEW.signing_delegator, NEW.vote_choice
+            );
+        END;
+
+-- Votes are saved with REPLACE, which runs the insert triggers while the old row is still visible
+CREATE TRIGGER vote_status_replaced_event
+    BEFORE INSERT ON votes
+    FOR EACH ROW
+        WHEN NEW.vote_status != IFNULL((SELECT vote_status FROM votes WHERE txid = NEW.txid), 'pending')
+        BEGIN
+            INSERT INTO events (event_kind, txid, vote_status) VALUES ('vote_status_changed', NEW.txid, NEW.vote_status);
+        END;
+
+CREATE TRIGGER vote_status_updated_event
+    AFTER UPDATE OF vote_status ON votes
+    FOR EACH ROW
+        WHEN NEW.vote_status != OLD.vote_status
+        BEGIN
+            INSERT INTO events (event_kind, txid, vote_status) VALUES ('vote_status_changed', NEW.txid, NEW.vote_status);
+        END;
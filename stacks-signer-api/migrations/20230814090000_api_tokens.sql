@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS api_tokens (
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    principal TEXT NOT NULL,
+    role TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS config_versions (
+    version INTEGER PRIMARY KEY AUTOINCREMENT,
+    config TEXT NOT NULL,
+    author TEXT NOT NULL,
+    restored_version INTEGER,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
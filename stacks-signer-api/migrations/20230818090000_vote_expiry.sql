@@ -0,0 +1,6 @@
+ALTER TABLE config ADD COLUMN expired_vote_choice TEXT;
+
+CREATE TABLE IF NOT EXISTS chain_state (
+    id INTEGER NOT NULL PRIMARY KEY,
+    burn_block_height INTEGER NOT NULL
+);
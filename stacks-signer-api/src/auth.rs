@@ -0,0 +1,248 @@
+use parse_display::{Display, FromStr};
+use secp256k1::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tracing::error;
+use utoipa::ToSchema;
+use warp::{hyper::StatusCode, Filter, Rejection, Reply};
+
+use crate::{db, routes::with_pool};
+
+/// The principal attributed to requests while authentication is disabled
+pub const ANONYMOUS_PRINCIPAL: &str = "anonymous";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Whether requests must carry an API token
+pub enum AuthMode {
+    /// Every request needs a bearer token with a sufficient role
+    Required,
+    /// Every request is let through as an anonymous admin. Only meant for local development.
+    Disabled,
+}
+
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    Display,
+    FromStr,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+#[display(style = "lowercase")]
+/// The role of an API token holder. Each role may do everything the roles before it may.
+pub enum Role {
+    /// May list transactions and stream events
+    Viewer,
+    /// May also vote on transactions
+    Voter,
+    /// May also read and update the signer configuration
+    Admin,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// The authenticated caller of a route
+pub struct Principal {
+    /// The name the API token was issued to
+    pub name: String,
+    /// The role of the API token
+    pub role: Role,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Reasons a request is refused by the authentication filter
+pub enum AuthError {
+    /// No bearer token was provided
+    MissingToken,
+    /// The bearer token is unknown
+    InvalidToken,
+    /// The bearer token's role does not allow the request
+    Forbidden,
+    /// The tokens could not be read from the database
+    DatabaseError,
+}
+
+impl warp::reject::Reject for AuthError {}
+
+impl AuthError {
+    /// The status code the request is refused with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::MissingToken | AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthError::Forbidden => StatusCode::FORBIDDEN,
+            AuthError::DatabaseError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Generate a new random API token.
+pub fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// Hash an API token for storage, so the database never holds usable tokens.
+pub fn hash_token(token: &str) -> String {
+    sha256::Hash::hash(token.as_bytes()).to_string()
+}
+
+/// A filter requiring a bearer token with at least the given role.
+///
+/// Requests are refused while no API token exists, unless authentication is disabled.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the Sqlite database connection pool.
+/// * auth: AuthMode - Whether requests must carry an API token.
+/// * role: Role - The minimum role allowed.
+///
+/// # Returns
+/// * impl Filter<Extract = (Principal,), Error = Rejection> + Clone: The filter extracting the caller.
+pub fn with_role(
+    pool: SqlitePool,
+    auth: AuthMode,
+    role: Role,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(with_pool(pool))
+        .and_then(
+            move |authorization: Option<String>, pool: SqlitePool| async move {
+                authenticate(authorization, auth, role, &pool)
+                    .await
+                    .map_err(warp::reject::custom)
+            },
+        )
+}
+
+async fn authenticate(
+    authorization: Option<String>,
+    auth: AuthMode,
+    role: Role,
+    pool: &SqlitePool,
+) -> Result<Principal, AuthError> {
+    if auth == AuthMode::Disabled {
+        return Ok(Principal {
+            name: ANONYMOUS_PRINCIPAL.to_string(),
+            role: Role::Admin,
+        });
+    }
+    let database_error = |e: db::Error| {
+        error!("Failed to read API tokens: {}", e);
+        AuthError::DatabaseError
+    };
+    let token = authorization
+        .as_deref()
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .ok_or(AuthError::MissingToken)?;
+    let principal = db::token::get_api_token(&hash_token(token.trim()), pool)
+        .await
+        .map_err(database_error)?
+        .ok_or(AuthError::InvalidToken)?;
+    if principal.role < role {
+        return Err(AuthError::Forbidden);
+    }
+    Ok(principal)
+}
+
+/// Reply to requests refused by the authentication filter with their status code.
+/// Any other rejection is passed on.
+pub async fn handle_rejection(rejection: Rejection) -> Result<Box<dyn Reply>, Rejection> {
+    match rejection.find::<AuthError>() {
+        Some(e) => Ok(Box::new(e.status())),
+        None => Err(rejection),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_pool;
+
+    async fn status(
+        pool: &SqlitePool,
+        auth: AuthMode,
+        role: Role,
+        token: Option<&str>,
+    ) -> StatusCode {
+        let mut request = warp::test::request().path("/");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {token}"));
+        }
+        let route = with_role(pool.clone(), auth, role)
+            .map(|principal: Principal| principal.name)
+            .recover(handle_rejection);
+        request.reply(&route).await.status()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_roles() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let required = AuthMode::Required;
+        // Without any token, every request is refused
+        assert_eq!(
+            status(&pool, required, Role::Viewer, None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let voter = generate_token();
+        db::token::add_api_token("alice", Role::Voter, &hash_token(&voter), &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            status(&pool, required, Role::Viewer, None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&pool, required, Role::Viewer, Some("unknown")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&pool, required, Role::Viewer, Some(&voter)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&pool, required, Role::Voter, Some(&voter)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&pool, required, Role::Admin, Some(&voter)).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_last_token_revoked() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let admin = generate_token();
+        db::token::add_api_token("alice", Role::Admin, &hash_token(&admin), &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            db::token::remove_api_tokens("alice", &pool).await.unwrap(),
+            1
+        );
+
+        // Revoking the last token must not open the API
+        for token in [None, Some(admin.as_str())] {
+            assert_eq!(
+                status(&pool, AuthMode::Required, Role::Viewer, token).await,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        assert_eq!(
+            status(&pool, AuthMode::Disabled, Role::Admin, None).await,
+            StatusCode::OK
+        );
+    }
+}
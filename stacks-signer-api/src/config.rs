@@ -1,5 +1,11 @@
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    transaction::TransactionKind,
+    vote::{VoteChoice, VoteStatus},
+};
 use utoipa::{ToResponse, ToSchema};
 
 const DEFAULT_MAX_AMOUNT: u64 = 100_000;
@@ -30,10 +36,20 @@ pub struct RawConfig {
     pub delegate_public_key: Option<PublicKey>,
     /// The public keys of signers that this signer has agreed to sign on behalf of
     pub delegator_public_keys: Option<Vec<PublicKey>>,
+    /// The voting weight (number of keys) of each signer, keyed by hex encoded public key
+    pub delegator_weights: Option<BTreeMap<String, u64>>,
     /// The addresses to be auto denied
     pub auto_deny_addresses: Option<Vec<String>>,
     /// The maximum dollar amount of a transaction that will be auto approved
     pub auto_approve_max_amount: Option<u64>,
+    /// The only originating addresses whose transactions may be auto approved
+    pub auto_approve_addresses: Option<Vec<String>>,
+    /// Per transaction kind overrides of the auto approve maximum amount
+    pub auto_approve_kind_max_amounts: Option<BTreeMap<TransactionKind, u64>>,
+    /// The maximum total amount of transactions auto approved within a day
+    pub auto_approve_daily_max_amount: Option<u64>,
+    /// The outcome of ballots still pending at their deadline
+    pub expired_vote_choice: Option<VoteChoice>,
 }
 
 impl RawConfig {
@@ -58,8 +74,43 @@ pub struct Config {
     /// The public keys of signers that this signer has agreed to sign on behalf of
     #[schema(value_type = Vec<String>)]
     pub delegator_public_keys: Vec<PublicKey>,
+    /// The voting weight (number of keys) of each signer, keyed by hex encoded public key.
+    /// Signers without an entry have a weight of one.
+    #[serde(default)]
+    pub delegator_weights: BTreeMap<String, u64>,
     /// The addresses to be auto denied
     pub auto_deny_addresses: Vec<String>,
+    /// The only originating addresses whose transactions may be auto approved.
+    /// Any originating address may be auto approved if empty.
+    #[serde(default)]
+    pub auto_approve_addresses: Vec<String>,
+    /// Per transaction kind overrides of the auto approve maximum amount
+    #[serde(default)]
+    #[schema(value_type = BTreeMap<String, u64>)]
+    pub auto_approve_kind_max_amounts: BTreeMap<TransactionKind, u64>,
+    /// The maximum total amount of transactions auto approved within a day
+    #[serde(default)]
+    pub auto_approve_daily_max_amount: Option<u64>,
+    /// The outcome of ballots still pending at their deadline.
+    /// Such ballots close without consensus if unset.
+    #[serde(default)]
+    pub expired_vote_choice: Option<VoteChoice>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, ToResponse, ToSchema)]
+/// A saved version of the signer configuration.
+pub struct ConfigVersion {
+    /// The increasing version number
+    pub version: u64,
+    /// The configuration saved in this version. Versions are saved without the secret key,
+    /// so it holds the current one.
+    pub config: Config,
+    /// The principal that saved this version
+    pub author: String,
+    /// The version this one restored, if it was saved by a rollback
+    pub restored_version: Option<u64>,
+    /// The time this version was saved, in seconds since the Unix epoch
+    pub created_at: u64,
 }
 
 impl Config {
@@ -72,10 +123,75 @@ impl Config {
             delegate_public_key: public_key,
             auto_approve_max_amount: DEFAULT_MAX_AMOUNT,
             delegator_public_keys: vec![],
+            delegator_weights: BTreeMap::new(),
             auto_deny_addresses: vec![],
+            auto_approve_addresses: vec![],
+            auto_approve_kind_max_amounts: BTreeMap::new(),
+            auto_approve_daily_max_amount: None,
+            expired_vote_choice: None,
         }
     }
 
+    /// The status ballots still pending at their deadline are closed with.
+    pub fn expired_vote_status(&self) -> VoteStatus {
+        match self.expired_vote_choice {
+            Some(VoteChoice::Approve) => VoteStatus::Approved,
+            Some(VoteChoice::Reject) => VoteStatus::Rejected,
+            None => VoteStatus::NoConsensus,
+        }
+    }
+
+    /// The signers eligible to vote: the delegate and every delegator it signs on behalf of.
+    pub fn voters(&self) -> BTreeSet<PublicKey> {
+        std::iter::once(self.delegate_public_key)
+            .chain(self.delegator_public_keys.iter().copied())
+            .collect()
+    }
+
+    /// The voting weight of the given signer, or None if it is not eligible to vote.
+    pub fn voting_weight(&self, public_key: &PublicKey) -> Option<u64> {
+        if self.voters().contains(public_key) {
+            Some(self.weight(public_key))
+        } else {
+            None
+        }
+    }
+
+    /// The combined voting weight of all eligible signers.
+    pub fn total_voting_weight(&self) -> u64 {
+        self.voters()
+            .iter()
+            .map(|public_key| self.weight(public_key))
+            .sum()
+    }
+
+    /// Whether both configurations hold the same settings. Their public key and address lists
+    /// are compared as sets, as the order they are stored and read back in is not kept.
+    pub fn same_settings(&self, other: &Config) -> bool {
+        self.normalized() == other.normalized()
+    }
+
+    fn normalized(&self) -> Config {
+        let mut config = self.clone();
+        for addresses in [
+            &mut config.auto_deny_addresses,
+            &mut config.auto_approve_addresses,
+        ] {
+            addresses.sort();
+            addresses.dedup();
+        }
+        config.delegator_public_keys.sort();
+        config.delegator_public_keys.dedup();
+        config
+    }
+
+    fn weight(&self, public_key: &PublicKey) -> u64 {
+        self.delegator_weights
+            .get(&public_key.to_string())
+            .copied()
+            .unwrap_or(1)
+    }
+
     /// Try to create a new signer configuration with a given hex encoded secret key string.
     pub fn from_secret_key(secret_key: &str) -> Result<Self, Error> {
         let secret_bytes = hex::decode(secret_key)?;
@@ -102,7 +218,14 @@ impl TryFrom<RawConfig> for Config {
                 .auto_approve_max_amount
                 .unwrap_or(DEFAULT_MAX_AMOUNT),
             delegator_public_keys: raw_config.delegator_public_keys.unwrap_or(vec![]),
+            delegator_weights: raw_config.delegator_weights.unwrap_or_default(),
             auto_deny_addresses: raw_config.auto_deny_addresses.unwrap_or(vec![]),
+            auto_approve_addresses: raw_config.auto_approve_addresses.unwrap_or_default(),
+            auto_approve_kind_max_amounts: raw_config
+                .auto_approve_kind_max_amounts
+                .unwrap_or_default(),
+            auto_approve_daily_max_amount: raw_config.auto_approve_daily_max_amount,
+            expired_vote_choice: raw_config.expired_vote_choice,
         })
     }
 }
@@ -1,43 +1,247 @@
-use crate::{config::Config, db::Error};
+use crate::{
+    config::{Config, ConfigVersion},
+    db::{begin_write, Error},
+    transaction::TransactionKind,
+};
 
 use secp256k1::{PublicKey, SecretKey};
-use sqlx::SqlitePool;
-use std::str::FromStr;
+use serde_json::Value;
+use sqlx::{sqlite::SqliteConnection, Executor, Sqlite, SqlitePool};
+use std::{collections::BTreeMap, str::FromStr};
 
 /// Helper function for retriving a signer from the database given a signer ID.
 pub async fn get_config(pool: &SqlitePool) -> Result<Config, Error> {
-    let row = sqlx::query!("SELECT * FROM config").fetch_one(pool).await?;
+    read_config(&mut *pool.acquire().await?).await
+}
+
+/// Helper function for reading the configuration in place from the database.
+async fn read_config(conn: &mut SqliteConnection) -> Result<Config, Error> {
+    let row = sqlx::query!("SELECT * FROM config")
+        .fetch_one(&mut *conn)
+        .await?;
     let secret_bytes = hex::decode(row.secret_key)?;
     Ok(Config {
         secret_key: SecretKey::from_slice(&secret_bytes)?,
         auto_approve_max_amount: row.auto_approve_max_amount as u64,
         delegate_public_key: PublicKey::from_str(row.delegate_public_key.as_str())?,
-        delegator_public_keys: get_delegator_public_keys(pool).await?,
-        auto_deny_addresses: get_auto_deny_addresses(pool).await?,
+        delegator_public_keys: get_delegator_public_keys(&mut *conn).await?,
+        delegator_weights: get_delegator_weights(&mut *conn).await?,
+        auto_deny_addresses: get_auto_deny_addresses(&mut *conn).await?,
+        auto_approve_addresses: get_auto_approve_addresses(&mut *conn).await?,
+        auto_approve_kind_max_amounts: get_auto_approve_kind_max_amounts(conn).await?,
+        auto_approve_daily_max_amount: row
+            .auto_approve_daily_max_amount
+            .map(|amount| amount as u64),
+        expired_vote_choice: row
+            .expired_vote_choice
+            .map(|choice| choice.parse())
+            .transpose()?,
     })
 }
 
-/// Helper function for adding a signer to the database.
-pub async fn update_config(pool: &SqlitePool, config: &Config) -> Result<(), Error> {
+/// Save the signer configuration to the database as a new version.
+/// Saving the configuration already in place records no version.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * config: &Config - The configuration to save.
+/// * author: &str - The principal saving the configuration.
+///
+/// # Returns
+/// * Result<(), Error>: The result of the database operation.
+pub async fn update_config(pool: &SqlitePool, config: &Config, author: &str) -> Result<(), Error> {
+    let mut tx = begin_write(pool).await?;
+    let unchanged = read_config(&mut tx)
+        .await
+        .is_ok_and(|current| current.same_settings(config));
+    if !unchanged {
+        write_config(&mut tx, config).await?;
+        add_config_version(&mut tx, config, author, None).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Restore a previous version of the signer configuration, saving it as a new version.
+/// Versions are saved without the secret key, so the current secret key is kept.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * version: u64 - The version to restore.
+/// * author: &str - The principal restoring the configuration.
+///
+/// # Returns
+/// * Result<Config, Error>: The restored configuration.
+pub async fn rollback_config(
+    pool: &SqlitePool,
+    version: u64,
+    author: &str,
+) -> Result<Config, Error> {
+    let mut tx = begin_write(pool).await?;
+    let config = get_config_version(version, &mut tx).await?.config;
+    write_config(&mut tx, &config).await?;
+    add_config_version(&mut tx, &config, author, Some(version)).await?;
+    tx.commit().await?;
+    Ok(config)
+}
+
+/// Get a saved version of the signer configuration, holding the current secret key.
+///
+/// # Params
+/// * version: u64 - The version to get.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<ConfigVersion, Error>: The saved version, or a RowNotFound error if there is none.
+pub async fn get_config_version(
+    version: u64,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<ConfigVersion, Error> {
+    let version = version as i64;
+    let row = sqlx::query!(
+        "SELECT v.version, v.config, v.author, v.restored_version, v.created_at, c.secret_key FROM config_versions v JOIN config c WHERE v.version = ?",
+        version
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(ConfigVersion {
+        version: row.version as u64,
+        config: versioned_config(&row.config, &row.secret_key)?,
+        author: row.author,
+        restored_version: row.restored_version.map(|version| version as u64),
+        created_at: row.created_at as u64,
+    })
+}
+
+/// Get a page of the saved versions of the signer configuration, newest first.
+/// Each version holds the current secret key.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * page: Option<usize> - The page number, starting at 1. Defaults to the first page.
+/// * limit: Option<usize> - The maximum number of versions per page. Defaults to all versions.
+///
+/// # Returns
+/// * Result<Vec<ConfigVersion>, Error>: The saved versions on the page.
+pub async fn get_config_history(
+    pool: &SqlitePool,
+    page: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<ConfigVersion>, Error> {
+    let offset = page
+        .unwrap_or(1)
+        .saturating_sub(1)
+        .saturating_mul(limit.unwrap_or(0))
+        .min(i64::MAX as usize) as i64;
+    // A negative limit returns every row
+    let limit = limit.map_or(-1, |limit| limit.min(i64::MAX as usize) as i64);
+    sqlx::query!(
+        "SELECT v.version, v.config, v.author, v.restored_version, v.created_at, c.secret_key FROM config_versions v JOIN config c ORDER BY v.version DESC LIMIT ?1 OFFSET ?2",
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Ok(ConfigVersion {
+            version: row.version as u64,
+            config: versioned_config(&row.config, &row.secret_key)?,
+            author: row.author,
+            restored_version: row.restored_version.map(|version| version as u64),
+            created_at: row.created_at as u64,
+        })
+    })
+    .collect()
+}
+
+/// Helper function for recording a saved configuration in the config history.
+/// The secret key is left out, so the history never holds past or present keys.
+async fn add_config_version(
+    conn: &mut SqliteConnection,
+    config: &Config,
+    author: &str,
+    restored_version: Option<u64>,
+) -> Result<(), Error> {
+    let mut config = serde_json::to_value(config)?;
+    if let Value::Object(fields) = &mut config {
+        fields.remove("secret_key");
+    }
+    let config = config.to_string();
+    let restored_version = restored_version.map(|version| version as i64);
+    sqlx::query!(
+        "INSERT INTO config_versions (config, author, restored_version) VALUES (?1, ?2, ?3)",
+        config,
+        author,
+        restored_version
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Helper function for restoring a saved configuration with the given hex encoded secret key.
+fn versioned_config(config: &str, secret_key: &str) -> Result<Config, Error> {
+    let mut config: Value = serde_json::from_str(config)?;
+    if let Value::Object(fields) = &mut config {
+        fields.insert("secret_key".to_string(), Value::from(secret_key));
+    }
+    Ok(serde_json::from_value(config)?)
+}
+
+/// Helper function for writing the configuration in place to the database.
+async fn write_config(conn: &mut SqliteConnection, config: &Config) -> Result<(), Error> {
     let secret_key = hex::encode(config.secret_key.secret_bytes()).to_string();
     let auto_approve_max_amount = config.auto_approve_max_amount as i64;
     let delegate_public_key = config.delegate_public_key.to_string();
+    let auto_approve_daily_max_amount = config
+        .auto_approve_daily_max_amount
+        .map(|amount| amount as i64);
+    let expired_vote_choice = config.expired_vote_choice.map(|choice| choice.to_string());
     sqlx::query!(
-        "REPLACE INTO config (id, secret_key, delegate_public_key, auto_approve_max_amount) VALUES (?1, ?2, ?3, ?4)",
+        "REPLACE INTO config (id, secret_key, delegate_public_key, auto_approve_max_amount, auto_approve_daily_max_amount, expired_vote_choice) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         1,
         secret_key,
         delegate_public_key,
         auto_approve_max_amount,
+        auto_approve_daily_max_amount,
+        expired_vote_choice,
     )
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
+    // Clear the previous lists so entries removed from the config are dropped
+    sqlx::query!("DELETE FROM delegator_public_keys")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM delegator_weights")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM auto_deny_addresses")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM auto_approve_addresses")
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM auto_approve_kind_max_amounts")
+        .execute(&mut *conn)
+        .await?;
     for delegator_public_key in &config.delegator_public_keys {
         let delegator_public_key = delegator_public_key.to_string();
         sqlx::query!(
             "REPLACE INTO delegator_public_keys (public_key) VALUES (?1)",
             delegator_public_key,
         )
-        .execute(pool)
+        .execute(&mut *conn)
+        .await?;
+    }
+    for (public_key, weight) in &config.delegator_weights {
+        let weight = *weight as i64;
+        sqlx::query!(
+            "REPLACE INTO delegator_weights (public_key, weight) VALUES (?1, ?2)",
+            public_key,
+            weight,
+        )
+        .execute(&mut *conn)
         .await?;
     }
     for address in &config.auto_deny_addresses {
@@ -45,16 +249,37 @@ pub async fn update_config(pool: &SqlitePool, config: &Config) -> Result<(), Err
             "REPLACE INTO auto_deny_addresses (address) VALUES (?1)",
             address,
         )
-        .execute(pool)
+        .execute(&mut *conn)
+        .await?;
+    }
+    for address in &config.auto_approve_addresses {
+        sqlx::query!(
+            "REPLACE INTO auto_approve_addresses (address) VALUES (?1)",
+            address,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    for (transaction_kind, max_amount) in &config.auto_approve_kind_max_amounts {
+        let transaction_kind = transaction_kind.to_string();
+        let max_amount = *max_amount as i64;
+        sqlx::query!(
+            "REPLACE INTO auto_approve_kind_max_amounts (transaction_kind, max_amount) VALUES (?1, ?2)",
+            transaction_kind,
+            max_amount,
+        )
+        .execute(&mut *conn)
         .await?;
     }
     Ok(())
 }
 
 /// Helper function for retrieving the auto deny addresses for a signer.
-async fn get_auto_deny_addresses(pool: &SqlitePool) -> Result<Vec<String>, Error> {
+async fn get_auto_deny_addresses(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Vec<String>, Error> {
     let addresses: Vec<String> = sqlx::query!("SELECT address FROM auto_deny_addresses")
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await?
         .iter()
         .map(|row| row.address.clone())
@@ -62,16 +287,56 @@ async fn get_auto_deny_addresses(pool: &SqlitePool) -> Result<Vec<String>, Error
     Ok(addresses)
 }
 
+/// Helper function for retrieving the addresses allowed to be auto approved for a signer.
+async fn get_auto_approve_addresses(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Vec<String>, Error> {
+    let addresses: Vec<String> = sqlx::query!("SELECT address FROM auto_approve_addresses")
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(|row| row.address.clone())
+        .collect();
+    Ok(addresses)
+}
+
+/// Helper function for retrieving the per transaction kind auto approve limits for a signer.
+async fn get_auto_approve_kind_max_amounts(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<BTreeMap<TransactionKind, u64>, Error> {
+    sqlx::query!("SELECT transaction_kind, max_amount FROM auto_approve_kind_max_amounts")
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(|row| Ok((row.transaction_kind.parse()?, row.max_amount as u64)))
+        .collect()
+}
+
 /// Helper function for retrieving the delegator public keys for a signer.
-async fn get_delegator_public_keys(pool: &SqlitePool) -> Result<Vec<PublicKey>, Error> {
+async fn get_delegator_public_keys(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Vec<PublicKey>, Error> {
     sqlx::query!("SELECT public_key FROM delegator_public_keys")
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await?
         .iter()
         .map(|row| PublicKey::from_str(row.public_key.as_str()).map_err(Error::from))
         .collect()
 }
 
+/// Helper function for retrieving the voting weights of a signer's delegators.
+async fn get_delegator_weights(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<BTreeMap<String, u64>, Error> {
+    let weights = sqlx::query!("SELECT public_key, weight FROM delegator_weights")
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(|row| (row.public_key.clone(), row.weight as u64))
+        .collect();
+    Ok(weights)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,7 +364,7 @@ mod tests {
         let mut expected_config =
             Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
         // Insert an initial config:
-        update_config(&pool, &expected_config)
+        update_config(&pool, &expected_config, "test")
             .await
             .expect("failed to add config");
         let config = get_config(&pool).await.expect("Failed to get signers");
@@ -109,7 +374,7 @@ mod tests {
         );
         // Update the config and verify the database updated
         expected_config.auto_approve_max_amount = 10;
-        update_config(&pool, &expected_config)
+        update_config(&pool, &expected_config, "test")
             .await
             .expect("failed to add config");
 
@@ -130,7 +395,7 @@ mod tests {
         let expected_config =
             Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
         // Insert an initial config:
-        update_config(&pool, &expected_config)
+        update_config(&pool, &expected_config, "test")
             .await
             .expect("failed to add config");
         let config = get_config(&pool).await.expect("Failed to get signers");
@@ -165,7 +430,7 @@ mod tests {
             PublicKey::from_str(TEST_PUBLIC_KEY_3).expect("Failed to parse public key."),
         ];
 
-        update_config(&pool, &config)
+        update_config(&pool, &config, "test")
             .await
             .expect("failed to add config");
         let keys = get_delegator_public_keys(&pool)
@@ -174,6 +439,53 @@ mod tests {
         assert_eq!(keys, config.delegator_public_keys);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_get_delegator_weights() {
+        let pool = init_db().await;
+        let mut config =
+            Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
+        config.delegator_weights = BTreeMap::from([
+            (TEST_PUBLIC_KEY_2.to_string(), 3),
+            (TEST_PUBLIC_KEY_3.to_string(), 5),
+        ]);
+
+        update_config(&pool, &config, "test")
+            .await
+            .expect("failed to add config");
+        let weights = get_delegator_weights(&pool)
+            .await
+            .expect("failed to get delegator weights");
+        assert_eq!(weights, config.delegator_weights);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_auto_approve_policy() {
+        let pool = init_db().await;
+        let mut config =
+            Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
+        config.auto_approve_addresses = vec!["address1".to_string(), "address2".to_string()];
+        config.auto_approve_kind_max_amounts = BTreeMap::from([
+            (TransactionKind::DepositReveal, 10),
+            (TransactionKind::WalletHandoff, 0),
+        ]);
+        config.auto_approve_daily_max_amount = Some(1000);
+        update_config(&pool, &config, "test")
+            .await
+            .expect("failed to add config");
+        assert_eq!(get_config(&pool).await.unwrap(), config);
+
+        // Removed rules must not linger after an update
+        config.auto_approve_addresses = vec!["address2".to_string()];
+        config.auto_approve_kind_max_amounts.clear();
+        config.auto_approve_daily_max_amount = None;
+        update_config(&pool, &config, "test")
+            .await
+            .expect("failed to update config");
+        assert_eq!(get_config(&pool).await.unwrap(), config);
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     #[ntest::timeout(1000)]
     async fn test_get_auto_deny_addresses() {
@@ -186,7 +498,7 @@ mod tests {
             "address3".to_string(),
         ];
 
-        update_config(&pool, &config)
+        update_config(&pool, &config, "test")
             .await
             .expect("failed to add config");
         let addresses = get_auto_deny_addresses(&pool)
@@ -194,4 +506,107 @@ mod tests {
             .expect("failed to get auto deny addresses");
         assert_eq!(addresses, config.auto_deny_addresses);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_rollback_config() {
+        let pool = init_db().await;
+        let mut config =
+            Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
+        update_config(&pool, &config, "alice")
+            .await
+            .expect("failed to add config");
+        let first_version = get_config_history(&pool, None, None).await.unwrap()[0].version;
+        config.auto_approve_max_amount = 10;
+        config.auto_deny_addresses = vec!["address1".to_string()];
+        update_config(&pool, &config, "bob")
+            .await
+            .expect("failed to update config");
+
+        let restored = rollback_config(&pool, first_version, "carol")
+            .await
+            .expect("failed to roll back config");
+        assert_eq!(get_config(&pool).await.unwrap(), restored);
+        assert_eq!(
+            restored,
+            get_config_version(first_version, &pool)
+                .await
+                .unwrap()
+                .config
+        );
+        let history = get_config_history(&pool, None, None).await.unwrap();
+        let summary: Vec<_> = history
+            .iter()
+            .map(|version| (version.author.as_str(), version.restored_version))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("carol", Some(first_version)),
+                ("bob", None),
+                ("alice", None)
+            ]
+        );
+        assert!(rollback_config(&pool, 42, "carol").await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_update_config_ignores_list_order() {
+        let pool = init_db().await;
+        let mut config =
+            Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
+        config.auto_deny_addresses = vec!["address1".to_string(), "address2".to_string()];
+        config.delegator_public_keys = vec![
+            PublicKey::from_str(TEST_PUBLIC_KEY_2).expect("Failed to parse public key."),
+            PublicKey::from_str(TEST_PUBLIC_KEY_3).expect("Failed to parse public key."),
+        ];
+        update_config(&pool, &config, "alice")
+            .await
+            .expect("failed to add config");
+
+        config.auto_deny_addresses.reverse();
+        config.delegator_public_keys.reverse();
+        update_config(&pool, &config, "bob")
+            .await
+            .expect("failed to update config");
+        assert_eq!(
+            get_config_history(&pool, None, None).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_config_versions_without_secret_key() {
+        let pool = init_db().await;
+        let config = Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config.");
+        update_config(&pool, &config, "alice")
+            .await
+            .expect("failed to add config");
+        let first_version = get_config_history(&pool, None, None).await.unwrap()[0].version;
+
+        // Rotate the secret key
+        let mut rotated = config.clone();
+        rotated.secret_key = SecretKey::from_slice(&[2; 32]).unwrap();
+        update_config(&pool, &rotated, "bob")
+            .await
+            .expect("failed to update config");
+
+        let rows = sqlx::query!("SELECT config FROM config_versions")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            assert!(!row.config.contains("secret_key"));
+        }
+
+        // Rolling back restores the settings but keeps the current secret key
+        let restored = rollback_config(&pool, first_version, "carol")
+            .await
+            .expect("failed to roll back config");
+        assert_eq!(restored.secret_key, rotated.secret_key);
+        assert_eq!(get_config(&pool).await.unwrap(), restored);
+    }
 }
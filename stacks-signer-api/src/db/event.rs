@@ -0,0 +1,177 @@
+use sqlx::SqlitePool;
+
+use crate::{db::Error, event::Event};
+
+/// Get the events recorded after a given event ID, oldest first.
+///
+/// # Params
+/// * id: u64 - The ID of the last event already seen, or 0 for all events.
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<Vec<Event>, Error>: The events found in the database.
+pub async fn get_events_after(id: u64, pool: &SqlitePool) -> Result<Vec<Event>, Error> {
+    let id = id as i64;
+    sqlx::query!("SELECT * FROM events WHERE id > ? ORDER BY id", id)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(Event {
+                id: row.id as u64,
+                event_kind: row.event_kind.parse()?,
+                txid: row.txid.clone(),
+                signing_delegator: row.signing_delegator.clone(),
+                vote_choice: row
+                    .vote_choice
+                    .as_ref()
+                    .map(|choice| choice.parse())
+                    .transpose()?,
+                vote_status: row
+                    .vote_status
+                    .as_ref()
+                    .map(|status| status.parse())
+                    .transpose()?,
+                created_at: row.created_at as u64,
+            })
+        })
+        .collect()
+}
+
+/// Get the ID of the most recently recorded event.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<Option<u64>, Error>: The last event ID, or None if no event was recorded yet.
+pub async fn get_last_event_id(pool: &SqlitePool) -> Result<Option<u64>, Error> {
+    let row = sqlx::query!("SELECT id FROM events ORDER BY id DESC LIMIT 1")
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|row| row.id as u64))
+}
+
+/// Delete the events recorded before a given time.
+///
+/// # Params
+/// * before: u64 - The time in seconds since the Unix epoch before which events are deleted.
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<u64, Error>: The number of events deleted.
+pub async fn prune_events(before: u64, pool: &SqlitePool) -> Result<u64, Error> {
+    let before = before as i64;
+    let result = sqlx::query!("DELETE FROM events WHERE created_at < ?", before)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{
+            init_pool,
+            transaction::add_transaction,
+            vote::{add_vote, close_expired_votes, get_vote_by_id},
+        },
+        event::EventKind,
+        transaction::Transaction,
+        vote::VoteStatus,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_vote_status_events() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        assert_eq!(get_last_event_id(&pool).await.unwrap(), None);
+
+        let approved = Transaction {
+            txid: "1".repeat(64),
+            ..Default::default()
+        };
+        let expired = Transaction {
+            txid: "2".repeat(64),
+            transaction_deadline_block_height: 10,
+            ..Default::default()
+        };
+        for tx in [&approved, &expired] {
+            add_transaction(&pool, tx)
+                .await
+                .expect("failed to add transaction");
+        }
+        // Updating a transaction is not an ingestion
+        add_transaction(&pool, &approved)
+            .await
+            .expect("failed to update transaction");
+
+        let mut vote = get_vote_by_id(&approved.txid, &pool).await.unwrap();
+        // Saving a vote without a status change records no event
+        add_vote(&vote, &pool).await.unwrap();
+        vote.vote_tally.vote_status = VoteStatus::Approved;
+        add_vote(&vote, &pool).await.unwrap();
+        close_expired_votes(11, VoteStatus::NoConsensus, &pool)
+            .await
+            .unwrap();
+
+        let events = get_events_after(0, &pool).await.unwrap();
+        let summary: Vec<_> = events
+            .iter()
+            .map(|event| (event.event_kind, event.txid.clone(), event.vote_status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (EventKind::TransactionIngested, approved.txid.clone(), None),
+                (EventKind::TransactionIngested, expired.txid.clone(), None),
+                (
+                    EventKind::VoteStatusChanged,
+                    approved.txid.clone(),
+                    Some(VoteStatus::Approved)
+                ),
+                (
+                    EventKind::VoteStatusChanged,
+                    expired.txid.clone(),
+                    Some(VoteStatus::NoConsensus)
+                ),
+            ]
+        );
+        assert_eq!(get_last_event_id(&pool).await.unwrap(), Some(events[3].id));
+        assert_eq!(
+            get_events_after(events[2].id, &pool).await.unwrap(),
+            vec![events[3].clone()]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_prune_events() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        add_transaction(&pool, &Transaction::default())
+            .await
+            .expect("failed to add transaction");
+        let event = get_events_after(0, &pool).await.unwrap().remove(0);
+
+        assert_eq!(prune_events(event.created_at, &pool).await.unwrap(), 0);
+        assert_eq!(prune_events(event.created_at + 1, &pool).await.unwrap(), 1);
+        assert!(get_events_after(0, &pool).await.unwrap().is_empty());
+
+        // Event IDs keep increasing after all events were pruned
+        add_transaction(
+            &pool,
+            &Transaction {
+                txid: "1".repeat(64),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("failed to add transaction");
+        assert!(get_last_event_id(&pool).await.unwrap().unwrap() > event.id);
+    }
+}
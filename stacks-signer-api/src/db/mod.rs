@@ -1,13 +1,17 @@
 #![deny(missing_docs)]
 /// Module that handles signers-related database operations
 pub mod config;
+/// Module that handles event-related database operations
+pub mod event;
+/// Module that handles API token-related database operations
+pub mod token;
 /// Module that handles transaction-related database operations
 pub mod transaction;
 /// Module that handles vote-related database operations
 pub mod vote;
 
 use parse_display::ParseError;
-use sqlx::SqlitePool;
+use sqlx::{Sqlite, SqlitePool, Transaction};
 
 /// Custom error type for this database module
 #[derive(thiserror::Error, Debug)]
@@ -27,6 +31,9 @@ pub enum Error {
     /// Secret key parsing related error
     #[error("Invalid Secret Key: {0}")]
     InvalidSecretKey(#[from] hex::FromHexError),
+    /// Serialized data related error
+    #[error("Serde JSON Error: {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
 }
 
 impl warp::reject::Reject for Error {}
@@ -46,3 +53,23 @@ pub async fn init_pool(path: Option<String>) -> Result<SqlitePool, Error> {
     sqlx::migrate!().run(&pool).await?;
     Ok(pool)
 }
+
+/// Begin a transaction that holds the database write lock from its start.
+///
+/// SQLite only takes the write lock at a transaction's first write, so two transactions that
+/// read before they write could both act on what they read before the other committed. Holding
+/// the lock from the start makes such transactions wait for each other instead.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<Transaction<'static, Sqlite>, Error>: The transaction, rolled back unless committed.
+pub async fn begin_write(pool: &SqlitePool) -> Result<Transaction<'static, Sqlite>, Error> {
+    let mut transaction = pool.begin().await?;
+    // Any write takes the lock, even one that changes no rows
+    sqlx::query("UPDATE chain_state SET id = id WHERE 0")
+        .execute(&mut transaction)
+        .await?;
+    Ok(transaction)
+}
@@ -0,0 +1,111 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::{Principal, Role},
+    db::Error,
+};
+
+/// Add an API token to the database.
+///
+/// # Params
+/// * principal: &str - The name the token is issued to.
+/// * role: Role - The role granted to the token.
+/// * token_hash: &str - The hash of the token.
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<(), Error>: The result of the database operation.
+pub async fn add_api_token(
+    principal: &str,
+    role: Role,
+    token_hash: &str,
+    pool: &SqlitePool,
+) -> Result<(), Error> {
+    let role = role.to_string();
+    sqlx::query!(
+        "INSERT INTO api_tokens (token_hash, principal, role) VALUES (?1, ?2, ?3)",
+        token_hash,
+        principal,
+        role
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Get the holder of an API token from the database.
+///
+/// # Params
+/// * token_hash: &str - The hash of the token.
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<Option<Principal>, Error>: The token holder, or None if the token is unknown.
+pub async fn get_api_token(
+    token_hash: &str,
+    pool: &SqlitePool,
+) -> Result<Option<Principal>, Error> {
+    sqlx::query!(
+        "SELECT principal, role FROM api_tokens WHERE token_hash = ?",
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|row| {
+        Ok(Principal {
+            name: row.principal,
+            role: row.role.parse()?,
+        })
+    })
+    .transpose()
+}
+
+/// Get the holders of all API tokens from the database.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<Vec<Principal>, Error>: The token holders, one per token.
+pub async fn get_api_tokens(pool: &SqlitePool) -> Result<Vec<Principal>, Error> {
+    sqlx::query!("SELECT principal, role FROM api_tokens ORDER BY principal, role")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Principal {
+                name: row.principal,
+                role: row.role.parse()?,
+            })
+        })
+        .collect()
+}
+
+/// Check whether any API token exists in the database.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<bool, Error>: Whether any API token exists.
+pub async fn has_api_tokens(pool: &SqlitePool) -> Result<bool, Error> {
+    let row = sqlx::query!("SELECT token_hash FROM api_tokens LIMIT 1")
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Remove all API tokens issued to a principal from the database.
+///
+/// # Params
+/// * principal: &str - The name the tokens were issued to.
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<u64, Error>: The number of tokens removed.
+pub async fn remove_api_tokens(principal: &str, pool: &SqlitePool) -> Result<u64, Error> {
+    let result = sqlx::query!("DELETE FROM api_tokens WHERE principal = ?1", principal)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
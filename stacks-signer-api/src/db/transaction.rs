@@ -1,19 +1,30 @@
 use crate::{
     db::Error,
-    transaction::{Transaction, TransactionAddress},
+    transaction::{
+        SortOrder, Transaction, TransactionAddress, TransactionPage, TransactionQuery,
+        TransactionResponse, TransactionSort, DEFAULT_TRANSACTION_LIMIT, MAX_TRANSACTION_LIMIT,
+    },
+    vote::VoteTally,
 };
 
-use sqlx::SqlitePool;
+use sqlx::{
+    query::Query,
+    sqlite::{Sqlite, SqliteArguments, SqliteConnection, SqliteRow},
+    Executor, Row, SqlitePool,
+};
 
-/// Add a given transaction to the database.
+/// Add a given transaction to the database, updating it if it already exists.
 ///
 /// # Params
-/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
 /// * transaction: Transaction - The transaction object to add to the database.
 ///
 /// # Returns
 /// * Result<(), DatabaseError>: The result of the database operation.
-pub async fn add_transaction(pool: &SqlitePool, transaction: &Transaction) -> Result<(), Error> {
+pub async fn add_transaction(
+    executor: impl Executor<'_, Database = Sqlite>,
+    transaction: &Transaction,
+) -> Result<(), Error> {
     let txid = transaction.txid.clone();
     let transaction_kind = &transaction.transaction_kind.to_string();
     let transaction_block_height = transaction
@@ -23,17 +34,37 @@ pub async fn add_transaction(pool: &SqlitePool, transaction: &Transaction) -> Re
     let transaction_amount = transaction.transaction_amount as i64;
     let transaction_fees = transaction.transaction_fees as i64;
     let memo = &transaction.memo;
-    let transaction_originator_address = ""; //&transaction.transaction_originator_address.0;
-    let transaction_debit_address = ""; //&transaction.transaction_debit_address.0;
-    let transaction_credit_address = ""; //&transaction.transaction_credit_address.0;
+    let transaction_originator_address = transaction.transaction_originator_address.address();
+    let transaction_originator_chain = transaction
+        .transaction_originator_address
+        .chain()
+        .to_string();
+    let transaction_debit_address = transaction.transaction_debit_address.address();
+    let transaction_debit_chain = transaction.transaction_debit_address.chain().to_string();
+    let transaction_credit_address = transaction.transaction_credit_address.address();
+    let transaction_credit_chain = transaction.transaction_credit_address.chain().to_string();
 
     sqlx::query!(
         r#"
-    REPLACE INTO transactions (
+    INSERT INTO transactions (
         txid, transaction_kind, transaction_block_height, transaction_deadline_block_height,
         transaction_amount, transaction_fees, memo, transaction_originator_address,
-        transaction_debit_address, transaction_credit_address
-    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        transaction_originator_chain, transaction_debit_address, transaction_debit_chain,
+        transaction_credit_address, transaction_credit_chain
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT(txid) DO UPDATE SET
+        transaction_kind = excluded.transaction_kind,
+        transaction_block_height = excluded.transaction_block_height,
+        transaction_deadline_block_height = excluded.transaction_deadline_block_height,
+        transaction_amount = excluded.transaction_amount,
+        transaction_fees = excluded.transaction_fees,
+        memo = excluded.memo,
+        transaction_originator_address = excluded.transaction_originator_address,
+        transaction_originator_chain = excluded.transaction_originator_chain,
+        transaction_debit_address = excluded.transaction_debit_address,
+        transaction_debit_chain = excluded.transaction_debit_chain,
+        transaction_credit_address = excluded.transaction_credit_address,
+        transaction_credit_chain = excluded.transaction_credit_chain"#,
         txid,
         transaction_kind,
         transaction_block_height,
@@ -42,10 +73,13 @@ pub async fn add_transaction(pool: &SqlitePool, transaction: &Transaction) -> Re
         transaction_fees,
         memo,
         transaction_originator_address,
+        transaction_originator_chain,
         transaction_debit_address,
-        transaction_credit_address
+        transaction_debit_chain,
+        transaction_credit_address,
+        transaction_credit_chain
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
@@ -70,14 +104,20 @@ pub async fn get_transactions(pool: &SqlitePool) -> Result<Vec<Transaction>, Err
             let transaction_amount = row.transaction_amount as u64;
             let transaction_fees = row.transaction_fees as u64;
             let memo = row.memo.clone();
-            let transaction_originator_address =
-                TransactionAddress::Bitcoin(row.transaction_originator_address.clone());
+            let transaction_originator_address = TransactionAddress::new(
+                row.transaction_originator_chain.parse()?,
+                row.transaction_originator_address.clone(),
+            );
 
-            let transaction_debit_address =
-                TransactionAddress::Bitcoin(row.transaction_debit_address.clone());
+            let transaction_debit_address = TransactionAddress::new(
+                row.transaction_debit_chain.parse()?,
+                row.transaction_debit_address.clone(),
+            );
 
-            let transaction_credit_address =
-                TransactionAddress::Bitcoin(row.transaction_credit_address.clone());
+            let transaction_credit_address = TransactionAddress::new(
+                row.transaction_credit_chain.parse()?,
+                row.transaction_credit_address.clone(),
+            );
 
             Ok(Transaction {
                 txid,
@@ -95,6 +135,200 @@ pub async fn get_transactions(pool: &SqlitePool) -> Result<Vec<Transaction>, Err
         .collect()
 }
 
+/// A value bound to a transaction listing query
+enum Param<'a> {
+    Text(&'a str),
+    Integer(i64),
+}
+
+/// Bind the given values, in order, to a query.
+fn bind_params<'q>(
+    mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    params: &[Param<'q>],
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    for param in params {
+        query = match param {
+            Param::Text(text) => query.bind(*text),
+            Param::Integer(integer) => query.bind(*integer),
+        };
+    }
+    query
+}
+
+/// Get a page of transactions and their votes matching the filters of a query.
+///
+/// Transactions are sorted by the query's sort field and then by transaction ID, so the ID of
+/// the last transaction of a page is a stable cursor for the next.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * query: &TransactionQuery - The filters, sorting and pagination to apply.
+///
+/// # Returns
+/// * Result<TransactionPage, Error>: The page of transactions found in the database.
+pub async fn get_transaction_page(
+    pool: &SqlitePool,
+    query: &TransactionQuery,
+) -> Result<TransactionPage, Error> {
+    let kind = query.kind.as_ref().map(|kind| kind.to_string());
+    let status = query.status.map(|status| status.to_string());
+    let mut conditions = vec![];
+    let mut params = vec![];
+    if let Some(kind) = &kind {
+        conditions.push("t.transaction_kind = ?".to_string());
+        params.push(Param::Text(kind));
+    }
+    if let Some(status) = &status {
+        conditions.push("v.vote_status = ?".to_string());
+        params.push(Param::Text(status));
+    }
+    if let Some(min_amount) = query.min_amount {
+        conditions.push("t.transaction_amount >= ?".to_string());
+        params.push(Param::Integer(min_amount as i64));
+    }
+    if let Some(max_amount) = query.max_amount {
+        conditions.push("t.transaction_amount <= ?".to_string());
+        params.push(Param::Integer(max_amount as i64));
+    }
+    if let Some(address) = &query.address {
+        conditions.push(
+            "(t.transaction_originator_address = ? OR t.transaction_debit_address = ? OR t.transaction_credit_address = ?)"
+                .to_string(),
+        );
+        params.extend([
+            Param::Text(address),
+            Param::Text(address),
+            Param::Text(address),
+        ]);
+    }
+    if let Some(min_block_height) = query.min_block_height {
+        conditions.push("t.transaction_block_height >= ?".to_string());
+        params.push(Param::Integer(min_block_height as i64));
+    }
+    if let Some(max_block_height) = query.max_block_height {
+        conditions.push("t.transaction_block_height <= ?".to_string());
+        params.push(Param::Integer(max_block_height as i64));
+    }
+
+    let count_sql = format!(
+        "SELECT COUNT(*) AS total FROM transactions t JOIN votes v ON v.txid = t.txid {}",
+        where_clause(&conditions)
+    );
+    let total: i64 = bind_params(sqlx::query(&count_sql), &params)
+        .fetch_one(pool)
+        .await?
+        .try_get("total")?;
+
+    let sort = match query.sort.unwrap_or_default() {
+        TransactionSort::Txid => "t.txid",
+        TransactionSort::BlockHeight => "IFNULL(t.transaction_block_height, -1)",
+        TransactionSort::DeadlineBlockHeight => "t.transaction_deadline_block_height",
+        TransactionSort::Amount => "t.transaction_amount",
+        TransactionSort::Fees => "t.transaction_fees",
+    };
+    let (direction, comparison) = match query.order.unwrap_or_default() {
+        SortOrder::Asc => ("ASC", ">"),
+        SortOrder::Desc => ("DESC", "<"),
+    };
+    if let Some(cursor) = &query.cursor {
+        // Continue after the cursor transaction's position in the sort order
+        conditions.push(format!(
+            "({sort}, t.txid) {comparison} ((SELECT {sort} FROM transactions t WHERE t.txid = ?), ?)"
+        ));
+        params.extend([Param::Text(cursor), Param::Text(cursor)]);
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRANSACTION_LIMIT)
+        .min(MAX_TRANSACTION_LIMIT);
+    // Fetch one extra transaction to learn whether there is a next page
+    params.push(Param::Integer(limit as i64 + 1));
+    let page_sql = format!(
+        r#"SELECT t.*, v.vote_status, v.vote_choice, v.vote_mechanism, v.target_consensus, v.current_consensus
+    FROM transactions t JOIN votes v ON v.txid = t.txid {}
+    ORDER BY {sort} {direction}, t.txid {direction} LIMIT ?"#,
+        where_clause(&conditions)
+    );
+    let mut transactions = bind_params(sqlx::query(&page_sql), &params)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(transaction_response)
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let next_cursor = if transactions.len() as u64 > limit {
+        transactions.truncate(limit as usize);
+        transactions
+            .last()
+            .map(|transaction| transaction.transaction.txid.clone())
+    } else {
+        None
+    };
+    Ok(TransactionPage {
+        transactions,
+        total: total as u64,
+        next_cursor,
+    })
+}
+
+/// Join the conditions of a transaction listing query into a WHERE clause.
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// Build a transaction response from a row of transactions joined with their votes.
+fn transaction_response(row: &SqliteRow) -> Result<TransactionResponse, Error> {
+    let transaction_kind: String = row.try_get("transaction_kind")?;
+    let transaction_block_height: Option<i64> = row.try_get("transaction_block_height")?;
+    let transaction_deadline_block_height: i64 =
+        row.try_get("transaction_deadline_block_height")?;
+    let transaction_amount: i64 = row.try_get("transaction_amount")?;
+    let transaction_fees: i64 = row.try_get("transaction_fees")?;
+    let vote_status: String = row.try_get("vote_status")?;
+    let vote_choice: Option<String> = row.try_get("vote_choice")?;
+    let vote_mechanism: String = row.try_get("vote_mechanism")?;
+    let target_consensus: i64 = row.try_get("target_consensus")?;
+    let current_consensus: i64 = row.try_get("current_consensus")?;
+    let originator_chain: String = row.try_get("transaction_originator_chain")?;
+    let debit_chain: String = row.try_get("transaction_debit_chain")?;
+    let credit_chain: String = row.try_get("transaction_credit_chain")?;
+
+    Ok(TransactionResponse {
+        transaction: Transaction {
+            txid: row.try_get("txid")?,
+            transaction_kind: transaction_kind.parse()?,
+            transaction_block_height: transaction_block_height.map(|height| height as u64),
+            transaction_deadline_block_height: transaction_deadline_block_height as u64,
+            transaction_amount: transaction_amount as u64,
+            transaction_fees: transaction_fees as u64,
+            memo: row.try_get("memo")?,
+            transaction_originator_address: TransactionAddress::new(
+                originator_chain.parse()?,
+                row.try_get("transaction_originator_address")?,
+            ),
+            transaction_debit_address: TransactionAddress::new(
+                debit_chain.parse()?,
+                row.try_get("transaction_debit_address")?,
+            ),
+            transaction_credit_address: TransactionAddress::new(
+                credit_chain.parse()?,
+                row.try_get("transaction_credit_address")?,
+            ),
+        },
+        vote_tally: VoteTally {
+            vote_status: vote_status.parse()?,
+            target_consensus: target_consensus as u64,
+            current_consensus: current_consensus as u64,
+        },
+        vote_choice: vote_choice.map(|choice| choice.parse()).transpose()?,
+        vote_mechanism: vote_mechanism.parse()?,
+    })
+}
+
 /// Get a transaction with a specific transaction ID from the database.
 ///
 /// # Params
@@ -114,13 +348,20 @@ pub async fn get_transaction_by_id(txid: &str, pool: &SqlitePool) -> Result<Tran
     let transaction_amount = row.transaction_amount as u64;
     let transaction_fees = row.transaction_fees as u64;
     let memo = row.memo.clone();
-    let transaction_originator_address =
-        TransactionAddress::Bitcoin(row.transaction_originator_address.clone());
+    let transaction_originator_address = TransactionAddress::new(
+        row.transaction_originator_chain.parse()?,
+        row.transaction_originator_address.clone(),
+    );
 
-    let transaction_debit_address =
-        TransactionAddress::Bitcoin(row.transaction_debit_address.clone());
+    let transaction_debit_address = TransactionAddress::new(
+        row.transaction_debit_chain.parse()?,
+        row.transaction_debit_address.clone(),
+    );
 
-    let transaction_credit_address = TransactionAddress::Bitcoin(row.transaction_credit_address);
+    let transaction_credit_address = TransactionAddress::new(
+        row.transaction_credit_chain.parse()?,
+        row.transaction_credit_address,
+    );
 
     Ok(Transaction {
         txid,
@@ -135,3 +376,108 @@ pub async fn get_transaction_by_id(txid: &str, pool: &SqlitePool) -> Result<Tran
         transaction_credit_address,
     })
 }
+
+/// Get the highest Bitcoin block height of any transaction in the database.
+///
+/// # Params
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<Option<u64>>: The highest block height seen, or None if no mined transactions are known.
+pub async fn get_max_block_height(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Option<u64>, Error> {
+    let row = sqlx::query!(
+        "SELECT transaction_block_height FROM transactions WHERE transaction_block_height IS NOT NULL ORDER BY transaction_block_height DESC LIMIT 1"
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(row
+        .and_then(|row| row.transaction_block_height)
+        .map(|height| height as u64))
+}
+
+/// Get the last Bitcoin block height whose sBTC operations were ingested.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+///
+/// # Returns
+/// * Result<Option<u64>>: The last processed block height, or None if nothing was ingested yet.
+pub async fn get_last_processed_block_height(pool: &SqlitePool) -> Result<Option<u64>, Error> {
+    let row = sqlx::query!("SELECT last_processed_block_height FROM ingestion_state WHERE id = 1")
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|row| row.last_processed_block_height as u64))
+}
+
+/// Set the last Bitcoin block height whose sBTC operations were ingested.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * block_height: u64 - The last processed block height.
+///
+/// # Returns
+/// * Result<(), Error>: The result of the database operation.
+pub async fn set_last_processed_block_height(
+    pool: &SqlitePool,
+    block_height: u64,
+) -> Result<(), Error> {
+    let block_height = block_height as i64;
+    sqlx::query!(
+        "REPLACE INTO ingestion_state (id, last_processed_block_height) VALUES (1, ?1)",
+        block_height
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Get the last Bitcoin block height reported by the chain.
+///
+/// # Params
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<Option<u64>>: The burn block height, or None if it was never tracked.
+pub async fn get_burn_block_height(
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Option<u64>, Error> {
+    let row = sqlx::query!("SELECT burn_block_height FROM chain_state WHERE id = 1")
+        .fetch_optional(executor)
+        .await?;
+    Ok(row.map(|row| row.burn_block_height as u64))
+}
+
+/// Set the last Bitcoin block height reported by the chain.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * block_height: u64 - The burn block height.
+///
+/// # Returns
+/// * Result<(), Error>: The result of the database operation.
+pub async fn set_burn_block_height(pool: &SqlitePool, block_height: u64) -> Result<(), Error> {
+    let block_height = block_height as i64;
+    sqlx::query!(
+        "REPLACE INTO chain_state (id, burn_block_height) VALUES (1, ?1)",
+        block_height
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Get the current Bitcoin block height: the tracked burn block height, or the highest block
+/// height of any transaction if that is further along.
+///
+/// # Params
+/// * conn: SqliteConnection - The SQLite connection or transaction to run the queries on.
+///
+/// # Returns
+/// * Result<Option<u64>>: The current block height, or None if no block height is known.
+pub async fn get_current_block_height(conn: &mut SqliteConnection) -> Result<Option<u64>, Error> {
+    let burn_block_height = get_burn_block_height(&mut *conn).await?;
+    let max_block_height = get_max_block_height(conn).await?;
+    Ok(burn_block_height.max(max_block_height))
+}
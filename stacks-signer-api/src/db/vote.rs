@@ -1,19 +1,22 @@
-use sqlx::SqlitePool;
+use sqlx::{Executor, Sqlite};
 
 use crate::{
     db::Error,
-    vote::{Vote, VoteTally},
+    vote::{DelegatorVote, Vote, VoteStatus, VoteTally},
 };
 
 /// Add a given vote to the database.
 ///
 /// # Params
-/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
 /// * vote: Vote - The vote object to add to the database.
 ///
 /// # Returns
 /// * Result<(), Error>: The result of the database operation.
-pub async fn add_vote(vote: &Vote, pool: &SqlitePool) -> Result<(), Error> {
+pub async fn add_vote(
+    vote: &Vote,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<(), Error> {
     let txid = vote.txid.clone();
     let vote_status = vote.vote_tally.vote_status.to_string();
     let vote_choice = vote.vote_choice.map(|choice| choice.to_string());
@@ -31,7 +34,7 @@ pub async fn add_vote(vote: &Vote, pool: &SqlitePool) -> Result<(), Error> {
         target_consenus,
         current_consensus
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
@@ -40,13 +43,16 @@ pub async fn add_vote(vote: &Vote, pool: &SqlitePool) -> Result<(), Error> {
 ///
 /// # Params
 /// * txid: String - The transaction ID to search for.
-/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
 ///
 /// # Returns
 /// * Result<Vote>: The vote found in the database.
-pub async fn get_vote_by_id(txid: &str, pool: &SqlitePool) -> Result<Vote, Error> {
+pub async fn get_vote_by_id(
+    txid: &str,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Vote, Error> {
     let row = sqlx::query!("SELECT * FROM votes WHERE txid = ?", txid)
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await?;
     let txid = row.txid.clone();
 
@@ -70,3 +76,318 @@ pub async fn get_vote_by_id(txid: &str, pool: &SqlitePool) -> Result<Vote, Error
     };
     Ok(vote)
 }
+
+/// Add a given delegator vote to the database.
+///
+/// # Params
+/// * vote: DelegatorVote - The delegator vote object to add to the database.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<(), Error>: The result of the database operation. Fails if the delegator already voted.
+pub async fn add_delegator_vote(
+    vote: &DelegatorVote,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<(), Error> {
+    let txid = vote.txid.clone();
+    let signing_delegator = vote.signing_delegator.clone();
+    let vote_choice = vote.vote_choice.to_string();
+    let vote_weight = vote.vote_weight as i64;
+    sqlx::query!(
+        r#"INSERT INTO delegator_votes (
+        txid, signing_delegator, vote_choice, vote_weight
+    ) VALUES (?, ?, ?, ?);"#,
+        txid,
+        signing_delegator,
+        vote_choice,
+        vote_weight
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Get all delegator votes cast on a specific transaction ID from the database.
+///
+/// # Params
+/// * txid: String - The transaction ID to search for.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<Vec<DelegatorVote>>: The delegator votes found in the database.
+pub async fn get_delegator_votes(
+    txid: &str,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Vec<DelegatorVote>, Error> {
+    sqlx::query!("SELECT * FROM delegator_votes WHERE txid = ?", txid)
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(DelegatorVote {
+                txid: row.txid.clone(),
+                signing_delegator: row.signing_delegator.clone(),
+                vote_choice: row.vote_choice.parse()?,
+                vote_weight: row.vote_weight as u64,
+            })
+        })
+        .collect()
+}
+
+/// Close every pending vote whose transaction deadline is below the given block height.
+///
+/// # Params
+/// * block_height: u64 - The current Bitcoin block height.
+/// * vote_status: VoteStatus - The status to close the votes with.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<u64, Error>: The number of votes that were closed.
+pub async fn close_expired_votes(
+    block_height: u64,
+    vote_status: VoteStatus,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<u64, Error> {
+    let block_height = block_height as i64;
+    let pending = VoteStatus::Pending.to_string();
+    let vote_status = vote_status.to_string();
+    let result = sqlx::query!(
+        r#"UPDATE votes SET vote_status = ?1 WHERE vote_status = ?2 AND txid IN (
+        SELECT txid FROM transactions WHERE transaction_deadline_block_height < ?3
+    );"#,
+        vote_status,
+        pending,
+        block_height
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Get the nonce of the last vote accepted from a delegator.
+///
+/// # Params
+/// * public_key: String - The public key of the delegator.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<Option<u64>>: The last nonce used, or None if the delegator never voted.
+pub async fn get_delegator_nonce(
+    public_key: &str,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<Option<u64>, Error> {
+    let row = sqlx::query!(
+        "SELECT nonce FROM delegator_nonces WHERE public_key = ?",
+        public_key
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(row.map(|row| row.nonce as u64))
+}
+
+/// Advance the nonce of the last vote accepted from a delegator, unless the given nonce was
+/// already used. Checking and updating the nonce in one statement keeps concurrent votes from
+/// reusing it.
+///
+/// # Params
+/// * public_key: String - The public key of the delegator.
+/// * nonce: u64 - The nonce of the accepted vote.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<bool, Error>: Whether the nonce was advanced, false if it is not above the last one.
+pub async fn advance_delegator_nonce(
+    public_key: &str,
+    nonce: u64,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<bool, Error> {
+    let nonce = nonce as i64;
+    let result = sqlx::query!(
+        "INSERT INTO delegator_nonces (public_key, nonce) VALUES (?1, ?2)
+            ON CONFLICT(public_key) DO UPDATE SET nonce = excluded.nonce
+            WHERE excluded.nonce > delegator_nonces.nonce",
+        public_key,
+        nonce
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record that a transaction was automatically approved.
+///
+/// # Params
+/// * txid: String - The ID of the approved transaction.
+/// * transaction_amount: u64 - The amount of sats in the approved transaction.
+/// * approved_at: u64 - The unix timestamp of the approval.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<(), Error>: The result of the database operation.
+pub async fn add_auto_approval(
+    txid: &str,
+    transaction_amount: u64,
+    approved_at: u64,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<(), Error> {
+    let transaction_amount = transaction_amount as i64;
+    let approved_at = approved_at as i64;
+    sqlx::query!(
+        "REPLACE INTO auto_approvals (txid, transaction_amount, approved_at) VALUES (?1, ?2, ?3)",
+        txid,
+        transaction_amount,
+        approved_at
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Get the total amount of transactions automatically approved since a given time.
+///
+/// # Params
+/// * since: u64 - The unix timestamp to count approvals from.
+/// * executor: impl Executor - The SQLite connection pool or transaction to run the query on.
+///
+/// # Returns
+/// * Result<u64, Error>: The total amount of sats approved.
+pub async fn get_auto_approved_amount(
+    since: u64,
+    executor: impl Executor<'_, Database = Sqlite>,
+) -> Result<u64, Error> {
+    let since = since as i64;
+    let amount = sqlx::query!(
+        "SELECT transaction_amount FROM auto_approvals WHERE approved_at >= ?",
+        since
+    )
+    .fetch_all(executor)
+    .await?
+    .iter()
+    .map(|row| row.transaction_amount as u64)
+    .sum();
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{init_pool, transaction::add_transaction},
+        transaction::Transaction,
+        vote::VoteChoice,
+    };
+    use sqlx::SqlitePool;
+
+    async fn init_db() -> SqlitePool {
+        init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.")
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_delegator_votes() {
+        let pool = init_db().await;
+        let tx = Transaction::default();
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+
+        let vote = DelegatorVote {
+            txid: tx.txid.clone(),
+            signing_delegator: "delegator".to_string(),
+            vote_choice: VoteChoice::Approve,
+            vote_weight: 2,
+        };
+        add_delegator_vote(&vote, &pool)
+            .await
+            .expect("failed to add delegator vote");
+        // A delegator may only vote once
+        assert!(add_delegator_vote(&vote, &pool).await.is_err());
+
+        let votes = get_delegator_votes(&tx.txid, &pool)
+            .await
+            .expect("failed to get delegator votes");
+        assert_eq!(votes, vec![vote]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_delegator_nonce() {
+        let pool = init_db().await;
+        assert_eq!(get_delegator_nonce("delegator", &pool).await.unwrap(), None);
+        assert!(advance_delegator_nonce("delegator", 7, &pool)
+            .await
+            .unwrap());
+        assert_eq!(
+            get_delegator_nonce("delegator", &pool).await.unwrap(),
+            Some(7)
+        );
+        // Reused and lower nonces are refused
+        assert!(!advance_delegator_nonce("delegator", 7, &pool)
+            .await
+            .unwrap());
+        assert!(!advance_delegator_nonce("delegator", 3, &pool)
+            .await
+            .unwrap());
+        assert!(advance_delegator_nonce("delegator", 8, &pool)
+            .await
+            .unwrap());
+        assert_eq!(
+            get_delegator_nonce("delegator", &pool).await.unwrap(),
+            Some(8)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_auto_approved_amount() {
+        let pool = init_db().await;
+        for (i, approved_at) in [100, 200, 300].into_iter().enumerate() {
+            let tx = Transaction {
+                txid: i.to_string().repeat(64),
+                ..Default::default()
+            };
+            add_transaction(&pool, &tx)
+                .await
+                .expect("failed to add transaction");
+            add_auto_approval(&tx.txid, 10, approved_at, &pool)
+                .await
+                .expect("failed to add auto approval");
+        }
+        assert_eq!(get_auto_approved_amount(0, &pool).await.unwrap(), 30);
+        assert_eq!(get_auto_approved_amount(200, &pool).await.unwrap(), 20);
+        assert_eq!(get_auto_approved_amount(301, &pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_close_expired_votes() {
+        let pool = init_db().await;
+        let tx = Transaction {
+            transaction_deadline_block_height: 10,
+            ..Default::default()
+        };
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+
+        assert_eq!(
+            close_expired_votes(10, VoteStatus::NoConsensus, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+        let vote = get_vote_by_id(&tx.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Pending);
+
+        assert_eq!(
+            close_expired_votes(11, VoteStatus::NoConsensus, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        let vote = get_vote_by_id(&tx.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::NoConsensus);
+    }
+}
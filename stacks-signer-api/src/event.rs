@@ -0,0 +1,183 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use parse_display::{Display, FromStr};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::broadcast;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::{
+    db::event::{get_events_after, get_last_event_id, prune_events},
+    vote::{VoteChoice, VoteStatus},
+};
+
+/// How often the database is checked for new events
+pub const EVENT_POLLING_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The number of events kept for subscribers that fall behind
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// How long events are kept in the database for clients to replay
+pub const EVENT_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How often events older than the retention period are deleted
+const EVENT_PRUNING_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, ToSchema, Display, FromStr)]
+#[serde(rename_all = "snake_case")]
+#[display(style = "snake_case")]
+/// The kind of change an event reports
+pub enum EventKind {
+    /// A transaction was added to the database
+    TransactionIngested,
+    /// A voter cast a vote on a transaction
+    VoteCast,
+    /// The vote status of a transaction changed
+    VoteStatusChanged,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, ToSchema)]
+/// A change to the transactions or votes of the signer
+pub struct Event {
+    /// The increasing event ID
+    pub id: u64,
+    /// The kind of change
+    pub event_kind: EventKind,
+    /// The hexadecimal ID of the changed transaction
+    pub txid: String,
+    /// The public key of the voter, for cast votes
+    pub signing_delegator: Option<String>,
+    /// The choice of the voter, for cast votes
+    pub vote_choice: Option<VoteChoice>,
+    /// The new vote status, for vote status changes
+    pub vote_status: Option<VoteStatus>,
+    /// The time the change was recorded, in seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+/// Publishes the events recorded in the database to its subscribers.
+#[derive(Debug, Clone)]
+pub struct EventBroadcaster {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBroadcaster {
+    /// Start publishing the events recorded in the database from now on.
+    ///
+    /// # Params
+    /// * pool: SqlitePool - The SQLite database connection pool the events are recorded in.
+    /// * interval: Duration - How often to check the database for new events.
+    ///
+    /// # Returns
+    /// * EventBroadcaster: The broadcaster to subscribe to.
+    pub fn spawn(pool: SqlitePool, interval: Duration) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let broadcaster = Self { sender };
+        let publisher = broadcaster.clone();
+        tokio::spawn(async move { publisher.publish(pool, interval).await });
+        broadcaster
+    }
+
+    /// Subscribe to the events published after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    async fn publish(self, pool: SqlitePool, interval: Duration) {
+        let mut last_id = match get_last_event_id(&pool).await {
+            Ok(last_id) => last_id.unwrap_or(0),
+            Err(e) => {
+                error!("Failed to read events: {}", e);
+                return;
+            }
+        };
+        let mut next_pruning = Instant::now();
+        loop {
+            if Instant::now() >= next_pruning {
+                next_pruning += EVENT_PRUNING_INTERVAL;
+                Self::prune(&pool).await;
+            }
+            tokio::time::sleep(interval).await;
+            match get_events_after(last_id, &pool).await {
+                Ok(events) => {
+                    for event in events {
+                        last_id = event.id;
+                        // Sending only fails when nobody is subscribed
+                        let _ = self.sender.send(event);
+                    }
+                }
+                Err(e) => error!("Failed to read events: {}", e),
+            }
+        }
+    }
+
+    /// Delete the events older than the retention period.
+    async fn prune(pool: &SqlitePool) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let before = now.saturating_sub(EVENT_RETENTION).as_secs();
+        match prune_events(before, pool).await {
+            Ok(0) => {}
+            Ok(pruned) => info!("Pruned {} events", pruned),
+            Err(e) => error!("Failed to prune events: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{init_pool, transaction::add_transaction, vote::add_delegator_vote},
+        transaction::Transaction,
+        vote::DelegatorVote,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_broadcast_events() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let old = Transaction {
+            txid: "1".repeat(64),
+            ..Default::default()
+        };
+        add_transaction(&pool, &old)
+            .await
+            .expect("failed to add transaction");
+
+        let broadcaster = EventBroadcaster::spawn(pool.clone(), Duration::from_millis(10));
+        let mut receiver = broadcaster.subscribe();
+        // Let the broadcaster skip the events recorded before it started
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let tx = Transaction {
+            txid: "2".repeat(64),
+            ..Default::default()
+        };
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+        let vote = DelegatorVote {
+            txid: tx.txid.clone(),
+            signing_delegator: "delegator".to_string(),
+            vote_choice: VoteChoice::Approve,
+            vote_weight: 1,
+        };
+        add_delegator_vote(&vote, &pool)
+            .await
+            .expect("failed to add delegator vote");
+
+        let ingested = receiver.recv().await.unwrap();
+        assert_eq!(ingested.event_kind, EventKind::TransactionIngested);
+        assert_eq!(ingested.txid, tx.txid);
+        let cast = receiver.recv().await.unwrap();
+        assert_eq!(cast.event_kind, EventKind::VoteCast);
+        assert_eq!(cast.signing_delegator, Some(vote.signing_delegator));
+        assert_eq!(cast.vote_choice, Some(VoteChoice::Approve));
+        assert!(cast.id > ingested.id);
+    }
+}
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use stacks_coordinator::{
+    bitcoin_node::{Error as BitcoinNodeError, LocalhostBitcoinNode},
+    stacks_node::{client::NodeClient, Error as StacksNodeError, StacksNode},
+};
+use tracing::{debug, error, info};
+
+use crate::db::{
+    self, config::get_config, transaction::set_burn_block_height, vote::close_expired_votes,
+};
+
+/// The default number of seconds to wait between burn block height polls
+pub const DEFAULT_EXPIRY_POLLING_INTERVAL: u64 = 30;
+
+/// Errors that can occur while tracking the burn block height
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Database related error
+    #[error("Database Error: {0}")]
+    DatabaseError(#[from] db::Error),
+    /// Stacks node related error
+    #[error("Stacks Node Error: {0}")]
+    StacksNodeError(#[from] StacksNodeError),
+    /// Bitcoin node related error
+    #[error("Bitcoin Node Error: {0}")]
+    BitcoinNodeError(#[from] BitcoinNodeError),
+}
+
+/// A source of the current Bitcoin block height
+#[async_trait]
+pub trait BurnHeightSource: Send + Sync {
+    /// The current Bitcoin block height
+    async fn burn_block_height(&self) -> Result<u64, Error>;
+}
+
+#[async_trait]
+impl BurnHeightSource for NodeClient {
+    async fn burn_block_height(&self) -> Result<u64, Error> {
+        Ok(StacksNode::burn_block_height(self).await?)
+    }
+}
+
+#[async_trait]
+impl BurnHeightSource for LocalhostBitcoinNode {
+    async fn burn_block_height(&self) -> Result<u64, Error> {
+        Ok(self.get_block_count().await?)
+    }
+}
+
+/// Tracks the Bitcoin block height and closes the ballots whose deadline has passed
+pub struct ExpiryTracker<S> {
+    source: S,
+}
+
+impl<S: BurnHeightSource> ExpiryTracker<S> {
+    /// Create a new expiry tracker.
+    ///
+    /// # Params
+    /// * source: S - The source of the Bitcoin block height, typically a Bitcoin or Stacks node client.
+    pub fn new(source: S) -> Self {
+        Self { source }
+    }
+
+    /// Record the current Bitcoin block height and close the ballots that expired by then,
+    /// with the outcome configured for expired ballots.
+    ///
+    /// # Returns
+    /// * Result<u64, Error>: The number of ballots closed.
+    pub async fn poll(&self, pool: &SqlitePool) -> Result<u64, Error> {
+        let block_height = self.source.burn_block_height().await?;
+        set_burn_block_height(pool, block_height).await?;
+        debug!("Burn block height is {}", block_height);
+        let config = get_config(pool).await?;
+        Ok(close_expired_votes(block_height, config.expired_vote_status(), pool).await?)
+    }
+
+    /// Poll the source forever, waiting the given interval between polls.
+    pub async fn run(self, pool: SqlitePool, polling_interval: Duration) {
+        loop {
+            match self.poll(&pool).await {
+                Ok(0) => {}
+                Ok(closed) => info!("Closed {} expired ballots", closed),
+                Err(e) => error!("Failed to close expired ballots: {}", e),
+            }
+            tokio::time::sleep(polling_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        db::{
+            config::update_config,
+            init_pool,
+            transaction::{add_transaction, get_burn_block_height},
+            vote::get_vote_by_id,
+        },
+        transaction::Transaction,
+        vote::{VoteChoice, VoteStatus},
+    };
+
+    const TEST_SECRET_KEY: &str =
+        "26F85CE8B2C635AD92F6148E4443FE415F512F3F29F44AB0E2CBDA819295BBD5";
+
+    struct TestSource(u64);
+
+    #[async_trait]
+    impl BurnHeightSource for TestSource {
+        async fn burn_block_height(&self) -> Result<u64, Error> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_poll() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let mut config = Config::from_secret_key(TEST_SECRET_KEY).expect("Failed to create config");
+        config.expired_vote_choice = Some(VoteChoice::Reject);
+        update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to add configuration to database.");
+        let expired = Transaction {
+            txid: "1".repeat(64),
+            transaction_deadline_block_height: 10,
+            ..Default::default()
+        };
+        let pending = Transaction {
+            txid: "2".repeat(64),
+            transaction_deadline_block_height: 11,
+            ..Default::default()
+        };
+        for tx in [&expired, &pending] {
+            add_transaction(&pool, tx)
+                .await
+                .expect("failed to add transaction");
+        }
+
+        assert_eq!(
+            ExpiryTracker::new(TestSource(11))
+                .poll(&pool)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(get_burn_block_height(&pool).await.unwrap(), Some(11));
+        let vote = get_vote_by_id(&expired.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Rejected);
+        let vote = get_vote_by_id(&pending.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Pending);
+    }
+}
@@ -0,0 +1,487 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bitcoin::{
+    util::address::{Payload, WitnessVersion},
+    Address, Network,
+};
+use blockstack_lib::{
+    address::{C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG},
+    chainstate::stacks::address::{PoxAddress, PoxAddressType20, PoxAddressType32},
+};
+use sqlx::SqlitePool;
+use stacks_coordinator::stacks_node::{
+    Error as StacksNodeError, PegInOp, PegOutRequestOp, StacksNode,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    db::{
+        self,
+        transaction::{get_last_processed_block_height, set_last_processed_block_height},
+    },
+    policy::ingest_transaction,
+    transaction::{Transaction, TransactionAddress, TransactionKind},
+};
+
+/// The default number of Bitcoin blocks signers have to vote on an ingested operation
+pub const DEFAULT_VOTING_WINDOW: u64 = 144;
+
+/// Errors that can occur while ingesting sBTC operations
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Database related error
+    #[error("Database Error: {0}")]
+    DatabaseError(#[from] db::Error),
+    /// Stacks node related error
+    #[error("Stacks Node Error: {0}")]
+    StacksNodeError(#[from] StacksNodeError),
+}
+
+/// A source of the sBTC operations mined in each Bitcoin block
+#[async_trait]
+pub trait SbtcOpSource: Send + Sync {
+    /// The current Bitcoin block height
+    async fn burn_block_height(&self) -> Result<u64, StacksNodeError>;
+    /// The peg-in operations mined in the given Bitcoin block
+    async fn get_peg_in_ops(&self, block_height: u64) -> Result<Vec<PegInOp>, StacksNodeError>;
+    /// The peg-out request operations mined in the given Bitcoin block
+    async fn get_peg_out_request_ops(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<PegOutRequestOp>, StacksNodeError>;
+}
+
+#[async_trait]
+impl<N: StacksNode> SbtcOpSource for N {
+    async fn burn_block_height(&self) -> Result<u64, StacksNodeError> {
+        StacksNode::burn_block_height(self).await
+    }
+
+    async fn get_peg_in_ops(&self, block_height: u64) -> Result<Vec<PegInOp>, StacksNodeError> {
+        StacksNode::get_peg_in_ops(self, block_height).await
+    }
+
+    async fn get_peg_out_request_ops(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<PegOutRequestOp>, StacksNodeError> {
+        StacksNode::get_peg_out_request_ops(self, block_height).await
+    }
+}
+
+/// Polls a Stacks node for sBTC operations and ingests them as transactions to vote on.
+///
+/// Only peg-ins and peg-out requests are ingested. Wallet hand-offs are not yet, as the Stacks
+/// node does not report them as burn operations.
+pub struct Ingester<S> {
+    source: S,
+    start_block_height: Option<u64>,
+    voting_window: u64,
+    address_version: u8,
+}
+
+impl<S: SbtcOpSource> Ingester<S> {
+    /// Create a new ingester.
+    ///
+    /// # Params
+    /// * source: S - The source of sBTC operations, typically a Stacks node client.
+    /// * start_block_height: Option<u64> - The block height to start from if nothing was ingested yet.
+    ///   Defaults to the current block height.
+    /// * voting_window: u64 - The number of blocks after an operation is mined until its vote is due.
+    /// * mainnet: bool - Whether the operations are mined on mainnet, which sets the version of the
+    ///   Stacks addresses recovered from peg-out requests.
+    pub fn new(
+        source: S,
+        start_block_height: Option<u64>,
+        voting_window: u64,
+        mainnet: bool,
+    ) -> Self {
+        let address_version = if mainnet {
+            C32_ADDRESS_VERSION_MAINNET_SINGLESIG
+        } else {
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG
+        };
+        Self {
+            source,
+            start_block_height,
+            voting_window,
+            address_version,
+        }
+    }
+
+    /// Ingest the operations of every block mined since the last poll.
+    ///
+    /// # Returns
+    /// * Result<usize, Error>: The number of transactions ingested.
+    pub async fn poll(&self, pool: &SqlitePool) -> Result<usize, Error> {
+        let target_block_height = self.source.burn_block_height().await?;
+        let start_block_height = match get_last_processed_block_height(pool).await? {
+            Some(block_height) => block_height + 1,
+            None => self.start_block_height.unwrap_or(target_block_height),
+        };
+        let mut ingested = 0;
+        for block_height in start_block_height..=target_block_height {
+            let transactions = self.transactions(block_height).await?;
+            for transaction in &transactions {
+                ingest_transaction(pool, transaction).await?;
+            }
+            // Only mark the block processed once all of its transactions are stored
+            set_last_processed_block_height(pool, block_height).await?;
+            ingested += transactions.len();
+            debug!("Processed block height {}", block_height);
+        }
+        Ok(ingested)
+    }
+
+    /// Poll the source forever, waiting the given interval between polls.
+    pub async fn run(self, pool: SqlitePool, polling_interval: Duration) {
+        loop {
+            match self.poll(&pool).await {
+                Ok(0) => {}
+                Ok(ingested) => info!("Ingested {} sBTC operations", ingested),
+                Err(e) => error!("Failed to ingest sBTC operations: {}", e),
+            }
+            tokio::time::sleep(polling_interval).await;
+        }
+    }
+
+    async fn transactions(&self, block_height: u64) -> Result<Vec<Transaction>, Error> {
+        // TODO: ingest wallet hand-offs as TransactionKind::WalletHandoff once the Stacks node
+        // serves them from /v2/burn_ops like the other sBTC operations
+        let mut transactions = vec![];
+        match self.source.get_peg_in_ops(block_height).await {
+            Err(StacksNodeError::UnknownBlockHeight(height)) => {
+                debug!("Failed to find burn block height {}", height);
+            }
+            Err(e) => return Err(e.into()),
+            Ok(ops) => transactions.extend(
+                ops.iter()
+                    .map(|op| peg_in_transaction(op, self.voting_window)),
+            ),
+        }
+        match self.source.get_peg_out_request_ops(block_height).await {
+            Err(StacksNodeError::UnknownBlockHeight(height)) => {
+                debug!("Failed to find burn block height {}", height);
+            }
+            Err(e) => return Err(e.into()),
+            Ok(ops) => transactions.extend(ops.iter().filter_map(|op| {
+                let transaction =
+                    peg_out_request_transaction(op, self.voting_window, self.address_version);
+                if transaction.is_none() {
+                    warn!(
+                        "Skipping peg-out request {} with an unrecoverable signature",
+                        op.txid
+                    );
+                }
+                transaction
+            })),
+        }
+        Ok(transactions)
+    }
+}
+
+/// Map a peg-in operation into a deposit transaction to vote on.
+pub fn peg_in_transaction(op: &PegInOp, voting_window: u64) -> Transaction {
+    let recipient = TransactionAddress::Stacks(op.recipient.to_string());
+    Transaction {
+        txid: op.txid.to_hex(),
+        transaction_kind: TransactionKind::DepositReveal,
+        transaction_block_height: Some(op.block_height),
+        transaction_deadline_block_height: op.block_height + voting_window,
+        transaction_amount: op.amount,
+        transaction_fees: 0,
+        memo: op.memo.clone(),
+        transaction_originator_address: recipient.clone(),
+        transaction_debit_address: TransactionAddress::Bitcoin(pox_address(&op.peg_wallet_address)),
+        transaction_credit_address: recipient,
+    }
+}
+
+/// Map a peg-out request operation into a withdrawal transaction to vote on, originated by the
+/// Stacks address that signed the request.
+///
+/// # Params
+/// * op: &PegOutRequestOp - The peg-out request operation to map.
+/// * voting_window: u64 - The number of blocks after the operation is mined until its vote is due.
+/// * address_version: u8 - The version of the Stacks address recovered from the request signature.
+///
+/// # Returns
+/// * Option<Transaction>: The withdrawal transaction, or None if the sender cannot be recovered.
+pub fn peg_out_request_transaction(
+    op: &PegOutRequestOp,
+    voting_window: u64,
+    address_version: u8,
+) -> Option<Transaction> {
+    let sender = op.stx_address(address_version).ok()?;
+    let recipient = TransactionAddress::Bitcoin(pox_address(&op.recipient));
+    Some(Transaction {
+        txid: op.txid.to_hex(),
+        transaction_kind: TransactionKind::WithdrawalReveal,
+        transaction_block_height: Some(op.block_height),
+        transaction_deadline_block_height: op.block_height + voting_window,
+        transaction_amount: op.amount,
+        transaction_fees: op.fulfillment_fee,
+        memo: op.memo.clone(),
+        transaction_originator_address: TransactionAddress::Stacks(sender.to_string()),
+        transaction_debit_address: TransactionAddress::Bitcoin(pox_address(&op.peg_wallet_address)),
+        transaction_credit_address: recipient,
+    })
+}
+
+/// Render a Bitcoin address: base58 for legacy scripts and bech32 or bech32m for segwit ones.
+fn pox_address(address: &PoxAddress) -> String {
+    let (mainnet, version, program) = match address {
+        PoxAddress::Standard(..) => {
+            return address
+                .clone()
+                .to_b58()
+                .unwrap_or_else(|| address.to_db_string())
+        }
+        PoxAddress::Addr20(mainnet, PoxAddressType20::P2WPKH, bytes) => {
+            (*mainnet, WitnessVersion::V0, bytes.to_vec())
+        }
+        PoxAddress::Addr32(mainnet, PoxAddressType32::P2WSH, bytes) => {
+            (*mainnet, WitnessVersion::V0, bytes.to_vec())
+        }
+        PoxAddress::Addr32(mainnet, PoxAddressType32::P2TR, bytes) => {
+            (*mainnet, WitnessVersion::V1, bytes.to_vec())
+        }
+    };
+    Address {
+        payload: Payload::WitnessProgram { version, program },
+        network: if mainnet {
+            Network::Bitcoin
+        } else {
+            Network::Testnet
+        },
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, db::init_pool, vote::VoteStatus};
+    use blockstack_lib::{
+        burnchains::{PrivateKey, Txid},
+        chainstate::stacks::address::AddressHashMode,
+        types::chainstate::{
+            BurnchainHeaderHash, StacksAddress, StacksPrivateKey, StacksPublicKey,
+        },
+        util::{
+            hash::{Hash160, Sha256Sum},
+            secp256k1::MessageSignature,
+        },
+    };
+    use std::sync::Mutex;
+
+    const TEST_SECRET_KEY: &str =
+        "26F85CE8B2C635AD92F6148E4443FE415F512F3F29F44AB0E2CBDA819295BBD5";
+    const SENDER_PRIVATE_KEY: &str =
+        "b244296d5907de9864c0b0d51f98a13c52890be0404e83f273144cd5b9960eed01";
+    const TAPROOT_PROGRAM: [u8; 32] = [
+        0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87, 0x0b,
+        0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16, 0xf8,
+        0x17, 0x98,
+    ];
+
+    /// A fake chain holding one peg-in and one peg-out request per block
+    struct FakeNode {
+        burn_block_height: Mutex<u64>,
+    }
+
+    #[async_trait]
+    impl SbtcOpSource for FakeNode {
+        async fn burn_block_height(&self) -> Result<u64, StacksNodeError> {
+            Ok(*self.burn_block_height.lock().unwrap())
+        }
+
+        async fn get_peg_in_ops(&self, block_height: u64) -> Result<Vec<PegInOp>, StacksNodeError> {
+            Ok(vec![peg_in_op(block_height)])
+        }
+
+        async fn get_peg_out_request_ops(
+            &self,
+            block_height: u64,
+        ) -> Result<Vec<PegOutRequestOp>, StacksNodeError> {
+            if block_height == 2 {
+                return Err(StacksNodeError::UnknownBlockHeight(block_height));
+            }
+            Ok(vec![peg_out_request_op(block_height)])
+        }
+    }
+
+    fn test_pox_address() -> PoxAddress {
+        PoxAddress::Standard(StacksAddress::new(0, Hash160([0; 20])), None)
+    }
+
+    fn peg_in_op(block_height: u64) -> PegInOp {
+        PegInOp {
+            recipient: StacksAddress::new(26, Hash160([0; 20])).into(),
+            peg_wallet_address: test_pox_address(),
+            amount: 1337,
+            memo: vec![1, 3, 3, 7],
+            txid: Txid([block_height as u8; 32]),
+            burn_header_hash: BurnchainHeaderHash([block_height as u8; 32]),
+            block_height,
+            vtxindex: 0,
+        }
+    }
+
+    fn sender_address() -> StacksAddress {
+        let private_key = StacksPrivateKey::from_hex(SENDER_PRIVATE_KEY).unwrap();
+        StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&private_key)],
+        )
+        .unwrap()
+    }
+
+    /// A peg-out request to a taproot address, signed by the sender key
+    fn peg_out_request_op(block_height: u64) -> PegOutRequestOp {
+        let amount: u64 = 1000;
+        // The sender signs the amount followed by the recipient's script pubkey
+        let mut msg = amount.to_be_bytes().to_vec();
+        msg.extend_from_slice(&[81, 32]); // OP_1 OP_PUSHBYTES_32
+        msg.extend_from_slice(&TAPROOT_PROGRAM);
+        let signature = StacksPrivateKey::from_hex(SENDER_PRIVATE_KEY)
+            .unwrap()
+            .sign(Sha256Sum::from_data(&msg).as_bytes())
+            .unwrap();
+        PegOutRequestOp {
+            recipient: PoxAddress::Addr32(false, PoxAddressType32::P2TR, TAPROOT_PROGRAM),
+            peg_wallet_address: test_pox_address(),
+            amount,
+            fulfillment_fee: 10,
+            signature,
+            memo: vec![],
+            txid: Txid([block_height as u8 + 100; 32]),
+            burn_header_hash: BurnchainHeaderHash([block_height as u8; 32]),
+            block_height,
+            vtxindex: 1,
+        }
+    }
+
+    #[test]
+    fn maps_operations() {
+        let op = peg_in_op(5);
+        let tx = peg_in_transaction(&op, 10);
+        assert_eq!(tx.txid, op.txid.to_hex());
+        assert_eq!(tx.transaction_kind, TransactionKind::DepositReveal);
+        assert_eq!(tx.transaction_block_height, Some(5));
+        assert_eq!(tx.transaction_deadline_block_height, 15);
+        assert_eq!(tx.transaction_amount, 1337);
+        assert_eq!(tx.memo, vec![1, 3, 3, 7]);
+        assert_eq!(
+            tx.transaction_credit_address,
+            TransactionAddress::Stacks(op.recipient.to_string())
+        );
+
+        let op = peg_out_request_op(5);
+        let tx = peg_out_request_transaction(&op, 10, C32_ADDRESS_VERSION_TESTNET_SINGLESIG)
+            .expect("Failed to recover the peg-out sender");
+        assert_eq!(tx.transaction_kind, TransactionKind::WithdrawalReveal);
+        assert_eq!(tx.transaction_amount, 1000);
+        assert_eq!(tx.transaction_fees, 10);
+        assert_eq!(
+            tx.transaction_originator_address,
+            TransactionAddress::Stacks(sender_address().to_string())
+        );
+        assert_eq!(
+            tx.transaction_credit_address,
+            TransactionAddress::Bitcoin(
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47zagq".to_string()
+            )
+        );
+
+        // Requests whose sender cannot be recovered are skipped
+        let op = PegOutRequestOp {
+            signature: MessageSignature([0; 65]),
+            ..op
+        };
+        assert_eq!(
+            peg_out_request_transaction(&op, 10, C32_ADDRESS_VERSION_TESTNET_SINGLESIG),
+            None
+        );
+    }
+
+    #[test]
+    fn renders_segwit_addresses() {
+        // BIP 173 and BIP 350 test vectors
+        let program = [
+            0x75, 0x1e, 0x76, 0xe8, 0x19, 0x91, 0x96, 0xd4, 0x54, 0x94, 0x1c, 0x45, 0xd1, 0xb3,
+            0xa3, 0x23, 0xf1, 0x43, 0x3b, 0xd6,
+        ];
+        assert_eq!(
+            pox_address(&PoxAddress::Addr20(true, PoxAddressType20::P2WPKH, program)),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            pox_address(&PoxAddress::Addr20(
+                false,
+                PoxAddressType20::P2WPKH,
+                program
+            )),
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        );
+        assert_eq!(
+            pox_address(&PoxAddress::Addr32(
+                true,
+                PoxAddressType32::P2TR,
+                TAPROOT_PROGRAM
+            )),
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_poll() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let mut config = Config::from_secret_key(TEST_SECRET_KEY).expect("Failed to create config");
+        config.auto_approve_max_amount = 1000;
+        db::config::update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to add configuration to database.");
+
+        let node = FakeNode {
+            burn_block_height: Mutex::new(3),
+        };
+        let ingester = Ingester::new(node, Some(1), DEFAULT_VOTING_WINDOW, false);
+        // Block 2 has no peg-out requests
+        assert_eq!(ingester.poll(&pool).await.unwrap(), 5);
+        assert_eq!(
+            get_last_processed_block_height(&pool).await.unwrap(),
+            Some(3)
+        );
+        let transactions = db::transaction::get_transactions(&pool).await.unwrap();
+        assert_eq!(transactions.len(), 5);
+
+        // The auto vote policy ran on ingestion: peg-outs are within the limit, peg-ins are not
+        let peg_in = db::vote::get_vote_by_id(&peg_in_op(1).txid.to_hex(), &pool)
+            .await
+            .unwrap();
+        assert_eq!(peg_in.vote_tally.vote_status, VoteStatus::Pending);
+        let peg_out = db::vote::get_vote_by_id(&peg_out_request_op(1).txid.to_hex(), &pool)
+            .await
+            .unwrap();
+        assert_eq!(peg_out.vote_tally.vote_status, VoteStatus::Approved);
+
+        // Nothing is ingested twice, and polling resumes after the last processed block
+        assert_eq!(ingester.poll(&pool).await.unwrap(), 0);
+        *ingester.source.burn_block_height.lock().unwrap() = 4;
+        assert_eq!(ingester.poll(&pool).await.unwrap(), 2);
+        assert_eq!(
+            db::transaction::get_transactions(&pool)
+                .await
+                .unwrap()
+                .len(),
+            7
+        );
+    }
+}
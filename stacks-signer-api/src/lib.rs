@@ -6,12 +6,22 @@ This library contains API calls to configure a signer to auto-sign transactions
 
 Usage documentation can be found in the [README](https://github.com/Trust-Machines/core-eng/stacks-signer-api/README.md).
 */
+/// API token authentication
+pub mod auth;
 /// Signer configuration
 pub mod config;
 /// Sqlite database
 pub mod db;
 /// Signer API Errors
 pub mod error;
+/// Transaction and vote events
+pub mod event;
+/// Vote expiry tracking
+pub mod expiry;
+/// sBTC operation ingestion
+pub mod ingest;
+/// Auto vote policy
+pub mod policy;
 /// Signer API Routes
 pub mod routes;
 /// Transactions
@@ -1,12 +1,21 @@
+use blockstack_lib::{types::chainstate::StacksAddress, vm::ContractName};
 use clap::Parser;
 use rand::Rng;
 use sqlx::SqlitePool;
+use stacks_coordinator::{bitcoin_node::LocalhostBitcoinNode, stacks_node::client::NodeClient};
 use stacks_signer_api::{
-    config::Config,
+    auth::{generate_token, hash_token, AuthMode, Role},
+    config::{Config, ConfigVersion},
     db::{self, transaction::add_transaction, vote::add_vote},
     error::{ErrorCode, ErrorResponse},
+    event::{Event, EventKind},
+    expiry::{ExpiryTracker, DEFAULT_EXPIRY_POLLING_INTERVAL},
+    ingest::{Ingester, DEFAULT_VOTING_WINDOW},
     routes::all_routes,
-    transaction::{Transaction, TransactionAddress, TransactionKind, TransactionResponse},
+    transaction::{
+        SortOrder, Transaction, TransactionAddress, TransactionKind, TransactionPage,
+        TransactionResponse, TransactionSort,
+    },
     vote::{Vote, VoteChoice, VoteMechanism, VoteRequest, VoteResponse, VoteStatus, VoteTally},
 };
 use std::{
@@ -14,9 +23,14 @@ use std::{
     net::{IpAddr, SocketAddr},
     path::Path,
     sync::Arc,
+    time::Duration,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use utoipa::OpenApi;
+use url::Url;
+use utoipa::{
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+    Modify, OpenApi,
+};
 use utoipa_swagger_ui::Config as SwaggerConfig;
 use warp::{
     http::Uri,
@@ -36,13 +50,19 @@ const TEST_SECRET_KEY: &str = "26F85CE8B2C635AD92F6148E4443FE415F512F3F29F44AB0E
         stacks_signer_api::routes::vote::vote,
         stacks_signer_api::routes::config::get_config,
         stacks_signer_api::routes::config::update_config,
+        stacks_signer_api::routes::config::get_config_history,
+        stacks_signer_api::routes::config::rollback_config,
+        stacks_signer_api::routes::events::get_events,
     ),
     components(
         schemas(
             Transaction,
             TransactionAddress,
             TransactionKind,
+            TransactionPage,
             TransactionResponse,
+            TransactionSort,
+            SortOrder,
             VoteResponse,
             VoteChoice,
             VoteMechanism,
@@ -51,13 +71,38 @@ const TEST_SECRET_KEY: &str = "26F85CE8B2C635AD92F6148E4443FE415F512F3F29F44AB0E
             VoteTally,
             ErrorCode,
             ErrorResponse,
-            Config
+            Config,
+            ConfigVersion,
+            Event,
+            EventKind
         ),
-        responses(TransactionResponse, VoteResponse, Config, ErrorResponse)
-    )
+        responses(
+            TransactionResponse,
+            TransactionPage,
+            VoteResponse,
+            Config,
+            ConfigVersion,
+            ErrorResponse
+        )
+    ),
+    modifiers(&BearerTokenAddon)
 )]
 struct ApiDoc;
 
+/// Document the bearer token authentication of the routes
+struct BearerTokenAddon;
+
+impl Modify for BearerTokenAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
 pub fn initiate_tracing_subscriber() {
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
@@ -72,6 +117,37 @@ enum Command {
     Swagger(SwaggerArgs),
     Simulator(SimulatorArgs),
     Run(RunArgs),
+    #[command(subcommand)]
+    Token(TokenCommand),
+}
+
+/// Manage the API tokens of the signer API.
+/// Every request is refused until a token is created, unless the server runs with --no-auth.
+#[derive(clap::Subcommand, Debug, Clone)]
+enum TokenCommand {
+    /// Create an API token and print it
+    Create(CreateTokenArgs),
+    /// Revoke all API tokens of a principal
+    Revoke(RevokeTokenArgs),
+    /// List the principals holding API tokens
+    List,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct CreateTokenArgs {
+    /// Name of the token holder, recorded in the audit log
+    #[arg(long)]
+    pub principal: String,
+    /// Role granted to the token
+    #[arg(long, value_enum)]
+    pub role: Role,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct RevokeTokenArgs {
+    /// Name of the token holder
+    #[arg(long)]
+    pub principal: String,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -82,6 +158,27 @@ struct ServerArgs {
     /// Address to run API server on
     #[arg(short, long, default_value = "0.0.0.0")]
     pub address: IpAddr,
+    /// Let every request through as an anonymous admin, without an API token.
+    /// Only meant for local development.
+    #[arg(long)]
+    pub no_auth: bool,
+}
+
+impl ServerArgs {
+    /// Whether requests must carry an API token, warning when they need not or no token exists.
+    async fn auth_mode(&self, pool: &SqlitePool) -> anyhow::Result<AuthMode> {
+        if self.no_auth {
+            println!(
+                "WARNING: API authentication is DISABLED. Anyone reaching {} has full admin access!",
+                self.address
+            );
+            return Ok(AuthMode::Disabled);
+        }
+        if !db::token::has_api_tokens(pool).await? {
+            println!("WARNING: No API token exists, so every request will be refused. Create one with `token create`.");
+        }
+        Ok(AuthMode::Required)
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -117,6 +214,44 @@ struct RunArgs {
     /// Required if env DATABASE_URL is not set or no config is found in the database
     #[arg(short, long)]
     pub config: Option<String>,
+    /// Options for ingesting sBTC operations from a Stacks node
+    #[command(flatten)]
+    pub ingest: IngestArgs,
+    /// Options for closing ballots once their deadline has passed
+    #[command(flatten)]
+    pub expiry: ExpiryArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct ExpiryArgs {
+    /// RPC URL of a Bitcoin node to track the block height from, i.e. http://<user>:<password>@<host>:<port>.
+    /// Defaults to the Stacks node given by --stacks-node-url.
+    #[arg(long)]
+    pub bitcoin_node_url: Option<Url>,
+    /// Seconds to wait between polls of the block height
+    #[arg(long, default_value_t = DEFAULT_EXPIRY_POLLING_INTERVAL)]
+    pub expiry_polling_interval: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct IngestArgs {
+    /// RPC URL of a Stacks node to ingest sBTC operations from.
+    /// Transactions are only ingested if provided.
+    #[arg(long, requires = "sbtc_contract")]
+    pub stacks_node_url: Option<Url>,
+    /// The sBTC contract identifier, i.e. <address>.<name>
+    #[arg(long)]
+    pub sbtc_contract: Option<String>,
+    /// Bitcoin block height to start ingesting from. Defaults to the current block height.
+    /// Ignored once operations have been ingested, as ingestion resumes from the last processed block.
+    #[arg(long)]
+    pub start_block_height: Option<u64>,
+    /// Number of Bitcoin blocks signers have to vote on an ingested operation
+    #[arg(long, default_value_t = DEFAULT_VOTING_WINDOW)]
+    pub voting_window: u64,
+    /// Seconds to wait between polls of the Stacks node
+    #[arg(long, default_value = "10")]
+    pub polling_interval: u64,
 }
 
 #[derive(Parser, Debug)]
@@ -136,9 +271,10 @@ async fn verify_config(pool: &SqlitePool, config_path: Option<String>) -> anyhow
     }
     // If we have a config path, try to load the config from the file
     if let Some(path) = config_path {
-        let config = Config::from_path(path)
+        let config = Config::from_path(&path)
             .map_err(|e| anyhow::anyhow!("Failed to load config from file: {}", e))?;
-        db::config::update_config(pool, &config).await?;
+        // Restarting with an unchanged file records no new config version
+        db::config::update_config(pool, &config, &format!("config file {}", path)).await?;
     } else {
         db::config::get_config(pool).await.map_err(|_| {
             anyhow::anyhow!(
@@ -158,10 +294,61 @@ async fn init_pool() -> anyhow::Result<SqlitePool> {
     Ok(pool)
 }
 
+/// Parse a contract identifier of the form <address>.<name>
+fn parse_contract(contract: &str) -> anyhow::Result<(ContractName, StacksAddress)> {
+    let (address, name) = contract
+        .split_once('.')
+        .ok_or(anyhow::anyhow!("Invalid sBTC contract: {}", contract))?;
+    let address = StacksAddress::from_string(address).ok_or(anyhow::anyhow!(
+        "Invalid sBTC contract address: {}",
+        address
+    ))?;
+    let name = ContractName::try_from(name.to_string())
+        .map_err(|e| anyhow::anyhow!("Invalid sBTC contract name: {}", e))?;
+    Ok((name, address))
+}
+
+/// Ingest sBTC operations from a Stacks node in the background if one was provided
+fn start_ingestion(pool: &SqlitePool, args: IngestArgs) -> anyhow::Result<()> {
+    let (Some(stacks_node_url), Some(sbtc_contract)) = (args.stacks_node_url, args.sbtc_contract)
+    else {
+        return Ok(());
+    };
+    let (contract_name, contract_address) = parse_contract(&sbtc_contract)?;
+    // Peg-out senders are recovered on the network the sBTC contract is deployed to
+    let mainnet = contract_address.is_mainnet();
+    let stacks_node = NodeClient::new(stacks_node_url, contract_name, contract_address);
+    let ingester = Ingester::new(
+        stacks_node,
+        args.start_block_height,
+        args.voting_window,
+        mainnet,
+    );
+    tokio::spawn(ingester.run(pool.clone(), Duration::from_secs(args.polling_interval)));
+    Ok(())
+}
+
+/// Close expired ballots in the background if a Bitcoin or Stacks node was provided
+fn start_expiry_tracking(pool: &SqlitePool, args: &RunArgs) -> anyhow::Result<()> {
+    let polling_interval = Duration::from_secs(args.expiry.expiry_polling_interval);
+    if let Some(bitcoin_node_url) = &args.expiry.bitcoin_node_url {
+        let tracker = ExpiryTracker::new(LocalhostBitcoinNode::new(bitcoin_node_url.clone()));
+        tokio::spawn(tracker.run(pool.clone(), polling_interval));
+    } else if let (Some(stacks_node_url), Some(sbtc_contract)) =
+        (&args.ingest.stacks_node_url, &args.ingest.sbtc_contract)
+    {
+        let (contract_name, contract_address) = parse_contract(sbtc_contract)?;
+        let stacks_node = NodeClient::new(stacks_node_url.clone(), contract_name, contract_address);
+        tokio::spawn(ExpiryTracker::new(stacks_node).run(pool.clone(), polling_interval));
+    }
+    Ok(())
+}
+
 /// Run the Signer API server on the provided port and address
 async fn run(pool: SqlitePool, server_args: ServerArgs) -> anyhow::Result<()> {
     // Create the routes
-    let routes = all_routes(pool);
+    let auth = server_args.auth_mode(&pool).await?;
+    let routes = all_routes(pool, auth);
 
     // Run the warp server
     let socket = SocketAddr::new(server_args.address, server_args.port);
@@ -170,6 +357,35 @@ async fn run(pool: SqlitePool, server_args: ServerArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Create, revoke or list the API tokens stored in the database
+async fn manage_tokens(command: TokenCommand) -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    match command {
+        TokenCommand::Create(args) => {
+            let token = generate_token();
+            db::token::add_api_token(&args.principal, args.role, &hash_token(&token), &pool)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to create API token: {}", e))?;
+            println!("{}", token);
+        }
+        TokenCommand::Revoke(args) => {
+            let revoked = db::token::remove_api_tokens(&args.principal, &pool)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to revoke API tokens: {}", e))?;
+            println!("Revoked {} API token(s) of {}", revoked, args.principal);
+        }
+        TokenCommand::List => {
+            let principals = db::token::get_api_tokens(&pool)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to list API tokens: {}", e))?;
+            for principal in principals {
+                println!("{} {}", principal.name, principal.role);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Generate the OpenAPI json docs and save to file or print to stdout
 fn generate_docs(output: &Option<String>) -> anyhow::Result<()> {
     let docs = ApiDoc::openapi();
@@ -191,7 +407,7 @@ async fn run_simulator(args: SimulatorArgs) -> anyhow::Result<()> {
     let pool = init_pool().await?;
     let config = Config::from_secret_key(TEST_SECRET_KEY)
         .map_err(|e| anyhow::anyhow!("Failed to generate config from secret key: {}", e))?;
-    db::config::update_config(&pool, &config)
+    db::config::update_config(&pool, &config, "simulator")
         .await
         .map_err(|e| anyhow::anyhow!("Failed to update config: {}", e))?;
     let (txs, votes) = generate_txs_votes();
@@ -239,7 +455,8 @@ async fn run_swagger(args: &SwaggerArgs) -> anyhow::Result<()> {
         args.server.address, args.server.port
     );
 
-    warp::serve(api_doc.or(swagger_ui).or(all_routes(pool)))
+    let auth = args.server.auth_mode(&pool).await?;
+    warp::serve(api_doc.or(swagger_ui).or(all_routes(pool, auth)))
         .run(socket)
         .await;
     Ok(())
@@ -288,6 +505,7 @@ async fn main() {
         Command::Docs(args) => generate_docs(&args.output),
         Command::Swagger(args) => run_swagger(&args).await,
         Command::Simulator(args) => run_simulator(args).await,
+        Command::Token(command) => manage_tokens(command).await,
         Command::Run(args) => {
             // Initialize the connection pool
             match init_pool().await {
@@ -296,6 +514,14 @@ async fn main() {
                         println!("Error occurred running API server: {}", e);
                         return;
                     }
+                    if let Err(e) = start_expiry_tracking(&pool, &args) {
+                        println!("Error occurred starting vote expiry tracking: {}", e);
+                        return;
+                    }
+                    if let Err(e) = start_ingestion(&pool, args.ingest) {
+                        println!("Error occurred starting transaction ingestion: {}", e);
+                        return;
+                    }
                     run(pool, args.server).await
                 }
                 Err(e) => Err(e),
@@ -0,0 +1,312 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sqlx::SqlitePool;
+
+use crate::{
+    config::Config,
+    db::{
+        self,
+        vote::{
+            add_auto_approval, add_delegator_vote, add_vote, get_auto_approved_amount,
+            get_delegator_votes, get_vote_by_id,
+        },
+        Error,
+    },
+    transaction::Transaction,
+    vote::{DelegatorVote, Vote, VoteChoice, VoteMechanism},
+};
+
+/// The period over which `Config::auto_approve_daily_max_amount` applies, in seconds
+const DAY: u64 = 24 * 60 * 60;
+
+/// Add a transaction to the database and cast the signer's auto vote on it if the policy allows.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the SQLite database connection pool.
+/// * transaction: &Transaction - The transaction to ingest.
+///
+/// # Returns
+/// * Result<Vote, Error>: The transaction's vote after applying the policy.
+pub async fn ingest_transaction(
+    pool: &SqlitePool,
+    transaction: &Transaction,
+) -> Result<Vote, Error> {
+    let config = db::config::get_config(pool).await?;
+    // Summing the approvals of the last day and recording this one in one transaction keeps
+    // concurrent ingestions from exceeding the daily maximum together
+    let mut tx = db::begin_write(pool).await?;
+    db::transaction::add_transaction(&mut tx, transaction).await?;
+    let mut vote = get_vote_by_id(&transaction.txid, &mut tx).await?;
+    let signing_delegator = config.delegate_public_key.to_string();
+    let mut votes = get_delegator_votes(&transaction.txid, &mut tx).await?;
+    // Never override a vote the signer already cast
+    if votes
+        .iter()
+        .any(|vote| vote.signing_delegator == signing_delegator)
+    {
+        tx.commit().await?;
+        return Ok(vote);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let approved_amount = get_auto_approved_amount(now.saturating_sub(DAY), &mut tx).await?;
+    let Some(vote_choice) = evaluate(&config, transaction, approved_amount) else {
+        tx.commit().await?;
+        return Ok(vote);
+    };
+    let delegator_vote = DelegatorVote {
+        txid: transaction.txid.clone(),
+        signing_delegator,
+        vote_choice,
+        vote_weight: config
+            .voting_weight(&config.delegate_public_key)
+            .unwrap_or(1),
+    };
+    add_delegator_vote(&delegator_vote, &mut tx).await?;
+    if vote_choice == VoteChoice::Approve {
+        add_auto_approval(
+            &transaction.txid,
+            transaction.transaction_amount,
+            now,
+            &mut tx,
+        )
+        .await?;
+    }
+    votes.push(delegator_vote);
+
+    vote.vote_choice = Some(vote_choice);
+    vote.vote_mechanism = VoteMechanism::Auto;
+    vote.vote_tally.tally(&votes, config.total_voting_weight());
+    add_vote(&vote, &mut tx).await?;
+    tx.commit().await?;
+    Ok(vote)
+}
+
+/// Evaluate the signer's auto vote policy for a transaction.
+///
+/// # Params
+/// * config: &Config - The signer configuration holding the policy rules.
+/// * transaction: &Transaction - The transaction to evaluate.
+/// * approved_amount: u64 - The total amount auto approved within the last day.
+///
+/// # Returns
+/// * Option<VoteChoice>: The vote to cast automatically, or None if the transaction requires a manual vote.
+pub fn evaluate(
+    config: &Config,
+    transaction: &Transaction,
+    approved_amount: u64,
+) -> Option<VoteChoice> {
+    let addresses = [
+        &transaction.transaction_originator_address,
+        &transaction.transaction_debit_address,
+        &transaction.transaction_credit_address,
+    ];
+    if addresses.iter().any(|address| {
+        config
+            .auto_deny_addresses
+            .iter()
+            .any(|denied| denied == address.address())
+    }) {
+        return Some(VoteChoice::Reject);
+    }
+
+    let max_amount = config
+        .auto_approve_kind_max_amounts
+        .get(&transaction.transaction_kind)
+        .copied()
+        .unwrap_or(config.auto_approve_max_amount);
+    if transaction.transaction_amount > max_amount {
+        return None;
+    }
+    let originator = transaction.transaction_originator_address.address();
+    if !config.auto_approve_addresses.is_empty()
+        && !config
+            .auto_approve_addresses
+            .iter()
+            .any(|allowed| allowed == originator)
+    {
+        return None;
+    }
+    if let Some(daily_max_amount) = config.auto_approve_daily_max_amount {
+        if approved_amount.saturating_add(transaction.transaction_amount) > daily_max_amount {
+            return None;
+        }
+    }
+    Some(VoteChoice::Approve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::init_pool,
+        transaction::{TransactionAddress, TransactionKind},
+        vote::VoteStatus,
+    };
+    use std::collections::BTreeMap;
+
+    const TEST_SECRET_KEY: &str =
+        "26F85CE8B2C635AD92F6148E4443FE415F512F3F29F44AB0E2CBDA819295BBD5";
+
+    fn config() -> Config {
+        Config::from_secret_key(TEST_SECRET_KEY).expect("Failed to create config")
+    }
+
+    fn transaction(amount: u64) -> Transaction {
+        Transaction {
+            transaction_amount: amount,
+            transaction_originator_address: TransactionAddress::Bitcoin("alice".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn approves_below_max_amount() {
+        let mut config = config();
+        config.auto_approve_max_amount = 100;
+        assert_eq!(
+            evaluate(&config, &transaction(100), 0),
+            Some(VoteChoice::Approve)
+        );
+        assert_eq!(evaluate(&config, &transaction(101), 0), None);
+    }
+
+    #[test]
+    fn denies_addresses() {
+        let mut config = config();
+        config.auto_deny_addresses = vec!["alice".to_string()];
+        assert_eq!(
+            evaluate(&config, &transaction(u64::MAX), 0),
+            Some(VoteChoice::Reject)
+        );
+
+        let mut tx = transaction(1);
+        tx.transaction_originator_address = TransactionAddress::Bitcoin("bob".to_string());
+        tx.transaction_credit_address = TransactionAddress::Stacks("alice".to_string());
+        assert_eq!(evaluate(&config, &tx, 0), Some(VoteChoice::Reject));
+    }
+
+    #[test]
+    fn applies_kind_max_amounts() {
+        let mut config = config();
+        config.auto_approve_max_amount = 100;
+        config.auto_approve_kind_max_amounts =
+            BTreeMap::from([(TransactionKind::WithdrawalFulfill, 10)]);
+        assert_eq!(
+            evaluate(&config, &transaction(50), 0),
+            Some(VoteChoice::Approve)
+        );
+
+        let mut tx = transaction(50);
+        tx.transaction_kind = TransactionKind::WithdrawalFulfill;
+        assert_eq!(evaluate(&config, &tx, 0), None);
+    }
+
+    #[test]
+    fn restricts_to_allowed_addresses() {
+        let mut config = config();
+        config.auto_approve_addresses = vec!["bob".to_string()];
+        assert_eq!(evaluate(&config, &transaction(1), 0), None);
+
+        config.auto_approve_addresses.push("alice".to_string());
+        assert_eq!(
+            evaluate(&config, &transaction(1), 0),
+            Some(VoteChoice::Approve)
+        );
+    }
+
+    #[test]
+    fn caps_daily_volume() {
+        let mut config = config();
+        config.auto_approve_daily_max_amount = Some(1000);
+        assert_eq!(
+            evaluate(&config, &transaction(100), 900),
+            Some(VoteChoice::Approve)
+        );
+        assert_eq!(evaluate(&config, &transaction(100), 901), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_ingest_transaction() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let mut config = config();
+        config.auto_approve_max_amount = 100;
+        config.auto_approve_daily_max_amount = Some(150);
+        config.auto_deny_addresses = vec!["mallory".to_string()];
+        db::config::update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to add configuration to database.");
+
+        // The signer is the only voter, so its auto vote decides the ballot
+        let tx = Transaction {
+            txid: "1".repeat(64),
+            ..transaction(100)
+        };
+        let vote = ingest_transaction(&pool, &tx).await.unwrap();
+        assert_eq!(vote.vote_choice, Some(VoteChoice::Approve));
+        assert_eq!(vote.vote_mechanism, VoteMechanism::Auto);
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Approved);
+        assert_eq!(get_vote_by_id(&tx.txid, &pool).await.unwrap(), vote);
+
+        // Re-ingesting the transaction must not cast a second vote
+        assert_eq!(ingest_transaction(&pool, &tx).await.unwrap(), vote);
+
+        // Over the daily cap the transaction is left for a manual vote
+        let tx = Transaction {
+            txid: "2".repeat(64),
+            ..transaction(100)
+        };
+        let vote = ingest_transaction(&pool, &tx).await.unwrap();
+        assert_eq!(vote.vote_choice, None);
+        assert_eq!(vote.vote_mechanism, VoteMechanism::Manual);
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Pending);
+
+        let mut tx = Transaction {
+            txid: "3".repeat(64),
+            ..transaction(1)
+        };
+        tx.transaction_debit_address = TransactionAddress::Bitcoin("mallory".to_string());
+        let vote = ingest_transaction(&pool, &tx).await.unwrap();
+        assert_eq!(vote.vote_choice, Some(VoteChoice::Reject));
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Rejected);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(2000)]
+    async fn test_concurrent_daily_cap() {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let mut config = config();
+        config.auto_approve_max_amount = 100;
+        config.auto_approve_daily_max_amount = Some(150);
+        db::config::update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to add configuration to database.");
+
+        // Only one of two transactions ingested at once fits in the daily cap
+        let first = Transaction {
+            txid: "1".repeat(64),
+            ..transaction(100)
+        };
+        let second = Transaction {
+            txid: "2".repeat(64),
+            ..transaction(100)
+        };
+        let (first, second) = tokio::join!(
+            ingest_transaction(&pool, &first),
+            ingest_transaction(&pool, &second)
+        );
+        let approved = [first.unwrap(), second.unwrap()]
+            .iter()
+            .filter(|vote| vote.vote_choice == Some(VoteChoice::Approve))
+            .count();
+        assert_eq!(approved, 1);
+    }
+}
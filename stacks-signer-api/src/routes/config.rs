@@ -1,27 +1,32 @@
 use std::convert::Infallible;
 
 use crate::{
+    auth::{with_role, AuthMode, Principal, Role},
     config::Config,
     db,
-    routes::{json_body, with_pool},
+    routes::{json_body, with_pool, Pagination},
 };
 use sqlx::SqlitePool;
+use tracing::{error, info};
 use warp::{hyper::StatusCode, Filter, Reply};
 
 /// Route for updating the signer config.
 ///
 /// # Params
 /// * pool: SqlitePool - The reference to the Sqlite database connection pool.
+/// * auth: AuthMode - Whether requests must carry an API token.
 ///
 /// # Returns
 /// * impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone:
 ///   The Warp filter for the update_config_route endpoint for routing HTTP requests.
 pub fn update_config_route(
     pool: SqlitePool,
+    auth: AuthMode,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     warp::post()
         .and(warp::path!("v1" / "config"))
         .and(warp::path::end())
+        .and(with_role(pool.clone(), auth, Role::Admin))
         .and(json_body::<Config>())
         .and(with_pool(pool))
         .and_then(update_config)
@@ -31,20 +36,64 @@ pub fn update_config_route(
 ///
 /// # Params
 /// * pool: SqlitePool - The reference to the Sqlite database connection pool.
+/// * auth: AuthMode - Whether requests must carry an API token.
 ///
 /// # Returns
 /// * impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone:
 ///  The Warp filter for the get_config_route endpoint for routing HTTP requests.
 pub fn get_config_route(
     pool: SqlitePool,
+    auth: AuthMode,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     warp::get()
         .and(warp::path!("v1" / "config"))
         .and(warp::path::end())
+        .and(with_role(pool.clone(), auth, Role::Admin))
         .and(with_pool(pool))
         .and_then(get_config)
 }
 
+/// Route for fetching the saved versions of the signer's configuration.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the Sqlite database connection pool.
+/// * auth: AuthMode - Whether requests must carry an API token.
+///
+/// # Returns
+/// * impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone:
+///  The Warp filter for the get_config_history endpoint for routing HTTP requests.
+pub fn get_config_history_route(
+    pool: SqlitePool,
+    auth: AuthMode,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path!("v1" / "config" / "history"))
+        .and(with_role(pool.clone(), auth, Role::Admin))
+        .and(warp::query::<Pagination>())
+        .and(with_pool(pool))
+        .and_then(get_config_history)
+}
+
+/// Route for restoring a saved version of the signer's configuration.
+///
+/// # Params
+/// * pool: SqlitePool - The reference to the Sqlite database connection pool.
+/// * auth: AuthMode - Whether requests must carry an API token.
+///
+/// # Returns
+/// * impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone:
+///  The Warp filter for the rollback_config endpoint for routing HTTP requests.
+pub fn rollback_config_route(
+    pool: SqlitePool,
+    auth: AuthMode,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::post()
+        .and(warp::path!("v1" / "config" / "history" / u64 / "rollback"))
+        .and(with_role(pool.clone(), auth, Role::Admin))
+        .and(with_pool(pool))
+        .and_then(rollback_config)
+}
+
 /// Update the signer's configuration.
 #[utoipa::path(
     post,
@@ -52,27 +101,64 @@ pub fn get_config_route(
     request_body = Config,
     responses(
         (status = OK, description = "Config updated successfully."),
+        (status = UNAUTHORIZED, description = "Missing or unknown API token."),
+        (status = FORBIDDEN, description = "API token is not an admin token."),
         (status = INTERNAL_SERVER_ERROR, description = "Internal server error occurred.", body = ErrorResponse)
     ),
+    security(("bearer_token" = []))
 )]
-pub async fn update_config(config: Config, pool: SqlitePool) -> Result<Box<dyn Reply>, Infallible> {
-    if db::config::update_config(&pool, &config).await.is_ok() {
+pub async fn update_config(
+    principal: Principal,
+    config: Config,
+    pool: SqlitePool,
+) -> Result<Box<dyn Reply>, Infallible> {
+    let old_config = db::config::get_config(&pool).await.ok();
+    if db::config::update_config(&pool, &config, &principal.name)
+        .await
+        .is_ok()
+    {
+        info!(
+            target: "audit",
+            principal = %principal.name,
+            "Config updated: {}",
+            changed_fields(old_config.as_ref(), &config).join(", ")
+        );
         Ok(Box::new(StatusCode::OK))
     } else {
         Ok(Box::new(StatusCode::NOT_FOUND))
     }
 }
 
+/// The names of the config fields that differ from the previous config, so updates can be
+/// audited without logging secret values.
+fn changed_fields(old_config: Option<&Config>, new_config: &Config) -> Vec<String> {
+    let old_config = serde_json::to_value(old_config).unwrap_or_default();
+    match serde_json::to_value(new_config) {
+        Ok(serde_json::Value::Object(new_config)) => new_config
+            .into_iter()
+            .filter(|(field, value)| old_config.get(field) != Some(value))
+            .map(|(field, _)| field)
+            .collect(),
+        _ => vec![],
+    }
+}
+
 /// Get the signer's configuration.
 #[utoipa::path(
     get,
     path = "/v1/config",
     responses(
         (status = OK, description = "Config retrieved successfully.", body = Config),
+        (status = UNAUTHORIZED, description = "Missing or unknown API token."),
+        (status = FORBIDDEN, description = "API token is not an admin token."),
         (status = INTERNAL_SERVER_ERROR, description = "Internal server error occurred.", body = ErrorResponse)
     ),
+    security(("bearer_token" = []))
 )]
-pub async fn get_config(pool: SqlitePool) -> Result<Box<dyn Reply>, Infallible> {
+pub async fn get_config(
+    _principal: Principal,
+    pool: SqlitePool,
+) -> Result<Box<dyn Reply>, Infallible> {
     if let Ok(config) = db::config::get_config(&pool).await {
         Ok(Box::new(warp::reply::with_status(
             warp::reply::json(&config),
@@ -83,10 +169,92 @@ pub async fn get_config(pool: SqlitePool) -> Result<Box<dyn Reply>, Infallible>
     }
 }
 
+/// Get the saved versions of the signer's configuration, newest first.
+#[utoipa::path(
+    get,
+    path = "/v1/config/history",
+    responses(
+        (status = OK, description = "Config versions retrieved successfully.", body = [ConfigVersion]),
+        (status = BAD_REQUEST, description = "Invalid page."),
+        (status = UNAUTHORIZED, description = "Missing or unknown API token."),
+        (status = FORBIDDEN, description = "API token is not an admin token."),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal server error occurred.", body = ErrorResponse)
+    ),
+    params(
+        ("page" = Option<usize>, Query, description = "Page number of the versions to return, starting at 1"),
+        ("limit" = Option<usize>, Query, description = "Maximum number of versions per page"),
+    ),
+    security(("bearer_token" = []))
+)]
+pub async fn get_config_history(
+    _principal: Principal,
+    query: Pagination,
+    pool: SqlitePool,
+) -> Result<Box<dyn Reply>, Infallible> {
+    if query.page == Some(0) {
+        return Ok(Box::new(StatusCode::BAD_REQUEST));
+    }
+    match db::config::get_config_history(&pool, query.page, query.limit).await {
+        Ok(versions) => Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&versions),
+            StatusCode::OK,
+        ))),
+        Err(e) => {
+            error!("Failed to retrieve config history: {}", e);
+            Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Restore a saved version of the signer's configuration as a new version.
+#[utoipa::path(
+    post,
+    path = "/v1/config/history/{version}/rollback",
+    responses(
+        (status = OK, description = "Config restored successfully.", body = Config),
+        (status = NOT_FOUND, description = "No config version was found."),
+        (status = UNAUTHORIZED, description = "Missing or unknown API token."),
+        (status = FORBIDDEN, description = "API token is not an admin token."),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal server error occurred.", body = ErrorResponse)
+    ),
+    params(
+        ("version" = u64, Path, description = "The config version to restore"),
+    ),
+    security(("bearer_token" = []))
+)]
+pub async fn rollback_config(
+    version: u64,
+    principal: Principal,
+    pool: SqlitePool,
+) -> Result<Box<dyn Reply>, Infallible> {
+    let old_config = db::config::get_config(&pool).await.ok();
+    match db::config::rollback_config(&pool, version, &principal.name).await {
+        Ok(config) => {
+            info!(
+                target: "audit",
+                principal = %principal.name,
+                "Config rolled back to version {}: {}",
+                version,
+                changed_fields(old_config.as_ref(), &config).join(", ")
+            );
+            Ok(Box::new(warp::reply::with_status(
+                warp::reply::json(&config),
+                StatusCode::OK,
+            )))
+        }
+        Err(db::Error::SqlxError(sqlx::Error::RowNotFound)) => Ok(Box::new(StatusCode::NOT_FOUND)),
+        Err(e) => {
+            error!("Failed to roll back config to version {}: {}", version, e);
+            Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
+    use crate::auth::{generate_token, handle_rejection, hash_token, ANONYMOUS_PRINCIPAL};
+    use crate::config::{Config, ConfigVersion};
     use crate::db::init_pool;
     use secp256k1::PublicKey;
     use std::str::FromStr;
@@ -111,7 +279,7 @@ mod tests {
         let mut expected_config =
             Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config");
         // First add a config.
-        db::config::update_config(&pool, &expected_config)
+        db::config::update_config(&pool, &expected_config, "test")
             .await
             .expect("Failed to add configuration to database.");
 
@@ -127,7 +295,7 @@ mod tests {
             .path("/v1/config")
             .method("POST")
             .json(&expected_config)
-            .reply(&update_config_route(pool.clone()))
+            .reply(&update_config_route(pool.clone(), AuthMode::Disabled))
             .await;
 
         assert_eq!(api.status(), StatusCode::OK);
@@ -161,7 +329,7 @@ mod tests {
         let pool = init_db().await;
         let expected_config =
             Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config");
-        db::config::update_config(&pool, &expected_config)
+        db::config::update_config(&pool, &expected_config, "test")
             .await
             .expect("Failed to add configuration to database.");
 
@@ -169,7 +337,7 @@ mod tests {
             .path("/v1/config")
             .method("GET")
             .header("content-type", "application/json")
-            .reply(&get_config_route(pool))
+            .reply(&get_config_route(pool, AuthMode::Disabled))
             .await;
         let body = api.body();
         let config: Config = serde_json::from_slice(body).expect("failed to deserialize config");
@@ -192,4 +360,122 @@ mod tests {
             expected_config.delegator_public_keys
         );
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_update_config_requires_admin() {
+        let pool = init_db().await;
+        let config = Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config");
+        db::config::update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to add configuration to database.");
+        let voter = generate_token();
+        let admin = generate_token();
+        db::token::add_api_token("voter", Role::Voter, &hash_token(&voter), &pool)
+            .await
+            .expect("Failed to add API token.");
+        db::token::add_api_token("admin", Role::Admin, &hash_token(&admin), &pool)
+            .await
+            .expect("Failed to add API token.");
+
+        let route = update_config_route(pool.clone(), AuthMode::Required).recover(handle_rejection);
+        let mut updated_config = config.clone();
+        updated_config.auto_approve_max_amount = 10;
+        for (token, status) in [(voter, StatusCode::FORBIDDEN), (admin, StatusCode::OK)] {
+            let api = warp::test::request()
+                .path("/v1/config")
+                .method("POST")
+                .header("authorization", format!("Bearer {token}"))
+                .json(&updated_config)
+                .reply(&route)
+                .await;
+            assert_eq!(api.status(), status);
+        }
+        let config = db::config::get_config(&pool)
+            .await
+            .expect("Failed to get configuration from the database");
+        assert_eq!(config, updated_config);
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        let config = Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config");
+        let mut updated_config = config.clone();
+        updated_config.auto_approve_max_amount += 1;
+        updated_config.auto_deny_addresses = vec!["Address1".to_string()];
+        assert_eq!(
+            changed_fields(Some(&config), &updated_config),
+            vec!["auto_approve_max_amount", "auto_deny_addresses"]
+        );
+        assert!(changed_fields(Some(&config), &config).is_empty());
+        assert!(changed_fields(None, &config).contains(&"secret_key".to_string()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_config_history_rollback() {
+        let pool = init_db().await;
+        let config = Config::from_secret_key(TEST_SECRET_KEY_1).expect("Failed to create config");
+        db::config::update_config(&pool, &config, "alice")
+            .await
+            .expect("Failed to add configuration to database.");
+        let mut updated_config = config.clone();
+        updated_config.auto_deny_addresses = vec!["Address1".to_string()];
+        db::config::update_config(&pool, &updated_config, "bob")
+            .await
+            .expect("Failed to update configuration.");
+        // Saving an unchanged config records no version
+        db::config::update_config(&pool, &updated_config, "bob")
+            .await
+            .expect("Failed to update configuration.");
+
+        let api = warp::test::request()
+            .path("/v1/config/history")
+            .method("GET")
+            .reply(&get_config_history_route(pool.clone(), AuthMode::Disabled))
+            .await;
+        assert_eq!(api.status(), StatusCode::OK);
+        let history: Vec<ConfigVersion> =
+            serde_json::from_slice(api.body()).expect("failed to deserialize config history");
+        let summary: Vec<_> = history
+            .iter()
+            .map(|version| (version.author.as_str(), &version.config))
+            .collect();
+        assert_eq!(summary, vec![("bob", &updated_config), ("alice", &config)]);
+
+        let api = warp::test::request()
+            .path(&format!(
+                "/v1/config/history/{}/rollback",
+                history[1].version
+            ))
+            .method("POST")
+            .reply(&rollback_config_route(pool.clone(), AuthMode::Disabled))
+            .await;
+        assert_eq!(api.status(), StatusCode::OK);
+        assert_eq!(db::config::get_config(&pool).await.unwrap(), config);
+        let history = db::config::get_config_history(&pool, None, None)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].author, ANONYMOUS_PRINCIPAL);
+        assert_eq!(history[0].restored_version, Some(history[2].version));
+        assert_eq!(history[0].config, config);
+
+        let api = warp::test::request()
+            .path("/v1/config/history?page=2&limit=2")
+            .method("GET")
+            .reply(&get_config_history_route(pool.clone(), AuthMode::Disabled))
+            .await;
+        assert_eq!(api.status(), StatusCode::OK);
+        let page: Vec<ConfigVersion> =
+            serde_json::from_slice(api.body()).expect("failed to deserialize config history");
+        assert_eq!(page, history[2..]);
+
+        let api = warp::test::request()
+            .path("/v1/config/history/42/rollback")
+            .method("POST")
+            .reply(&rollback_config_route(pool.clone(), AuthMode::Disabled))
+            .await;
+        assert_eq!(api.status(), StatusCode::NOT_FOUND);
+    }
 }
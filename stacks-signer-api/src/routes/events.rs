@@ -0,0 +1,144 @@
+use std::convert::Infallible;
+
+use sqlx::SqlitePool;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tracing::error;
+use warp::{hyper::StatusCode, sse, Filter, Reply};
+
+use crate::{
+    auth::{with_role, AuthMode, Principal, Role},
+    db,
+    event::{Event, EventBroadcaster},
+    routes::with_pool,
+};
+
+/// Stream transaction and vote events
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    responses(
+        (status = 200, description = "Server-sent event stream of transaction and vote events", body = Event, content_type = "text/event-stream"),
+        (status = UNAUTHORIZED, description = "Missing or unknown API token"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal server error")
+    ),
+    params(
+        ("Last-Event-ID" = Option<u64>, Header, description = "The ID of the last event received, to first replay the events missed since"),
+    ),
+    security(("bearer_token" = []))
+)]
+async fn get_events(
+    _principal: Principal,
+    last_event_id: Option<u64>,
+    pool: SqlitePool,
+    broadcaster: EventBroadcaster,
+) -> Result<Box<dyn Reply>, Infallible> {
+    // Subscribe before replaying so no event is missed in between
+    let receiver = broadcaster.subscribe();
+    let replayed = match last_event_id {
+        Some(id) => match db::event::get_events_after(id, &pool).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Failed to replay events: {}", e);
+                return Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+        },
+        None => vec![],
+    };
+    let last_replayed_id = replayed.last().map(|event| event.id).unwrap_or(0);
+    let stream = tokio_stream::iter(replayed)
+        .chain(live_events(receiver, last_replayed_id))
+        .map(|event| sse_event(&event));
+    Ok(Box::new(sse::reply(sse::keep_alive().stream(stream))))
+}
+
+/// Stream the published events that were not replayed already.
+///
+/// A subscriber that falls behind misses events, so its stream ends instead to make the client
+/// reconnect and replay them with the ID of the last event it received.
+fn live_events(
+    receiver: broadcast::Receiver<Event>,
+    last_replayed_id: u64,
+) -> impl Stream<Item = Event> {
+    BroadcastStream::new(receiver)
+        .map_while(Result::ok)
+        .filter(move |event| event.id > last_replayed_id)
+}
+
+/// Convert an event to a server-sent event named after its kind.
+fn sse_event(event: &Event) -> Result<sse::Event, serde_json::Error> {
+    sse::Event::default()
+        .id(event.id.to_string())
+        .event(event.event_kind.to_string())
+        .json_data(event)
+}
+
+/// Route for streaming transaction and vote events.
+///
+/// # Returns
+/// * impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone:
+///   The Warp filter for the get_events endpoint for routing HTTP requests.
+pub fn events_route(
+    pool: SqlitePool,
+    auth: AuthMode,
+    broadcaster: EventBroadcaster,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path!("v1" / "events"))
+        .and(with_role(pool.clone(), auth, Role::Viewer))
+        .and(warp::header::optional::<u64>("last-event-id"))
+        .and(with_pool(pool))
+        .and(warp::any().map(move || broadcaster.clone()))
+        .and_then(get_events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    fn event(id: u64) -> Event {
+        Event {
+            id,
+            event_kind: EventKind::TransactionIngested,
+            txid: "1".repeat(64),
+            signing_delegator: None,
+            vote_choice: None,
+            vote_status: None,
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_live_events() {
+        let (sender, receiver) = broadcast::channel(4);
+        for id in 1..=3 {
+            sender.send(event(id)).unwrap();
+        }
+        drop(sender);
+
+        let ids: Vec<_> = live_events(receiver, 1)
+            .map(|event| event.id)
+            .collect()
+            .await;
+        assert_eq!(ids, vec![2, 3]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_live_events_end_when_lagging() {
+        let (sender, receiver) = broadcast::channel(2);
+        for id in 1..=3 {
+            sender.send(event(id)).unwrap();
+        }
+
+        // The stream ends although the sender is still open, as event 1 was missed
+        let ids: Vec<_> = live_events(receiver, 0)
+            .map(|event| event.id)
+            .collect()
+            .await;
+        assert!(ids.is_empty());
+        drop(sender);
+    }
+}
@@ -1,6 +1,8 @@
 #![deny(missing_docs)]
 /// Config Routes
 pub mod config;
+/// Event Routes
+pub mod events;
 /// Transaction Routes
 pub mod transactions;
 /// Vote Routes
@@ -11,8 +13,16 @@ use sqlx::SqlitePool;
 use std::convert::Infallible;
 use warp::{Filter, Rejection, Reply};
 
+use crate::{
+    auth::{handle_rejection, AuthMode},
+    event::{EventBroadcaster, EVENT_POLLING_INTERVAL},
+};
+
 use self::{
-    config::{get_config_route, update_config_route},
+    config::{
+        get_config_history_route, get_config_route, rollback_config_route, update_config_route,
+    },
+    events::events_route,
     transactions::{get_transaction_by_id_route, get_transactions_route},
     vote::vote_route,
 };
@@ -26,27 +36,6 @@ pub struct Pagination {
     pub limit: Option<usize>,
 }
 
-/// Paginate a slice of items.
-///
-/// This utility function slices a given set of items based on the specified `page` and `limit`.
-/// If `page` and/or `limit` are not provided (None), the function will use default values.
-///
-/// # Params
-/// * items: &[T] - The reference to the slice of items to be paginated.
-/// * page: Option<usize> - The optional page number for pagination (1-based index).
-/// * limit: Option<usize> - The optional limit representing the maximum number of items per page.
-///
-/// # Returns
-/// * &[T]: A slice of the original items, paginated according to the provided page and limit.
-pub fn paginate_items<T>(items: &[T], page: Option<usize>, limit: Option<usize>) -> &[T] {
-    let page = page.unwrap_or(1);
-    let limit = limit.unwrap_or(usize::MAX);
-
-    let start_index = items.len().min((page - 1) * limit);
-    let end_index = items.len().min(start_index + limit);
-    &items[start_index.min(end_index)..end_index]
-}
-
 /// A helper function to extract a JSON body from a request.
 pub fn json_body<T: std::marker::Send + DeserializeOwned>(
 ) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
@@ -64,28 +53,45 @@ pub fn with_pool(
 
 /// A helper function to combine all routes into one Warp Filter
 ///
+/// Starts the event broadcaster behind the events route, so it must be called within a Tokio runtime.
+///
 /// # Params
 /// * pool: SqlitePool - The reference to the Sqlite database connection pool.
+/// * auth: AuthMode - Whether requests must carry an API token.
 ///
 /// # Returns
 /// * impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone: The Warp filter for the routes.
 pub fn all_routes(
     pool: SqlitePool,
+    auth: AuthMode,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     // Set up the routes
     // Config routes
-    let update_config_route = update_config_route(pool.clone());
-    let get_config_route = get_config_route(pool.clone());
+    let update_config_route = update_config_route(pool.clone(), auth);
+    let get_config_route = get_config_route(pool.clone(), auth);
+    let get_config_history_route = get_config_history_route(pool.clone(), auth);
+    let rollback_config_route = rollback_config_route(pool.clone(), auth);
     // Transaction routes
-    let get_transactions_route = get_transactions_route(pool.clone());
-    let get_transaction_by_id_route = get_transaction_by_id_route(pool.clone());
+    let get_transactions_route = get_transactions_route(pool.clone(), auth);
+    let get_transaction_by_id_route = get_transaction_by_id_route(pool.clone(), auth);
     // Vote routes
-    let vote_route = vote_route(pool);
+    let vote_route = vote_route(pool.clone(), auth);
+    // Event routes
+    let events_route = events_route(
+        pool.clone(),
+        auth,
+        EventBroadcaster::spawn(pool, EVENT_POLLING_INTERVAL),
+    );
 
     // Combine and return the routes in a single filter
     update_config_route
         .or(get_config_route)
+        .or(get_config_history_route)
+        .or(rollback_config_route)
         .or(get_transactions_route)
         .or(get_transaction_by_id_route)
         .or(vote_route)
+        .or(events_route)
+        // Reply to requests refused by the authentication filter with their status code
+        .recover(handle_rejection)
 }
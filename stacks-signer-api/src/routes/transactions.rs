@@ -1,41 +1,34 @@
 use std::convert::Infallible;
 
 use crate::{
-    db::{self, vote::get_vote_by_id},
-    routes::{paginate_items, with_pool},
-    transaction::TransactionResponse,
-    vote::VoteStatus,
+    auth::{with_role, AuthMode, Principal, Role},
+    db,
+    routes::with_pool,
+    transaction::{TransactionQuery, TransactionResponse},
 };
-use serde::Deserialize;
 use sqlx::SqlitePool;
 use tracing::error;
-use utoipa::IntoParams;
 use warp::{hyper::StatusCode, Filter, Reply};
 
-#[derive(Debug, Deserialize, IntoParams)]
-#[into_params(parameter_in = Query)]
-/// Query parameters for the transaction list
-pub struct TransactionQuery {
-    /// The page number.
-    pub page: Option<usize>,
-    /// The limit of transactions per page.
-    pub limit: Option<usize>,
-    /// The transaction status to filter by.
-    pub status: Option<VoteStatus>,
-}
 /// Get transaction by id
 #[utoipa::path(
     get,
     path = "/v1/transactions/{id}",
     responses(
         (status = 200, description = "Transaction found successfully", body = TransactionResponse),
-        (status = NOT_FOUND, description = "No transaction was found")
+        (status = NOT_FOUND, description = "No transaction was found"),
+        (status = UNAUTHORIZED, description = "Missing or unknown API token")
     ),
     params(
         ("id" = String, Path, description = "Transaction id for retrieving a specific Transaction"),
-    )
+    ),
+    security(("bearer_token" = []))
 )]
-async fn get_transaction_by_id(id: String, pool: SqlitePool) -> Result<Box<dyn Reply>, Infallible> {
+async fn get_transaction_by_id(
+    id: String,
+    _principal: Principal,
+    pool: SqlitePool,
+) -> Result<Box<dyn Reply>, Infallible> {
     if let Ok(tx) = db::transaction::get_transaction_by_id(id.as_str(), &pool).await {
         if let Ok(vote) = db::vote::get_vote_by_id(id.as_str(), &pool).await {
             let tx_response = TransactionResponse {
@@ -60,55 +53,48 @@ async fn get_transaction_by_id(id: String, pool: SqlitePool) -> Result<Box<dyn R
     }
 }
 
-/// Get list of all transactions
+/// Get a filtered and sorted page of transactions
 #[utoipa::path(
 get,
 path = "/v1/transactions",
 responses(
-    (status = 200, description = "Transaction list returned succesfully", body = Vec<TransactionResponse>),
+    (status = 200, description = "Transaction page returned succesfully", body = TransactionPage),
+    (status = BAD_REQUEST, description = "Invalid limit or unknown cursor"),
+    (status = UNAUTHORIZED, description = "Missing or unknown API token"),
     (status = INTERNAL_SERVER_ERROR, description = "Internal server error")
 ),
-params(TransactionQuery)
+params(TransactionQuery),
+security(("bearer_token" = []))
 )]
 async fn get_transactions(
+    _principal: Principal,
     query: TransactionQuery,
     pool: SqlitePool,
 ) -> Result<Box<dyn Reply>, Infallible> {
-    if query.page == Some(0) {
+    if query.limit == Some(0) {
         return Ok(Box::new(StatusCode::BAD_REQUEST));
     }
-    let mut filtered_transactions: Vec<TransactionResponse> = vec![];
-    if let Ok(txs) = db::transaction::get_transactions(&pool).await {
-        for tx in txs {
-            if let Ok(vote) = get_vote_by_id(&tx.txid, &pool).await {
-                let tx_response = TransactionResponse {
-                    transaction: tx,
-                    vote_tally: vote.vote_tally,
-                    vote_mechanism: vote.vote_mechanism,
-                    vote_choice: vote.vote_choice,
-                };
-                if let Some(status) = query.status {
-                    if vote.vote_tally.vote_status == status {
-                        filtered_transactions.push(tx_response);
-                    }
-                } else {
-                    filtered_transactions.push(tx_response);
-                }
-            } else {
-                error!(
-                    "Could not find cooresponding vote for transaction: {}. Database may be corrupted!",
-                    tx.txid
-                );
+    if let Some(cursor) = &query.cursor {
+        match db::transaction::get_transaction_by_id(cursor, &pool).await {
+            Ok(_) => {}
+            Err(db::Error::SqlxError(sqlx::Error::RowNotFound)) => {
+                return Ok(Box::new(StatusCode::BAD_REQUEST));
+            }
+            Err(e) => {
+                error!("Failed to look up transaction cursor: {}", e);
                 return Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR));
             }
         }
-        let results = paginate_items(&filtered_transactions, query.page, query.limit);
-        Ok(Box::new(warp::reply::with_status(
-            warp::reply::json(&results),
+    }
+    match db::transaction::get_transaction_page(&pool, &query).await {
+        Ok(page) => Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&page),
             StatusCode::OK,
-        )))
-    } else {
-        Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR))
+        ))),
+        Err(e) => {
+            error!("Failed to list transactions: {}", e);
+            Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR))
+        }
     }
 }
 
@@ -119,10 +105,12 @@ async fn get_transactions(
 ///   The Warp filter for the get_transactions_route endpoint for routing HTTP requests.
 pub fn get_transactions_route(
     pool: SqlitePool,
+    auth: AuthMode,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::get()
         .and(warp::path!("v1" / "transactions"))
         .and(warp::path::end())
+        .and(with_role(pool.clone(), auth, Role::Viewer))
         .and(warp::query::<TransactionQuery>())
         .and(with_pool(pool))
         .and_then(get_transactions)
@@ -135,9 +123,169 @@ pub fn get_transactions_route(
 ///   The Warp filter for the get_transaction_by_id_route endpoint for routing HTTP requests.
 pub fn get_transaction_by_id_route(
     pool: SqlitePool,
+    auth: AuthMode,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::get()
         .and(warp::path!("v1" / "transactions" / String))
+        .and(with_role(pool.clone(), auth, Role::Viewer))
         .and(with_pool(pool))
         .and_then(get_transaction_by_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{init_pool, transaction::add_transaction, vote::add_vote},
+        transaction::{Transaction, TransactionAddress, TransactionKind, TransactionPage},
+        vote::{Vote, VoteMechanism, VoteStatus, VoteTally},
+    };
+
+    /// Initialize a database with six transactions: odd ones are deposits mined at height 10
+    /// and approved, even ones are withdrawals mined at height 20 by "bob".
+    async fn init_db() -> SqlitePool {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        for i in 0..6u64 {
+            let deposit = i % 2 == 1;
+            let tx = Transaction {
+                txid: i.to_string().repeat(64),
+                transaction_kind: if deposit {
+                    TransactionKind::DepositReveal
+                } else {
+                    TransactionKind::WithdrawalReveal
+                },
+                transaction_block_height: Some(if deposit { 10 } else { 20 }),
+                transaction_amount: 100 * i,
+                transaction_originator_address: TransactionAddress::Bitcoin(
+                    if deposit { "alice" } else { "bob" }.to_string(),
+                ),
+                ..Default::default()
+            };
+            add_transaction(&pool, &tx)
+                .await
+                .expect("failed to add transaction");
+            if deposit {
+                let vote = Vote {
+                    txid: tx.txid.clone(),
+                    vote_tally: VoteTally {
+                        vote_status: VoteStatus::Approved,
+                        target_consensus: 70,
+                        current_consensus: 100,
+                    },
+                    vote_choice: None,
+                    vote_mechanism: VoteMechanism::Manual,
+                };
+                add_vote(&vote, &pool).await.expect("failed to add vote");
+            }
+        }
+        pool
+    }
+
+    async fn list(pool: &SqlitePool, query: &str) -> (StatusCode, Option<TransactionPage>) {
+        let api = warp::test::request()
+            .path(&format!("/v1/transactions?{query}"))
+            .method("GET")
+            .reply(&get_transactions_route(pool.clone(), AuthMode::Disabled))
+            .await;
+        (api.status(), serde_json::from_slice(api.body()).ok())
+    }
+
+    fn ids(page: &TransactionPage) -> Vec<char> {
+        page.transactions
+            .iter()
+            .map(|tx| tx.transaction.txid.chars().next().unwrap())
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_filter_transactions() {
+        let pool = init_db().await;
+
+        let (status, page) = list(&pool, "kind=depositreveal").await;
+        assert_eq!(status, StatusCode::OK);
+        let page = page.expect("failed to deserialize transaction page");
+        assert_eq!(ids(&page), vec!['1', '3', '5']);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.next_cursor, None);
+
+        let (_, page) = list(&pool, "status=pending&min_amount=100&max_amount=400").await;
+        assert_eq!(ids(&page.unwrap()), vec!['2', '4']);
+
+        let (_, page) = list(
+            &pool,
+            "address=alice&min_block_height=5&max_block_height=15",
+        )
+        .await;
+        assert_eq!(ids(&page.unwrap()), vec!['1', '3', '5']);
+
+        let (_, page) = list(&pool, "address=alice&min_block_height=15").await;
+        assert_eq!(page.unwrap().total, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_paginate_transactions() {
+        let pool = init_db().await;
+
+        let mut cursor = None;
+        let mut listed = vec![];
+        loop {
+            let query = match &cursor {
+                Some(cursor) => format!("sort=block_height&order=desc&limit=4&cursor={cursor}"),
+                None => "sort=block_height&order=desc&limit=4".to_string(),
+            };
+            let (status, page) = list(&pool, &query).await;
+            assert_eq!(status, StatusCode::OK);
+            let page = page.expect("failed to deserialize transaction page");
+            assert_eq!(page.total, 6);
+            listed.extend(ids(&page));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        // Sorted by block height, with ties broken by transaction ID in the same direction
+        assert_eq!(listed, vec!['4', '2', '0', '5', '3', '1']);
+
+        let (status, _) = list(&pool, "limit=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = list(&pool, &format!("cursor={}", "9".repeat(64))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_address_chains() {
+        let pool = init_db().await;
+        let txid = "1".repeat(64);
+
+        // Stacks addresses are not read back as Bitcoin addresses
+        let (status, page) = list(&pool, "kind=depositreveal&limit=1").await;
+        assert_eq!(status, StatusCode::OK);
+        let transaction = &page.unwrap().transactions[0].transaction;
+        assert_eq!(transaction.txid, txid);
+        assert_eq!(
+            transaction.transaction_originator_address,
+            TransactionAddress::Bitcoin("alice".to_string())
+        );
+        assert_eq!(
+            transaction.transaction_credit_address,
+            Transaction::default().transaction_credit_address
+        );
+
+        let api = warp::test::request()
+            .path(&format!("/v1/transactions/{txid}"))
+            .method("GET")
+            .reply(&get_transaction_by_id_route(
+                pool.clone(),
+                AuthMode::Disabled,
+            ))
+            .await;
+        assert_eq!(api.status(), StatusCode::OK);
+        let response: TransactionResponse = serde_json::from_slice(api.body()).unwrap();
+        assert_eq!(&response.transaction, transaction);
+    }
+}
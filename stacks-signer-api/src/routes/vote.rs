@@ -1,11 +1,21 @@
 use std::convert::Infallible;
 
 use crate::{
-    db::vote::{add_vote, get_vote_by_id},
+    auth::{with_role, AuthMode, Principal, Role},
+    db::{
+        begin_write,
+        config::get_config,
+        transaction::get_current_block_height,
+        vote::{
+            add_delegator_vote, add_vote, advance_delegator_nonce, close_expired_votes,
+            get_delegator_votes, get_vote_by_id,
+        },
+    },
     routes::{json_body, with_pool},
-    vote::{VoteRequest, VoteResponse, VoteStatus},
+    vote::{DelegatorVote, VoteRequest, VoteResponse, VoteStatus},
 };
 use sqlx::SqlitePool;
+use tracing::{error, info};
 use warp::{hyper::StatusCode, Filter, Reply};
 
 /// Vote for a transaction
@@ -18,35 +28,113 @@ use warp::{hyper::StatusCode, Filter, Reply};
         (status = NOT_FOUND, description = "Requested transaction not found."),
         (status = CONFLICT, description = "Vote has already been cast."),
         (status = BAD_REQUEST, description = "Invalid vote."),
-        (status = FORBIDDEN, description = "Voting period has ended.")
-    )
+        (status = UNAUTHORIZED, description = "Missing or unknown API token, or vote signature, delegator or nonce is invalid."),
+        (status = FORBIDDEN, description = "API token may not vote, or voting period has ended.")
+    ),
+    security(("bearer_token" = []))
 )]
-async fn vote(vote_request: VoteRequest, pool: SqlitePool) -> Result<Box<dyn Reply>, Infallible> {
-    if let Ok(mut vote) = get_vote_by_id(&vote_request.txid, &pool).await {
-        let vote_choice = vote_request.vote_choice;
-        if vote.vote_choice.is_some() {
-            return Ok(Box::new(StatusCode::CONFLICT));
-        }
-        if vote.vote_tally.vote_status != VoteStatus::Pending {
-            return Ok(Box::new(StatusCode::FORBIDDEN));
-        }
-        vote.vote_choice = Some(vote_choice);
-        // TODO: update consensus correctly
-        vote.vote_tally.current_consensus += 1;
-        if add_vote(&vote, &pool).await.is_ok() {
+async fn vote(
+    principal: Principal,
+    vote_request: VoteRequest,
+    pool: SqlitePool,
+) -> Result<Box<dyn Reply>, Infallible> {
+    let txid = vote_request.txid.clone();
+    match cast_vote(vote_request, &pool).await {
+        Ok(vote_response) => {
+            info!(
+                target: "audit",
+                principal = %principal.name,
+                "Vote cast on transaction {}: {}",
+                txid,
+                vote_response.vote_choice
+            );
             Ok(Box::new(warp::reply::with_status(
-                warp::reply::json(&VoteResponse {
-                    vote_choice,
-                    vote_tally: vote.vote_tally,
-                }),
+                warp::reply::json(&vote_response),
                 StatusCode::OK,
             )))
-        } else {
-            Ok(Box::new(StatusCode::INTERNAL_SERVER_ERROR))
         }
-    } else {
-        Ok(Box::new(StatusCode::NOT_FOUND))
+        Err(status) => Ok(Box::new(status)),
+    }
+}
+
+/// Record a delegator's vote and update the transaction's tally.
+async fn cast_vote(
+    vote_request: VoteRequest,
+    pool: &SqlitePool,
+) -> Result<VoteResponse, StatusCode> {
+    let internal_error = |e: crate::db::Error| {
+        error!("Failed to cast vote: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let config = get_config(pool).await.map_err(internal_error)?;
+    // Reading and updating the ballot in one transaction keeps concurrent votes from
+    // tallying over each other
+    let mut tx = begin_write(pool).await.map_err(internal_error)?;
+    // Close any ballots whose deadline has passed before accepting new votes
+    if let Some(block_height) = get_current_block_height(&mut tx)
+        .await
+        .map_err(internal_error)?
+    {
+        close_expired_votes(block_height, config.expired_vote_status(), &mut tx)
+            .await
+            .map_err(internal_error)?;
+    }
+    let mut vote = match get_vote_by_id(&vote_request.txid, &mut tx).await {
+        Ok(vote) => vote,
+        Err(crate::db::Error::SqlxError(sqlx::Error::RowNotFound)) => {
+            return Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => return Err(internal_error(e)),
+    };
+    if vote.vote_tally.vote_status != VoteStatus::Pending {
+        // Keep the ballots closed above even though this vote is refused
+        tx.commit().await.map_err(|e| internal_error(e.into()))?;
+        return Err(StatusCode::FORBIDDEN);
+    }
+    // Only accept votes signed by an eligible delegator with a fresh nonce
+    let delegator = vote_request.verify().ok_or(StatusCode::UNAUTHORIZED)?;
+    let vote_weight = config
+        .voting_weight(&delegator)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let signing_delegator = delegator.to_string();
+    if !advance_delegator_nonce(&signing_delegator, vote_request.nonce, &mut tx)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let mut votes = get_delegator_votes(&vote.txid, &mut tx)
+        .await
+        .map_err(internal_error)?;
+    if votes
+        .iter()
+        .any(|vote| vote.signing_delegator == signing_delegator)
+    {
+        return Err(StatusCode::CONFLICT);
+    }
+    let vote_choice = vote_request.vote_choice;
+    let delegator_vote = DelegatorVote {
+        txid: vote.txid.clone(),
+        signing_delegator,
+        vote_choice,
+        vote_weight,
+    };
+    add_delegator_vote(&delegator_vote, &mut tx)
+        .await
+        .map_err(internal_error)?;
+    votes.push(delegator_vote);
+
+    if delegator == config.delegate_public_key {
+        vote.vote_choice = Some(vote_choice);
     }
+    vote.vote_tally.tally(&votes, config.total_voting_weight());
+    add_vote(&vote, &mut tx).await.map_err(internal_error)?;
+    tx.commit().await.map_err(|e| internal_error(e.into()))?;
+    Ok(VoteResponse {
+        vote_choice,
+        vote_tally: vote.vote_tally,
+    })
 }
 
 /// Route for voting to approve or reject a specific transaction.
@@ -56,11 +144,243 @@ async fn vote(vote_request: VoteRequest, pool: SqlitePool) -> Result<Box<dyn Rep
 ///   The Warp filter for the get_transactions_route endpoint for routing HTTP requests.
 pub fn vote_route(
     pool: SqlitePool,
+    auth: AuthMode,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::post()
         .and(warp::path!("v1" / "vote"))
         .and(warp::path::end())
+        .and(with_role(pool.clone(), auth, Role::Voter))
         .and(json_body::<VoteRequest>())
         .and(with_pool(pool))
         .and_then(vote)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        db::{
+            self, init_pool,
+            transaction::{add_transaction, set_burn_block_height},
+        },
+        transaction::Transaction,
+        vote::VoteChoice,
+    };
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use std::collections::BTreeMap;
+
+    /// The signer's key and the keys of the two delegators it signs on behalf of
+    const TEST_SECRET_KEYS: [[u8; 32]; 3] = [[1; 32], [2; 32], [3; 32]];
+
+    fn secret_key(index: usize) -> SecretKey {
+        SecretKey::from_slice(&TEST_SECRET_KEYS[index]).expect("Failed to create secret key")
+    }
+
+    fn public_key(index: usize) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &secret_key(index))
+    }
+
+    /// Initialize a database whose signer delegates for two others, with a total voting weight of 5.
+    async fn init_db() -> SqlitePool {
+        let pool = init_pool(None)
+            .await
+            .expect("Failed to initialize a new database pool.");
+        let mut config = Config::new(secret_key(0));
+        config.delegator_public_keys = vec![public_key(1), public_key(2)];
+        config.delegator_weights = BTreeMap::from([(public_key(1).to_string(), 3)]);
+        db::config::update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to add configuration to database.");
+        pool
+    }
+
+    async fn cast(
+        pool: &SqlitePool,
+        vote_request: &VoteRequest,
+    ) -> (StatusCode, Option<VoteResponse>) {
+        let api = warp::test::request()
+            .path("/v1/vote")
+            .method("POST")
+            .json(vote_request)
+            .reply(&vote_route(pool.clone(), AuthMode::Disabled))
+            .await;
+        (api.status(), serde_json::from_slice(api.body()).ok())
+    }
+
+    fn signed(txid: &str, choice: VoteChoice, nonce: u64, signer: usize) -> VoteRequest {
+        VoteRequest::new(txid.to_string(), choice, nonce, &secret_key(signer))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_weighted_vote() {
+        let pool = init_db().await;
+        let tx = Transaction::default();
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+
+        let (status, response) = cast(&pool, &signed(&tx.txid, VoteChoice::Approve, 1, 1)).await;
+        assert_eq!(status, StatusCode::OK);
+        let tally = response
+            .expect("failed to deserialize vote response")
+            .vote_tally;
+        assert_eq!(tally.current_consensus, 60);
+        assert_eq!(tally.vote_status, VoteStatus::Pending);
+
+        let (status, _) = cast(&pool, &signed(&tx.txid, VoteChoice::Reject, 2, 1)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, response) = cast(&pool, &signed(&tx.txid, VoteChoice::Approve, 1, 0)).await;
+        assert_eq!(status, StatusCode::OK);
+        let tally = response
+            .expect("failed to deserialize vote response")
+            .vote_tally;
+        assert_eq!(tally.current_consensus, 80);
+        assert_eq!(tally.vote_status, VoteStatus::Approved);
+
+        let vote = get_vote_by_id(&tx.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_choice, Some(VoteChoice::Approve));
+        assert_eq!(vote.vote_tally, tally);
+
+        let (status, _) = cast(&pool, &signed(&tx.txid, VoteChoice::Reject, 1, 2)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_invalid_vote() {
+        let pool = init_db().await;
+        let tx = Transaction::default();
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+
+        let (status, _) = cast(&pool, &signed("unknown", VoteChoice::Approve, 1, 0)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // A vote claiming to be from a delegator but not signed by it
+        let mut forged = signed(&tx.txid, VoteChoice::Approve, 1, 0);
+        forged.signing_delegator = public_key(1).to_string();
+        let (status, _) = cast(&pool, &forged).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // A validly signed vote from a key that is not a delegator
+        let stranger = SecretKey::from_slice(&[4; 32]).expect("Failed to create secret key");
+        let vote_request = VoteRequest::new(tx.txid.clone(), VoteChoice::Approve, 1, &stranger);
+        let (status, _) = cast(&pool, &vote_request).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_replayed_vote() {
+        let pool = init_db().await;
+        let tx = Transaction::default();
+        let other_tx = Transaction {
+            txid: "1".repeat(64),
+            ..Default::default()
+        };
+        for tx in [&tx, &other_tx] {
+            add_transaction(&pool, tx)
+                .await
+                .expect("failed to add transaction");
+        }
+
+        let (status, _) = cast(&pool, &signed(&tx.txid, VoteChoice::Reject, 5, 2)).await;
+        assert_eq!(status, StatusCode::OK);
+        // Nonces must keep increasing across transactions
+        let (status, _) = cast(&pool, &signed(&other_tx.txid, VoteChoice::Reject, 5, 2)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = cast(&pool, &signed(&other_tx.txid, VoteChoice::Reject, 6, 2)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_expired_vote() {
+        let pool = init_db().await;
+        let tx = Transaction {
+            transaction_block_height: Some(1),
+            transaction_deadline_block_height: 5,
+            ..Default::default()
+        };
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+        // A transaction mined past the first one's deadline closes its ballot
+        let later_tx = Transaction {
+            txid: "1".repeat(64),
+            transaction_block_height: Some(6),
+            transaction_deadline_block_height: 10,
+            ..Default::default()
+        };
+        add_transaction(&pool, &later_tx)
+            .await
+            .expect("failed to add transaction");
+
+        let (status, _) = cast(&pool, &signed(&tx.txid, VoteChoice::Approve, 1, 0)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let vote = get_vote_by_id(&tx.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::NoConsensus);
+
+        let (status, _) = cast(&pool, &signed(&later_tx.txid, VoteChoice::Approve, 1, 0)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(1000)]
+    async fn test_vote_after_burn_deadline() {
+        let pool = init_db().await;
+        let mut config = db::config::get_config(&pool).await.unwrap();
+        config.expired_vote_choice = Some(VoteChoice::Reject);
+        db::config::update_config(&pool, &config, "test")
+            .await
+            .expect("Failed to update configuration.");
+        let tx = Transaction {
+            transaction_block_height: Some(1),
+            transaction_deadline_block_height: 5,
+            ..Default::default()
+        };
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+
+        // The tracked burn block height closes the ballot without any later transaction
+        set_burn_block_height(&pool, 6).await.unwrap();
+        let (status, _) = cast(&pool, &signed(&tx.txid, VoteChoice::Approve, 1, 0)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let vote = get_vote_by_id(&tx.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::Rejected);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ntest::timeout(2000)]
+    async fn test_concurrent_votes() {
+        let pool = init_db().await;
+        let tx = Transaction::default();
+        add_transaction(&pool, &tx)
+            .await
+            .expect("failed to add transaction");
+
+        // Every vote has to be tallied, whichever order the ballots are read and written in
+        let approve = signed(&tx.txid, VoteChoice::Approve, 1, 1);
+        let reject = signed(&tx.txid, VoteChoice::Reject, 1, 0);
+        let other_reject = signed(&tx.txid, VoteChoice::Reject, 1, 2);
+        let (first, second, third) = tokio::join!(
+            cast(&pool, &approve),
+            cast(&pool, &reject),
+            cast(&pool, &other_reject),
+        );
+        assert_eq!(first.0, StatusCode::OK);
+        assert_eq!(second.0, StatusCode::OK);
+        assert_eq!(third.0, StatusCode::OK);
+
+        let votes = get_delegator_votes(&tx.txid, &pool).await.unwrap();
+        assert_eq!(votes.len(), 3);
+        let vote = get_vote_by_id(&tx.txid, &pool).await.unwrap();
+        assert_eq!(vote.vote_tally.vote_status, VoteStatus::NoConsensus);
+        assert_eq!(vote.vote_tally.current_consensus, 60);
+    }
+}
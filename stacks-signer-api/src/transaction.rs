@@ -1,8 +1,13 @@
 use parse_display::{Display, FromStr};
 use serde::{Deserialize, Serialize};
-use utoipa::{ToResponse, ToSchema};
+use utoipa::{IntoParams, ToResponse, ToSchema};
 
-use crate::vote::{VoteChoice, VoteMechanism, VoteTally};
+use crate::vote::{VoteChoice, VoteMechanism, VoteStatus, VoteTally};
+
+/// The number of transactions returned per page when no limit is given
+pub const DEFAULT_TRANSACTION_LIMIT: u64 = 100;
+/// The maximum number of transactions returned per page
+pub const MAX_TRANSACTION_LIMIT: u64 = 1000;
 
 #[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, ToSchema)]
 /// The address of either a credit or debit transaction
@@ -13,7 +18,44 @@ pub enum TransactionAddress {
     Stacks(String),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize, ToSchema, Display, FromStr)]
+impl TransactionAddress {
+    /// Create an address on the given chain
+    pub fn new(chain: AddressChain, address: String) -> Self {
+        match chain {
+            AddressChain::Bitcoin => TransactionAddress::Bitcoin(address),
+            AddressChain::Stacks => TransactionAddress::Stacks(address),
+        }
+    }
+
+    /// The address, regardless of which chain it belongs to
+    pub fn address(&self) -> &str {
+        match self {
+            TransactionAddress::Bitcoin(address) | TransactionAddress::Stacks(address) => address,
+        }
+    }
+
+    /// The chain the address belongs to
+    pub fn chain(&self) -> AddressChain {
+        match self {
+            TransactionAddress::Bitcoin(_) => AddressChain::Bitcoin,
+            TransactionAddress::Stacks(_) => AddressChain::Stacks,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+/// The chain a transaction address belongs to
+pub enum AddressChain {
+    /// The Bitcoin chain
+    Bitcoin,
+    /// The Stacks chain
+    Stacks,
+}
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Deserialize, Serialize, ToSchema, Display, FromStr,
+)]
 #[serde(rename_all = "lowercase")]
 #[display(style = "lowercase")]
 /// The type of transaction being requested
@@ -89,3 +131,71 @@ pub struct TransactionResponse {
     /// The vote mechanism used
     pub vote_mechanism: VoteMechanism,
 }
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+/// The field transactions are sorted by
+pub enum TransactionSort {
+    /// The transaction ID
+    #[default]
+    Txid,
+    /// The height of the Bitcoin block that mined the transaction. Unmined transactions sort first.
+    BlockHeight,
+    /// The height of the Bitcoin block at which a vote is due
+    DeadlineBlockHeight,
+    /// The amount of sats in the transaction
+    Amount,
+    /// The amount of sats in the fee subsidy
+    Fees,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+/// The direction transactions are sorted in
+pub enum SortOrder {
+    /// Ascending order
+    #[default]
+    Asc,
+    /// Descending order
+    Desc,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Query parameters for the transaction list
+pub struct TransactionQuery {
+    /// The transaction kind to filter by.
+    pub kind: Option<TransactionKind>,
+    /// The transaction status to filter by.
+    pub status: Option<VoteStatus>,
+    /// The minimum transaction amount in sats.
+    pub min_amount: Option<u64>,
+    /// The maximum transaction amount in sats.
+    pub max_amount: Option<u64>,
+    /// An originator, debit or credit address the transaction must touch.
+    pub address: Option<String>,
+    /// The minimum Bitcoin block height the transaction was mined at.
+    pub min_block_height: Option<u64>,
+    /// The maximum Bitcoin block height the transaction was mined at.
+    pub max_block_height: Option<u64>,
+    /// The field to sort by. Ties are broken by transaction ID.
+    pub sort: Option<TransactionSort>,
+    /// The sort direction.
+    pub order: Option<SortOrder>,
+    /// The `next_cursor` of the previous page, to continue listing after it.
+    pub cursor: Option<String>,
+    /// The limit of transactions per page.
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, ToResponse, ToSchema)]
+#[response(description = "A page of transactions matching the query and their total count")]
+/// A page of transactions returned from a transaction list request
+pub struct TransactionPage {
+    /// The transactions of this page
+    pub transactions: Vec<TransactionResponse>,
+    /// The number of transactions matching the query's filters across all pages
+    pub total: u64,
+    /// The cursor of the next page, or None if this is the last page
+    pub next_cursor: Option<String>,
+}
@@ -1,5 +1,7 @@
 use parse_display::{Display, FromStr};
+use secp256k1::{ecdsa::Signature, hashes::sha256, Message, PublicKey, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr as _;
 use utoipa::{ToResponse, ToSchema};
 
 #[derive(FromStr, Display, Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, ToSchema)]
@@ -48,6 +50,47 @@ pub struct VoteRequest {
     pub signing_delegator: String,
     /// The vote choice.
     pub vote_choice: VoteChoice,
+    /// A number that must increase with every vote the delegator casts.
+    pub nonce: u64,
+    /// The hex encoded compact ECDSA signature of the vote by the signer delegator.
+    pub signature: String,
+}
+
+impl VoteRequest {
+    /// Create a vote request signed with the given delegator's secret key.
+    pub fn new(txid: String, vote_choice: VoteChoice, nonce: u64, secret_key: &SecretKey) -> Self {
+        let secp = Secp256k1::signing_only();
+        let mut request = Self {
+            txid,
+            signing_delegator: PublicKey::from_secret_key(&secp, secret_key).to_string(),
+            vote_choice,
+            nonce,
+            signature: String::new(),
+        };
+        let signature = secp.sign_ecdsa(&request.message(), secret_key);
+        request.signature = hex::encode(signature.serialize_compact());
+        request
+    }
+
+    /// The message signed by the delegator: the SHA-256 hash of
+    /// `"{txid}:{vote_choice}:{signing_delegator}:{nonce}"`.
+    pub fn message(&self) -> Message {
+        let data = format!(
+            "{}:{}:{}:{}",
+            self.txid, self.vote_choice, self.signing_delegator, self.nonce
+        );
+        Message::from_hashed_data::<sha256::Hash>(data.as_bytes())
+    }
+
+    /// Verify that the vote was signed by the signer delegator, returning its public key.
+    pub fn verify(&self) -> Option<PublicKey> {
+        let public_key = PublicKey::from_str(&self.signing_delegator).ok()?;
+        let signature = Signature::from_compact(&hex::decode(&self.signature).ok()?).ok()?;
+        Secp256k1::verification_only()
+            .verify_ecdsa(&self.message(), &signature, &public_key)
+            .ok()?;
+        Some(public_key)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, ToResponse, ToSchema)]
@@ -70,6 +113,58 @@ pub struct VoteTally {
     pub vote_status: VoteStatus,
 }
 
+impl VoteTally {
+    /// Recompute the tally from the delegator votes cast so far.
+    ///
+    /// `total_weight` is the combined voting weight of every eligible delegator, whether or
+    /// not they have voted yet. A pending ballot is decided once either choice holds at least
+    /// `target_consensus` percent of the total weight, or marked as having no consensus once
+    /// neither choice can reach it with the weight still outstanding.
+    pub fn tally(&mut self, votes: &[DelegatorVote], total_weight: u64) {
+        let weight_of = |choice: VoteChoice| -> u64 {
+            votes
+                .iter()
+                .filter(|vote| vote.vote_choice == choice)
+                .map(|vote| vote.vote_weight)
+                .sum()
+        };
+        let approve = weight_of(VoteChoice::Approve);
+        let reject = weight_of(VoteChoice::Reject);
+        let total = total_weight.max(approve + reject);
+        if total == 0 {
+            return;
+        }
+        self.current_consensus = approve.max(reject) * 100 / total;
+        if self.vote_status != VoteStatus::Pending {
+            return;
+        }
+        let reaches_target = |weight: u64| weight * 100 >= self.target_consensus * total;
+        let outstanding = total - approve - reject;
+        self.vote_status = if reaches_target(approve) {
+            VoteStatus::Approved
+        } else if reaches_target(reject) {
+            VoteStatus::Rejected
+        } else if !reaches_target(approve + outstanding) && !reaches_target(reject + outstanding) {
+            VoteStatus::NoConsensus
+        } else {
+            VoteStatus::Pending
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+/// A single delegator's vote on a transaction.
+pub struct DelegatorVote {
+    /// The voted on hexadecimal transaction ID.
+    pub txid: String,
+    /// The public key of the delegator that cast the vote
+    pub signing_delegator: String,
+    /// The delegator's vote choice.
+    pub vote_choice: VoteChoice,
+    /// The voting weight of the delegator at the time the vote was cast
+    pub vote_weight: u64,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
 /// The current vote info for a transaction
 pub struct Vote {
@@ -77,8 +172,105 @@ pub struct Vote {
     pub txid: String,
     /// The vote tally.
     pub vote_tally: VoteTally,
-    /// The vote choice.
+    /// The signer's own vote choice, if it has voted.
     pub vote_choice: Option<VoteChoice>,
     /// The current vote mechanism of the vote choice
     pub vote_mechanism: VoteMechanism,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_SECRET_KEY: [u8; 32] = [1; 32];
+
+    #[test]
+    fn vote_request_signature() {
+        let secret_key = SecretKey::from_slice(&TEST_SECRET_KEY).unwrap();
+        let request = VoteRequest::new("txid".to_string(), VoteChoice::Approve, 1, &secret_key);
+        let public_key = request.verify().expect("failed to verify signed vote");
+        assert_eq!(public_key.to_string(), request.signing_delegator);
+
+        // Changing any signed field invalidates the signature
+        let mut tampered = request.clone();
+        tampered.vote_choice = VoteChoice::Reject;
+        assert!(tampered.verify().is_none());
+        let mut tampered = request.clone();
+        tampered.nonce = 2;
+        assert!(tampered.verify().is_none());
+        let mut tampered = request.clone();
+        tampered.txid = "other".to_string();
+        assert!(tampered.verify().is_none());
+        let mut tampered = request;
+        tampered.signature = "00".to_string();
+        assert!(tampered.verify().is_none());
+    }
+
+    fn pending_tally() -> VoteTally {
+        VoteTally {
+            target_consensus: 70,
+            current_consensus: 0,
+            vote_status: VoteStatus::Pending,
+        }
+    }
+
+    fn delegator_vote(
+        signing_delegator: &str,
+        vote_choice: VoteChoice,
+        vote_weight: u64,
+    ) -> DelegatorVote {
+        DelegatorVote {
+            txid: "txid".to_string(),
+            signing_delegator: signing_delegator.to_string(),
+            vote_choice,
+            vote_weight,
+        }
+    }
+
+    #[test]
+    fn tally_weights_votes() {
+        let mut tally = pending_tally();
+        let mut votes = vec![delegator_vote("a", VoteChoice::Approve, 3)];
+        tally.tally(&votes, 10);
+        assert_eq!(tally.current_consensus, 30);
+        assert_eq!(tally.vote_status, VoteStatus::Pending);
+
+        votes.push(delegator_vote("b", VoteChoice::Approve, 4));
+        tally.tally(&votes, 10);
+        assert_eq!(tally.current_consensus, 70);
+        assert_eq!(tally.vote_status, VoteStatus::Approved);
+    }
+
+    #[test]
+    fn tally_rejects() {
+        let mut tally = pending_tally();
+        let votes = vec![
+            delegator_vote("a", VoteChoice::Approve, 1),
+            delegator_vote("b", VoteChoice::Reject, 8),
+        ];
+        tally.tally(&votes, 10);
+        assert_eq!(tally.current_consensus, 80);
+        assert_eq!(tally.vote_status, VoteStatus::Rejected);
+    }
+
+    #[test]
+    fn tally_detects_deadlock() {
+        let mut tally = pending_tally();
+        let votes = vec![
+            delegator_vote("a", VoteChoice::Approve, 4),
+            delegator_vote("b", VoteChoice::Reject, 4),
+        ];
+        tally.tally(&votes, 10);
+        assert_eq!(tally.current_consensus, 40);
+        assert_eq!(tally.vote_status, VoteStatus::NoConsensus);
+    }
+
+    #[test]
+    fn tally_keeps_decided_status() {
+        let mut tally = pending_tally();
+        tally.vote_status = VoteStatus::Rejected;
+        tally.tally(&[delegator_vote("a", VoteChoice::Approve, 10)], 10);
+        assert_eq!(tally.current_consensus, 100);
+        assert_eq!(tally.vote_status, VoteStatus::Rejected);
+    }
+}
@@ -21,6 +21,12 @@ pub enum Command {
         /// Config file path
         #[arg(short, long)]
         config: String,
+        /// URL of the signer API whose votes decide which transactions get signed
+        #[arg(long)]
+        signer_api_url: Option<String>,
+        /// API token of the signer API, if it requires authentication
+        #[arg(long, env = "SIGNER_API_TOKEN", requires = "signer_api_url")]
+        signer_api_token: Option<String>,
     },
     /// Generate Secp256k1 Private Key
     PrivateKey(Secp256k1),
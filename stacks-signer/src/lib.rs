@@ -4,6 +4,8 @@ pub mod cli;
 pub mod secp256k1;
 /// Module for signer operations
 pub mod signer;
+/// Module for consulting the votes recorded by the signer API
+pub mod signer_api;
 
 // set via _compile-time_ envars
 const GIT_BRANCH: Option<&'static str> = option_env!("GIT_BRANCH");
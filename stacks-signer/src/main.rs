@@ -15,11 +15,25 @@ async fn main() {
 
     // Determine what action the caller wishes to perform
     match cli.command {
-        Command::Run { id, config } => {
+        Command::Run {
+            id,
+            config,
+            signer_api_url,
+            signer_api_token,
+        } => {
             //TODO: getConf from sBTC contract instead
             match Config::from_path(&config) {
                 Ok(config) => {
                     let mut signer = Signer::new(config, id);
+                    if let Some(url) = signer_api_url {
+                        info!("Signing only transactions approved by {}", url);
+                        signer = match signer.with_signer_api(url, signer_api_token) {
+                            Ok(signer) => signer,
+                            Err(e) => {
+                                panic!("An error occurred creating the signer API client: {}", e)
+                            }
+                        };
+                    }
                     info!("{} signer id #{}", stacks_signer::version(), id); // sign-on message
                     if let Err(e) = signer.start_p2p_async().await {
                         panic!("An error occurred on the P2P Network: {}", e);
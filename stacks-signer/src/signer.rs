@@ -2,6 +2,8 @@ use frost_signer::config::Config;
 use frost_signer::net::{HttpNet, HttpNetListen};
 use frost_signer::signer::{Error as SignerError, Signer as FrostSigner};
 
+use crate::signer_api::SignerApi;
+
 #[derive(Clone)]
 pub struct Signer {
     frost_signer: FrostSigner,
@@ -15,8 +17,22 @@ impl Signer {
         }
     }
 
+    /// Only contribute signature shares for transactions approved in the signer API at the given url
+    pub fn with_signer_api(
+        mut self,
+        url: impl Into<String>,
+        token: Option<String>,
+    ) -> Result<Self, reqwest::Error> {
+        let mut signer_api = SignerApi::new(url)?;
+        if let Some(token) = token {
+            signer_api = signer_api.with_token(token);
+        }
+        self.frost_signer = self.frost_signer.with_approver(signer_api);
+        Ok(self)
+    }
+
     pub async fn start_p2p_async(&mut self) -> Result<(), SignerError> {
-        let net: HttpNet = HttpNet::new(self.frost_signer.config.http_relay_url.clone());
+        let net: HttpNet = HttpNet::from_config(&self.frost_signer.config)?;
         let net_queue = HttpNetListen::new(net.clone(), vec![]);
         self.frost_signer.start_p2p_async(net_queue).await
     }
@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bitcoin::{
+    consensus::{deserialize, encode::Error as EncodeError},
+    hashes::Hash,
+    psbt::Prevouts,
+    util::sighash::{Error as SighashError, SighashCache},
+    Address, SchnorrSighashType, Transaction as BitcoinTransaction, TxOut, Txid,
+};
+use frost_signer::{
+    signer::SignApprover,
+    signing_round::{SignatureShareRequest, SigningContext},
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// How long to wait for the signer API before refusing to sign
+pub const SIGNER_API_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The output of a peg-out request that pays the fulfillment fee to the peg wallet
+const FULFILLMENT_FEE_VOUT: u32 = 2;
+
+/// Reasons for refusing to sign a message
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to decode the transaction: {0}")]
+    DecodeError(#[from] EncodeError),
+    #[error("Failed to compute the sighash: {0}")]
+    SighashError(#[from] SighashError),
+    #[error("The message is not the sighash of input {0}")]
+    SighashMismatch(u64),
+    #[error("The transaction does not spend the fulfillment fee of {0}")]
+    MissingFulfillmentFee(String),
+    #[error("The transaction does not pay {0} sats to {1}")]
+    MissingPayment(u64, String),
+    #[error("Invalid Bitcoin address: {0}")]
+    InvalidAddress(String),
+    #[error("Transaction {0} was already fulfilled by {1}")]
+    AlreadySigned(String, Txid),
+    #[error("Transaction {0} has no vote")]
+    UnknownTransaction(String),
+    #[error("Vote status is {0:?}")]
+    NotApproved(VoteStatus),
+    #[error("Signer API error: {0}")]
+    SignerApiError(#[from] reqwest::Error),
+}
+
+/// The status of a transaction vote as reported by the signer API
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteStatus {
+    Pending,
+    Approved,
+    Rejected,
+    NoConsensus,
+}
+
+#[derive(Deserialize, Debug)]
+struct VoteTally {
+    vote_status: VoteStatus,
+}
+
+#[derive(Deserialize, Debug)]
+enum TransactionAddress {
+    Bitcoin(String),
+    Stacks(String),
+}
+
+/// The subset of a signer API transaction needed to check what gets signed for it
+#[derive(Deserialize, Debug)]
+struct Transaction {
+    transaction_amount: u64,
+    transaction_credit_address: TransactionAddress,
+}
+
+/// The subset of the signer API transaction response needed to decide on signing
+#[derive(Deserialize, Debug)]
+struct TransactionResponse {
+    transaction: Transaction,
+    vote_tally: VoteTally,
+}
+
+/// Client for the vote decisions recorded by a signer API instance
+#[derive(Clone, Debug)]
+pub struct SignerApi {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    /// The Bitcoin transaction signed for each sBTC transaction
+    signed: Arc<Mutex<HashMap<String, Txid>>>,
+}
+
+impl SignerApi {
+    pub fn new(url: impl Into<String>) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(SIGNER_API_TIMEOUT)
+                .build()?,
+            url: url.into().trim_end_matches('/').to_string(),
+            token: None,
+            signed: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Authenticate requests with the given signer API token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Retrieve the vote status of a transaction, or None if the signer API does not know it
+    pub async fn vote_status(&self, txid: &str) -> Result<Option<VoteStatus>, reqwest::Error> {
+        Ok(self
+            .transaction(txid)
+            .await?
+            .map(|transaction| transaction.vote_tally.vote_status))
+    }
+
+    async fn transaction(&self, txid: &str) -> Result<Option<TransactionResponse>, reqwest::Error> {
+        let mut request = self
+            .client
+            .get(format!("{}/v1/transactions/{}", self.url, txid));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Check the message signs for a Bitcoin transaction that carries out an approved vote
+    async fn check(&self, context: &SigningContext, message: &[u8]) -> Result<(), Error> {
+        let transaction = verify_sighash(context, message)?;
+        let response = self
+            .transaction(&context.txid)
+            .await?
+            .ok_or_else(|| Error::UnknownTransaction(context.txid.clone()))?;
+        if response.vote_tally.vote_status != VoteStatus::Approved {
+            return Err(Error::NotApproved(response.vote_tally.vote_status));
+        }
+        verify_payment(&transaction, &response.transaction)?;
+        self.record_signed(&context.txid, transaction.txid())
+    }
+
+    /// Record the Bitcoin transaction signed for an sBTC transaction. Each of its inputs may be
+    /// signed, but no other Bitcoin transaction may be signed for the same sBTC transaction.
+    fn record_signed(&self, txid: &str, signed_txid: Txid) -> Result<(), Error> {
+        let mut signed = self
+            .signed
+            .lock()
+            .expect("signed transactions lock poisoned");
+        match signed.get(txid) {
+            Some(previous) if *previous != signed_txid => {
+                Err(Error::AlreadySigned(txid.to_string(), *previous))
+            }
+            _ => {
+                signed.insert(txid.to_string(), signed_txid);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Decode the Bitcoin transaction of a signing context, checking the message is the taproot key
+/// spend sighash of its input and that it spends the fulfillment fee of the sBTC transaction.
+fn verify_sighash(context: &SigningContext, message: &[u8]) -> Result<BitcoinTransaction, Error> {
+    let transaction: BitcoinTransaction = deserialize(&context.transaction)?;
+    let prevouts = context
+        .prevouts
+        .iter()
+        .map(|prevout| deserialize(prevout))
+        .collect::<Result<Vec<TxOut>, _>>()?;
+    let index = usize::try_from(context.input_index)
+        .map_err(|_| Error::SighashMismatch(context.input_index))?;
+    let sighash = SighashCache::new(&transaction).taproot_key_spend_signature_hash(
+        index,
+        &Prevouts::All(&prevouts),
+        SchnorrSighashType::Default,
+    )?;
+    if sighash.into_inner()[..] != *message {
+        return Err(Error::SighashMismatch(context.input_index));
+    }
+    let spends_fulfillment_fee = transaction.input.iter().any(|input| {
+        input.previous_output.txid.to_string() == context.txid
+            && input.previous_output.vout == FULFILLMENT_FEE_VOUT
+    });
+    if !spends_fulfillment_fee {
+        return Err(Error::MissingFulfillmentFee(context.txid.clone()));
+    }
+    Ok(transaction)
+}
+
+/// Check the Bitcoin transaction pays the voted amount to the voted recipient
+fn verify_payment(transaction: &BitcoinTransaction, voted: &Transaction) -> Result<(), Error> {
+    let recipient = match &voted.transaction_credit_address {
+        TransactionAddress::Bitcoin(address) => address,
+        TransactionAddress::Stacks(address) => return Err(Error::InvalidAddress(address.clone())),
+    };
+    let script_pubkey = Address::from_str(recipient)
+        .map_err(|_| Error::InvalidAddress(recipient.clone()))?
+        .script_pubkey();
+    if !transaction.output.iter().any(|output| {
+        output.value == voted.transaction_amount && output.script_pubkey == script_pubkey
+    }) {
+        return Err(Error::MissingPayment(
+            voted.transaction_amount,
+            recipient.clone(),
+        ));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl SignApprover for SignerApi {
+    /// Only sign the sighash of a Bitcoin transaction carrying out a transaction the signer's vote
+    /// has approved
+    async fn approve(&self, request: &SignatureShareRequest) -> bool {
+        let Some(context) = &request.context else {
+            warn!("SignShareRequest does not reference a transaction. Refusing to sign.");
+            return false;
+        };
+        match self.check(context, &request.message).await {
+            Ok(()) => {
+                info!("Transaction {} approved. Signing.", context.txid);
+                true
+            }
+            Err(Error::SignerApiError(e)) => {
+                warn!(
+                    "Failed to retrieve the vote for transaction {}: {}. Refusing to sign.",
+                    context.txid, e
+                );
+                false
+            }
+            Err(e) => {
+                info!(
+                    "Transaction {} not signed: {}. Refusing to sign.",
+                    context.txid, e
+                );
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::{consensus::serialize, OutPoint, PackedLockTime, TxIn};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const RECIPIENT: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn peg_out_txid() -> Txid {
+        Txid::from_str(&"1".repeat(64)).unwrap()
+    }
+
+    /// A fulfillment of the peg-out request paying `amount` to the recipient
+    fn fulfillment(amount: u64) -> BitcoinTransaction {
+        BitcoinTransaction {
+            version: 2,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: peg_out_txid(),
+                    vout: FULFILLMENT_FEE_VOUT,
+                },
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey: Address::from_str(RECIPIENT).unwrap().script_pubkey(),
+            }],
+        }
+    }
+
+    fn sign_share_request(transaction: &BitcoinTransaction) -> SignatureShareRequest {
+        let prevouts = vec![TxOut {
+            value: 10,
+            script_pubkey: Address::from_str(RECIPIENT).unwrap().script_pubkey(),
+        }];
+        let sighash = SighashCache::new(transaction)
+            .taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&prevouts),
+                SchnorrSighashType::Default,
+            )
+            .unwrap();
+        SignatureShareRequest {
+            dkg_id: 0,
+            sign_id: 0,
+            correlation_id: 0,
+            nonce_responses: vec![],
+            message: sighash.into_inner().to_vec(),
+            context: Some(SigningContext {
+                txid: peg_out_txid().to_string(),
+                transaction: serialize(transaction),
+                prevouts: prevouts.iter().map(serialize).collect(),
+                input_index: 0,
+            }),
+        }
+    }
+
+    /// Serve the given vote status of a 1000 sat peg-out to the recipient for `requests` requests
+    async fn stub_signer_api(vote_status: &str, requests: usize) -> SignerApi {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let body = format!(
+            r#"{{
+                "transaction": {{
+                    "txid": "{}",
+                    "transaction_amount": 1000,
+                    "transaction_credit_address": {{"Bitcoin": "{}"}}
+                }},
+                "vote_tally": {{"vote_status": "{}"}}
+            }}"#,
+            peg_out_txid(),
+            RECIPIENT,
+            vote_status
+        );
+        tokio::spawn(async move {
+            for _ in 0..requests {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 1024];
+                stream.read(&mut request).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        SignerApi::new(url).unwrap()
+    }
+
+    #[test]
+    fn parse_transaction_response() {
+        let json = r#"{
+            "transaction": {
+                "txid": "00",
+                "transaction_amount": 1000,
+                "transaction_credit_address": {"Bitcoin": "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"}
+            },
+            "vote_tally": {
+                "target_consensus": 70,
+                "current_consensus": 100,
+                "vote_status": "approved"
+            },
+            "vote_choice": "approve",
+            "vote_mechanism": "manual"
+        }"#;
+        let response: TransactionResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.vote_tally.vote_status, VoteStatus::Approved);
+        assert_eq!(response.transaction.transaction_amount, 1000);
+
+        let status: VoteStatus = serde_json::from_str(r#""noconsensus""#).unwrap();
+        assert_eq!(status, VoteStatus::NoConsensus);
+    }
+
+    #[tokio::test]
+    async fn refuse_without_transaction() {
+        let signer_api = SignerApi::new("http://127.0.0.1:1/").unwrap();
+        let mut request = sign_share_request(&fulfillment(1000));
+        request.context = None;
+        assert!(!signer_api.approve(&request).await);
+    }
+
+    #[tokio::test]
+    async fn refuse_when_unreachable() {
+        let signer_api = SignerApi::new("http://127.0.0.1:1/").unwrap();
+        assert!(
+            !signer_api
+                .approve(&sign_share_request(&fulfillment(1000)))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn sign_approved() {
+        let signer_api = stub_signer_api("approved", 3).await;
+        let request = sign_share_request(&fulfillment(1000));
+        assert!(signer_api.approve(&request).await);
+        // Every input of the same transaction may be signed
+        assert!(signer_api.approve(&request).await);
+        // But no other transaction for the same peg-out
+        let mut other = fulfillment(1000);
+        other.lock_time = PackedLockTime(1);
+        assert!(!signer_api.approve(&sign_share_request(&other)).await);
+    }
+
+    #[tokio::test]
+    async fn refuse_rejected() {
+        let signer_api = stub_signer_api("rejected", 1).await;
+        assert!(
+            !signer_api
+                .approve(&sign_share_request(&fulfillment(1000)))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn refuse_pending() {
+        let signer_api = stub_signer_api("pending", 1).await;
+        assert!(
+            !signer_api
+                .approve(&sign_share_request(&fulfillment(1000)))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn refuse_other_payment() {
+        let signer_api = stub_signer_api("approved", 1).await;
+        assert!(
+            !signer_api
+                .approve(&sign_share_request(&fulfillment(999)))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn refuse_message_other_than_sighash() {
+        // The message is checked before the signer API is consulted
+        let signer_api = stub_signer_api("approved", 0).await;
+        let mut request = sign_share_request(&fulfillment(1000));
+        request.message = vec![0; 32];
+        assert!(!signer_api.approve(&request).await);
+
+        let mut request = sign_share_request(&fulfillment(1000));
+        if let Some(context) = request.context.as_mut() {
+            context.txid = "2".repeat(64);
+        }
+        assert!(!signer_api.approve(&request).await);
+    }
+}
@@ -6,7 +6,22 @@ use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWr
 
 pub const PROTOCOL: &str = "HTTP/1.1";
 
+const HTTP_1_0: &str = "HTTP/1.0";
+
 const CONTENT_LENGTH: &str = "content-length";
+const TRANSFER_ENCODING: &str = "transfer-encoding";
+const CONNECTION: &str = "connection";
+
+const CHUNKED: &str = "chunked";
+const CLOSE: &str = "close";
+const KEEP_ALIVE: &str = "keep-alive";
+
+/// The longest line, including the `\r\n`, accepted in the head of a message or a chunk header.
+pub const MAX_LINE_LENGTH: usize = 8 * 1024;
+/// The most header fields, including chunked trailers, accepted in a message.
+pub const MAX_HEADERS: usize = 64;
+/// The largest content accepted in a message.
+pub const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
 
 pub trait AsyncReadBuf: AsyncBufRead + Unpin + Send {}
 impl<T: AsyncBufRead + Unpin + Send> AsyncReadBuf for T {}
@@ -22,13 +37,41 @@ pub trait Message: Sized {
         content: Vec<u8>,
     ) -> Result<Self, Error>;
     fn first_line(&self) -> Vec<String>;
+    fn protocol(&self) -> &str;
     fn headers(&self) -> &HashMap<String, String>;
     fn content(&self) -> &Vec<u8>;
 
+    /// Whether empty content still needs a `content-length` header. Without one, the content of
+    /// a response only ends when the connection is closed.
+    fn requires_content_length(&self) -> bool {
+        false
+    }
+
+    /// Whether the connection can be reused for another message after this one.
+    ///
+    /// See https://www.rfc-editor.org/rfc/rfc9112#section-9.3
+    fn keep_alive(&self) -> bool {
+        match self.headers().get(CONNECTION) {
+            Some(value) if has_token(value, CLOSE) => false,
+            Some(value) if has_token(value, KEEP_ALIVE) => true,
+            _ => self.protocol() != HTTP_1_0,
+        }
+    }
+
+    fn is_chunked(&self) -> bool {
+        is_chunked(self.headers())
+    }
+
     async fn read(i: &mut impl AsyncReadBuf) -> Result<Self, Error> {
         async fn read_line(i: &mut impl AsyncReadBuf) -> Result<String, Error> {
             let mut buf = Vec::new();
-            let _len = i.read_until(b'\r', &mut buf).await?;
+            let _len = (&mut *i)
+                .take(MAX_LINE_LENGTH as u64 - 1)
+                .read_until(b'\r', &mut buf)
+                .await?;
+            if buf.pop() != Some(b'\r') {
+                return err("HTTP line is too long or incomplete");
+            }
             if i.read_u8().await? != 10 {
                 return err("invalid HTTP line");
             }
@@ -37,6 +80,53 @@ pub trait Message: Sized {
                 .map_err(|err| Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
         }
 
+        // `count` is the number of header fields read so far, trailers count towards the same limit.
+        async fn read_headers(
+            i: &mut impl AsyncReadBuf,
+            headers: &mut HashMap<String, String>,
+            count: &mut usize,
+        ) -> Result<(), Error> {
+            loop {
+                let line = read_line(i).await?;
+
+                let line = line.trim();
+
+                if line.is_empty() {
+                    return Ok(());
+                }
+
+                *count += 1;
+                if *count > MAX_HEADERS {
+                    return err("too many HTTP headers");
+                }
+
+                let (name, value) = line.split_once(':').to_io_result()?;
+                headers.insert(name.to_lowercase(), value.trim().to_string());
+            }
+        }
+
+        async fn read_content(
+            i: &mut impl AsyncReadBuf,
+            content_length: usize,
+            buf: &mut Vec<u8>,
+        ) -> Result<(), Error> {
+            // `buf` never exceeds the limit, so this can't overflow like adding the lengths would.
+            if content_length > MAX_CONTENT_LENGTH - buf.len() {
+                return err("HTTP content is too large");
+            }
+            let len = (&mut *i)
+                .take(content_length as u64)
+                .read_to_end(buf)
+                .await?;
+            if len != content_length {
+                return Err(Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "incomplete HTTP content",
+                ));
+            }
+            Ok(())
+        }
+
         // read and parse the request line
         let first_line = read_line(i)
             .await?
@@ -45,34 +135,33 @@ pub trait Message: Sized {
             .map(str::to_string)
             .collect();
         // read and parse headers
-        let mut content_length = 0;
         let mut headers = HashMap::default();
-        loop {
-            let line = read_line(i).await?;
-
-            let line = line.trim();
-
-            if line.is_empty() {
-                break;
-            }
-
-            let (name, value) = {
-                let (name, value) = line.split_once(':').to_io_result()?;
-                (name.to_lowercase(), value.trim())
-            };
+        let mut header_count = 0;
+        read_headers(i, &mut headers, &mut header_count).await?;
+        let content_length = headers.remove(CONTENT_LENGTH);
 
-            if name == CONTENT_LENGTH {
-                content_length = value.parse().to_io_result()?;
-            } else {
-                headers.insert(name, value.to_string());
+        let mut buf = Vec::new();
+        if is_chunked(&headers) {
+            // See https://www.rfc-editor.org/rfc/rfc9112#section-7.1
+            loop {
+                let line = read_line(i).await?;
+                let size = line.split(';').next().unwrap_or_default().trim();
+                let size = usize::from_str_radix(size, 16).to_io_result()?;
+                if size == 0 {
+                    break;
+                }
+                read_content(i, size, &mut buf).await?;
+                if !read_line(i).await?.is_empty() {
+                    return err("invalid HTTP chunk");
+                }
             }
+            // trailers are merged into the headers.
+            read_headers(i, &mut headers, &mut header_count).await?;
+        } else if let Some(content_length) = content_length {
+            let content_length = content_length.parse().to_io_result()?;
+            read_content(i, content_length, &mut buf).await?;
         }
 
-        let mut buf = vec![0u8; content_length];
-
-        // Read content_length bytes into buf
-        let _len = AsyncReadExt::read_exact(i, &mut buf).await?;
-
         // return the message
         Self::parse(first_line, headers, buf)
     }
@@ -97,7 +186,21 @@ pub trait Message: Sized {
         }
         let content = self.content();
         let len = content.len();
-        if len > 0 {
+        if self.is_chunked() {
+            o.write_all(EOL).await?;
+            if len > 0 {
+                o.write_all(format!("{len:x}").as_bytes()).await?;
+                o.write_all(EOL).await?;
+                o.write_all(content).await?;
+                o.write_all(EOL).await?;
+            }
+            o.write_all("0".as_bytes()).await?;
+            o.write_all(EOL).await?;
+            o.write_all(EOL).await?;
+            o.flush().await?;
+            return Ok(());
+        }
+        if len > 0 || self.requires_content_length() {
             write_header(o, CONTENT_LENGTH_BYTES, len.to_string().as_bytes()).await?;
         }
         //These could cause partial writes. Should we check the returned number of written bytes?
@@ -107,3 +210,212 @@ pub trait Message: Sized {
         Ok(())
     }
 }
+
+fn is_chunked(headers: &HashMap<String, String>) -> bool {
+    headers
+        .get(TRANSFER_ENCODING)
+        .is_some_and(|value| has_token(value, CHUNKED))
+}
+
+/// Checks if a comma separated header value contains `token`, ignoring case.
+fn has_token(value: &str, token: &str) -> bool {
+    value
+        .split(',')
+        .any(|item| item.trim().eq_ignore_ascii_case(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+    use std::time::Duration;
+
+    use crate::http::{MemIoStreamEx, Request, Response};
+
+    use super::{Message, MAX_HEADERS, MAX_LINE_LENGTH};
+
+    const VALID_MESSAGES: &[&str] = &[
+        "POST / HTTP/1.1\r\nContent-Length: 6\r\n\r\nHello!",
+        "GET /?id=1 HTTP/1.0\r\nConnection: keep-alive\r\n\r\n",
+        "POST /x HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nHel\r\n3;ext=1\r\nlo!\r\n0\r\nTrailer: x\r\n\r\n",
+    ];
+
+    /// A small deterministic generator, so failures can be reproduced.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn mutate(rng: &mut XorShift, input: &[u8]) -> Vec<u8> {
+        const INTERESTING: &[&[u8]] = &[
+            b"\r\n",
+            b"\r",
+            b":",
+            b" ",
+            b"Content-Length: 99999999999\r\n",
+            b"Content-Length: -1\r\n",
+            b"Transfer-Encoding: chunked\r\n",
+            b"ffffffffffffffffff\r\n",
+            b"ffffffffffffffff\r\n",
+            &[0xFF, 0xFE],
+        ];
+        let mut output = input.to_vec();
+        for _ in 0..=rng.below(4) {
+            let at = rng.below(output.len() + 1);
+            match rng.below(4) {
+                0 => output.truncate(at),
+                1 if at < output.len() => output[at] = rng.next() as u8,
+                2 => {
+                    let insert = INTERESTING[rng.below(INTERESTING.len())];
+                    output.splice(at..at, insert.iter().copied());
+                }
+                _ if at < output.len() => {
+                    output.remove(at);
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+
+    async fn read_all<T: Message + Send + Sync>(input: &[u8]) {
+        let mut output = Vec::default();
+        let mut stream = input.mem_io_stream(&mut output);
+        // reading malformed input may fail, but must neither panic nor hang.
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while let Ok(message) = T::read(&mut stream).await {
+                message.write(&mut Vec::new()).await.unwrap();
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fuzz_test() {
+        let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+        for _ in 0..2000 {
+            let valid = VALID_MESSAGES[rng.below(VALID_MESSAGES.len())];
+            let input = mutate(&mut rng, valid.as_bytes());
+            read_all::<Request>(&input).await;
+            read_all::<Response>(&input).await;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn keep_alive_test() {
+        let input = VALID_MESSAGES.concat();
+        let mut output = Vec::default();
+        let mut stream = input.as_bytes().mem_io_stream(&mut output);
+        let first = Request::read(&mut stream).await.unwrap();
+        assert_eq!(from_utf8(&first.content), Ok("Hello!"));
+        assert!(first.keep_alive());
+        let second = Request::read(&mut stream).await.unwrap();
+        assert_eq!(second.url, "/?id=1");
+        assert!(second.keep_alive());
+        let third = Request::read(&mut stream).await.unwrap();
+        assert_eq!(from_utf8(&third.content), Ok("Hello!"));
+        assert_eq!(third.headers["trailer"], "x");
+        assert!(Request::read(&mut stream).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn connection_header_test() {
+        const REQUESTS: &[(&str, bool)] = &[
+            ("GET / HTTP/1.1\r\n\r\n", true),
+            ("GET / HTTP/1.1\r\nConnection: close\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n", true),
+        ];
+        for (request, keep_alive) in REQUESTS {
+            let mut output = Vec::default();
+            let mut stream = request.as_bytes().mem_io_stream(&mut output);
+            let request = Request::read(&mut stream).await.unwrap();
+            assert_eq!(request.keep_alive(), *keep_alive);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn chunked_write_test() {
+        let response = Response::new(
+            200,
+            "OK".to_string(),
+            [("transfer-encoding".to_string(), "chunked".to_string())].into(),
+            "Hello, world!".as_bytes().to_vec(),
+        );
+        let mut v = Vec::default();
+        response.write(&mut v).await.unwrap();
+        const EXPECTED: &str = "\
+            HTTP/1.1 200 OK\r\n\
+            transfer-encoding:chunked\r\n\
+            \r\n\
+            d\r\n\
+            Hello, world!\r\n\
+            0\r\n\
+            \r\n";
+        assert_eq!(from_utf8(&v), Ok(EXPECTED));
+        let mut output = Vec::default();
+        let mut stream = v.as_slice().mem_io_stream(&mut output);
+        assert_eq!(Response::read(&mut stream).await.unwrap(), response);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn header_limits_test() {
+        let long_line = format!(
+            "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+            "a".repeat(MAX_LINE_LENGTH)
+        );
+        let many_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X: a\r\n".repeat(MAX_HEADERS + 1)
+        );
+        let allowed = format!("GET / HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(MAX_HEADERS));
+        let many_trailers = format!(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n{}\r\n0\r\n{}\r\n",
+            "X: a\r\n".repeat(MAX_HEADERS / 2),
+            "Y: a\r\n".repeat(MAX_HEADERS / 2)
+        );
+        let allowed_trailers = format!(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n{}\r\n0\r\n{}\r\n",
+            "X: a\r\n".repeat(MAX_HEADERS / 2 - 1),
+            "Y: a\r\n".repeat(MAX_HEADERS / 2)
+        );
+        for (input, is_ok) in [
+            (long_line, false),
+            (many_headers, false),
+            (allowed, true),
+            (many_trailers, false),
+            (allowed_trailers, true),
+        ] {
+            let mut output = Vec::default();
+            let mut stream = input.as_bytes().mem_io_stream(&mut output);
+            assert_eq!(Request::read(&mut stream).await.is_ok(), is_ok);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn malformed_chunks_test() {
+        const REQUESTS: &[&str] = &[
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nz\r\nHel\r\n0\r\n\r\n",
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nHello\r\n0\r\n\r\n",
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nHel",
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffff\r\nHel\r\n",
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nHel\r\nffffffffffffffff\r\nlo!\r\n",
+            "POST / HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\nHel",
+        ];
+        for request in REQUESTS {
+            let mut output = Vec::default();
+            let mut stream = request.as_bytes().mem_io_stream(&mut output);
+            assert!(Request::read(&mut stream).await.is_err());
+        }
+    }
+}
@@ -14,7 +14,7 @@ pub use mem_io_stream::{MemIoStream, MemIoStreamEx};
 pub use message::Message;
 pub use method::Method;
 pub use request::Request;
-pub use response::Response;
+pub use response::{Response, ResponseEx};
 pub use url::QueryEx;
 
 #[async_trait]
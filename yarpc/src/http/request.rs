@@ -59,6 +59,10 @@ impl Message for Request {
         .to_vec()
     }
 
+    fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
     fn headers(&self) -> &HashMap<String, String> {
         &self.headers
     }
@@ -39,7 +39,7 @@ impl Message for Response {
         let mut i = first_line.into_iter();
         let protocol = i.next().to_io_result()?;
         let code = i.next().to_io_result()?.parse().to_io_result()?;
-        let phrase = i.next().unwrap_or(String::default());
+        let phrase = i.next().unwrap_or_default();
         Ok(Response {
             protocol,
             code,
@@ -58,6 +58,10 @@ impl Message for Response {
         .to_vec()
     }
 
+    fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
     fn headers(&self) -> &HashMap<String, String> {
         &self.headers
     }
@@ -65,6 +69,10 @@ impl Message for Response {
     fn content(&self) -> &Vec<u8> {
         &self.content
     }
+
+    fn requires_content_length(&self) -> bool {
+        true
+    }
 }
 
 pub trait ResponseEx: Read {}
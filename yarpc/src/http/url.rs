@@ -2,9 +2,14 @@ use std::collections::HashMap;
 
 pub trait QueryEx {
     fn url_query(&self) -> HashMap<&str, &str>;
+    fn url_path(&self) -> &str;
 }
 
 impl QueryEx for str {
+    fn url_path(&self) -> &str {
+        self.split_once('?').map_or(self, |(left, _)| left)
+    }
+
     fn url_query(&self) -> HashMap<&str, &str> {
         match self.split_once('?') {
             Some((_, right)) if !right.is_empty() => right
@@ -53,4 +58,13 @@ mod tests {
         assert!(x.get("azx").unwrap().is_empty());
         assert_eq!(x.get("id").unwrap().to_owned(), "hello");
     }
+
+    #[test]
+    fn path_test() {
+        assert_eq!("".url_path(), "");
+        assert_eq!("/".url_path(), "/");
+        assert_eq!("/?id=1".url_path(), "/");
+        assert_eq!("/testnet?id=1&x".url_path(), "/testnet");
+        assert_eq!("/testnet/".url_path(), "/testnet/");
+    }
 }